@@ -0,0 +1,14 @@
+CREATE TABLE ibc_path
+(
+    id         BIGSERIAL PRIMARY KEY,
+    network    TEXT        NOT NULL,
+    chain_1    TEXT        NOT NULL,
+    chain_2    TEXT        NOT NULL,
+    ibc_data   jsonb       NOT NULL,
+    commit     TEXT        NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX ibc_path_chain_1_idx ON ibc_path (chain_1);
+CREATE INDEX ibc_path_chain_2_idx ON ibc_path (chain_2);
+CREATE INDEX ibc_path_created_at_idx ON ibc_path (created_at DESC);
+CREATE UNIQUE INDEX ibc_path_network_chains_commit_idx ON ibc_path (network, chain_1, chain_2, commit);
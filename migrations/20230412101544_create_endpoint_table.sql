@@ -0,0 +1,18 @@
+CREATE TABLE endpoint
+(
+    id          BIGSERIAL PRIMARY KEY,
+    kind        TEXT        NOT NULL, -- 'rpc', 'rest', or 'grpc'
+    address     TEXT        NOT NULL,
+    provider    TEXT,
+    is_alive    BOOLEAN     NOT NULL DEFAULT TRUE,
+    chain_id_fk BIGINT      NOT NULL REFERENCES chain (id) ON DELETE CASCADE,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX endpoint_created_at_idx ON endpoint (created_at DESC);
+CREATE UNIQUE INDEX endpoint_chain_id_fk_address_kind_idx ON endpoint (chain_id_fk, address, kind);
+
+CREATE TRIGGER endpoint_set_updated_at
+    BEFORE UPDATE ON endpoint
+    FOR EACH ROW
+EXECUTE PROCEDURE set_updated_at();
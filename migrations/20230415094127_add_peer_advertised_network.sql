@@ -0,0 +1,2 @@
+-- The chain id a peer reported during the p2p handshake. NULL until a handshake succeeds.
+ALTER TABLE peer ADD COLUMN advertised_network TEXT;
@@ -0,0 +1,16 @@
+-- History of liveness checks. Keyed by the chain and the peer's type and address instead of peer id
+-- because peer rows are recreated when a chain's content changes.
+CREATE TABLE peer_check
+(
+    id         BIGSERIAL PRIMARY KEY,
+    network    TEXT        NOT NULL,
+    chain_name TEXT        NOT NULL,
+    peer_type  TEXT        NOT NULL,
+    address    TEXT        NOT NULL,
+    success    BOOLEAN     NOT NULL,
+    error_kind TEXT,
+    latency_ms INTEGER,
+    checked_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX peer_check_peer_checked_at_idx ON peer_check (network, chain_name, peer_type, address, checked_at DESC);
+CREATE INDEX peer_check_checked_at_idx ON peer_check (checked_at);
@@ -0,0 +1,2 @@
+-- sha256 of chain.json and assetlist.json. Lets hydrate reuse rows for chains that did not change.
+ALTER TABLE chain ADD COLUMN content_hash TEXT;
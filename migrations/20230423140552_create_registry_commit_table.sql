@@ -0,0 +1,27 @@
+-- Every hydrated commit. A chain whose files did not change keeps its row across commits, so
+-- chain rows alone cannot tell which commits existed.
+CREATE TABLE registry_commit
+(
+    commit     TEXT PRIMARY KEY,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX registry_commit_created_at_idx ON registry_commit (created_at DESC);
+
+INSERT INTO registry_commit (commit, created_at)
+SELECT commit, MAX(created_at)
+FROM chain
+GROUP BY commit;
+
+-- The chain rows that make up each hydrated commit. A reused row is mapped to every commit it is
+-- part of, and chain.commit stays the first commit with its content.
+CREATE TABLE chain_commit
+(
+    commit      TEXT   NOT NULL REFERENCES registry_commit (commit) ON DELETE CASCADE,
+    chain_id_fk BIGINT NOT NULL REFERENCES chain (id) ON DELETE CASCADE,
+    PRIMARY KEY (commit, chain_id_fk)
+);
+CREATE INDEX chain_commit_chain_id_fk_idx ON chain_commit (chain_id_fk);
+
+INSERT INTO chain_commit (commit, chain_id_fk)
+SELECT commit, id
+FROM chain;
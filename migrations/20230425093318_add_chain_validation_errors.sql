@@ -0,0 +1,3 @@
+-- Errors from validating chain.json and assetlist.json against the registry's JSON schemas. Stored per
+-- commit because a reused chain row is validated again against each commit's schemas.
+ALTER TABLE chain_commit ADD COLUMN validation_errors jsonb NOT NULL DEFAULT '[]';
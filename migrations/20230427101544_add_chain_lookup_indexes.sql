@@ -0,0 +1,6 @@
+-- Lets clients find chains by what they know instead of the registry directory name.
+CREATE INDEX chain_chain_id_idx ON chain ((chain_data ->> 'chain_id'));
+CREATE INDEX chain_bech32_prefix_idx ON chain ((chain_data ->> 'bech32_prefix'));
+CREATE INDEX chain_slip44_idx ON chain ((chain_data -> 'slip44'));
+-- Containment queries on assets[].base and assets[].denom_units[].denom.
+CREATE INDEX chain_asset_data_idx ON chain USING GIN (asset_data jsonb_path_ops);
@@ -0,0 +1,53 @@
+-- One row per asset in a chain's assetlist.json so assets can be searched across chains.
+CREATE TABLE asset
+(
+    id           BIGSERIAL PRIMARY KEY,
+    chain_id_fk  BIGINT NOT NULL REFERENCES chain (id) ON DELETE CASCADE,
+    base         TEXT   NOT NULL,
+    symbol       TEXT,
+    coingecko_id TEXT,
+    type_asset   TEXT, -- e.g. 'sdk.coin', 'cw20', 'ics20'
+    data         JSONB  NOT NULL
+);
+CREATE UNIQUE INDEX asset_chain_id_fk_base_idx ON asset (chain_id_fk, base);
+CREATE INDEX asset_base_idx ON asset (base);
+CREATE INDEX asset_symbol_idx ON asset (lower(symbol));
+CREATE INDEX asset_coingecko_id_idx ON asset (coingecko_id);
+CREATE INDEX asset_type_asset_idx ON asset (type_asset);
+
+-- Every hop of an asset's traces, so multi-hop assets can be found by any chain they passed through.
+CREATE TABLE asset_trace
+(
+    asset_id_fk BIGINT NOT NULL REFERENCES asset (id) ON DELETE CASCADE,
+    position    INT    NOT NULL, -- index in the asset's traces, oldest hop first
+    chain_name  TEXT   NOT NULL, -- counterparty chain of the trace
+    base_denom  TEXT,            -- counterparty denom of the trace
+    PRIMARY KEY (asset_id_fk, position)
+);
+CREATE INDEX asset_trace_chain_name_base_denom_idx ON asset_trace (chain_name, base_denom);
+
+INSERT INTO asset (chain_id_fk, base, symbol, coingecko_id, type_asset, data)
+SELECT chain.id,
+       a ->> 'base',
+       a ->> 'symbol',
+       a ->> 'coingecko_id',
+       a ->> 'type_asset',
+       a
+FROM chain,
+     jsonb_array_elements(CASE
+                              WHEN jsonb_typeof(chain.asset_data -> 'assets') = 'array'
+                                  THEN chain.asset_data -> 'assets'
+                              ELSE '[]' END) a
+WHERE a ->> 'base' IS NOT NULL
+ON CONFLICT (chain_id_fk, base) DO NOTHING;
+
+INSERT INTO asset_trace (asset_id_fk, position, chain_name, base_denom)
+SELECT asset.id,
+       t.position - 1,
+       t.trace -> 'counterparty' ->> 'chain_name',
+       t.trace -> 'counterparty' ->> 'base_denom'
+FROM asset,
+     jsonb_array_elements(CASE
+                              WHEN jsonb_typeof(asset.data -> 'traces') = 'array' THEN asset.data -> 'traces'
+                              ELSE '[]' END) WITH ORDINALITY t(trace, position)
+WHERE t.trace -> 'counterparty' ->> 'chain_name' IS NOT NULL;
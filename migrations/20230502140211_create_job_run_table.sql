@@ -0,0 +1,12 @@
+-- One row per hydrate or liveness run, for metrics.
+CREATE TABLE job_run
+(
+    id          BIGSERIAL PRIMARY KEY,
+    job         TEXT        NOT NULL, -- 'hydrate' or 'liveness'
+    started_at  TIMESTAMPTZ NOT NULL,
+    finished_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    succeeded   BOOLEAN     NOT NULL,
+    -- Chains inserted by hydrate, or peers and endpoints checked by liveness.
+    items       BIGINT      NOT NULL DEFAULT 0
+);
+CREATE INDEX job_run_job_finished_at_idx ON job_run (job, finished_at DESC);
@@ -0,0 +1,2 @@
+-- 'registry' for peers copied from chain.json, 'discovered' for peers found by the crawler.
+ALTER TABLE peer ADD COLUMN source TEXT NOT NULL DEFAULT 'registry';
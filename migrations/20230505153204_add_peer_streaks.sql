@@ -0,0 +1,4 @@
+-- Consecutive failed and successful liveness checks. is_alive only changes once a streak reaches
+-- the liveness job's threshold.
+ALTER TABLE peer ADD COLUMN failure_streak INT NOT NULL DEFAULT 0;
+ALTER TABLE peer ADD COLUMN success_streak INT NOT NULL DEFAULT 0;
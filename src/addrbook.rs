@@ -0,0 +1,196 @@
+//! Builds CometBFT address books, the config/addrbook.json a node loads known peers from on start.
+//!
+//! CometBFT only picks addresses that are in a bucket, and its ip field must be an IP, so hostnames
+//! are resolved and each address is put in an "old" bucket, the type for addresses that were
+//! connected to successfully.
+
+use futures::future::join_all;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+// From CometBFT's p2p/pex/params.go
+const BUCKET_TYPE_OLD: u8 = 2;
+const OLD_BUCKET_COUNT: u64 = 64;
+
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct AddrBook {
+    /// Hex key CometBFT uses to hash addresses into buckets.
+    #[schema(example = "4d8e5f3b2a1c0d9e8f7a6b5c")]
+    pub key: String,
+    pub addrs: Vec<KnownAddress>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct KnownAddress {
+    pub addr: NetAddress,
+    /// Where the address was learned from. Always the address itself.
+    pub src: NetAddress,
+    pub buckets: Vec<u64>,
+    pub attempts: i32,
+    pub bucket_type: u8,
+    pub last_attempt: chrono::DateTime<chrono::Utc>,
+    /// 0001-01-01T00:00:00Z if the peer never passed a liveness check.
+    pub last_success: chrono::DateTime<chrono::Utc>,
+    pub last_ban_time: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct NetAddress {
+    /// Node id
+    pub id: String,
+    #[schema(value_type = String, example = "203.0.113.7")]
+    pub ip: IpAddr,
+    pub port: u16,
+}
+
+/// A peer to put in the book.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    /// id@host:port
+    pub address: String,
+    pub last_attempt: chrono::DateTime<chrono::Utc>,
+    pub last_success: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Splits id@host:port. IPv6 hosts are bracketed, e.g. id@[::1]:26656.
+pub fn parse_address(address: &str) -> Option<(&str, &str, u16)> {
+    let (id, host_port) = address.trim().split_once('@')?;
+    let (host, port) = host_port.rsplit_once(':')?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if id.is_empty() || host.is_empty() {
+        return None;
+    }
+    Some((id, host, port.parse().ok()?))
+}
+
+/// Builds a book for the chain. name only seeds the key, so the same chain always gets the same
+/// key. Addresses that cannot be parsed or resolved within timeout are left out, as are
+/// duplicates.
+pub async fn build(name: &str, entries: &[Entry], timeout: Duration) -> AddrBook {
+    let key = hex::encode(&Sha256::digest(name.as_bytes())[..12]);
+
+    let resolved = join_all(entries.iter().map(|entry| async move {
+        let (id, host, port) = parse_address(&entry.address)?;
+        let ip = resolve(host, port, timeout).await?;
+        Some((entry, id, ip, port))
+    }))
+    .await;
+
+    let mut addrs: Vec<KnownAddress> = vec![];
+    for (entry, id, ip, port) in resolved.into_iter().flatten() {
+        let addr = NetAddress {
+            id: id.to_string(),
+            ip,
+            port,
+        };
+        if addrs.iter().any(|known| known.addr == addr) {
+            continue;
+        }
+        addrs.push(KnownAddress {
+            buckets: vec![old_bucket(&key, &addr)],
+            src: addr.clone(),
+            addr,
+            attempts: 0,
+            bucket_type: BUCKET_TYPE_OLD,
+            last_attempt: entry.last_attempt,
+            last_success: entry.last_success.unwrap_or_else(go_zero_time),
+            last_ban_time: go_zero_time(),
+        });
+    }
+    AddrBook { key, addrs }
+}
+
+// 0001-01-01T00:00:00Z, which CometBFT uses for never.
+fn go_zero_time() -> chrono::DateTime<chrono::Utc> {
+    "0001-01-01T00:00:00Z".parse().unwrap()
+}
+
+async fn resolve(host: &str, port: u16, timeout: Duration) -> Option<IpAddr> {
+    if let Ok(ip) = host.parse() {
+        return Some(ip);
+    }
+    match tokio::time::timeout(timeout, tokio::net::lookup_host((host, port))).await {
+        Ok(Ok(mut addrs)) => addrs.next().map(|addr| addr.ip()),
+        Ok(Err(err)) => {
+            tracing::debug!("Failed to resolve {}: {}", host, err);
+            None
+        }
+        Err(_) => {
+            tracing::debug!("Timed out resolving {}", host);
+            None
+        }
+    }
+}
+
+// CometBFT does not check that the bucket matches its own hashing when loading a book, so any
+// stable bucket in range works.
+fn old_bucket(key: &str, addr: &NetAddress) -> u64 {
+    let hash = Sha256::digest(format!("{}{}@{}:{}", key, addr.id, addr.ip, addr.port));
+    u64::from_be_bytes(hash[..8].try_into().unwrap()) % OLD_BUCKET_COUNT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_address() {
+        assert_eq!(
+            parse_address("abc123@seed.example.com:26656"),
+            Some(("abc123", "seed.example.com", 26656))
+        );
+        assert_eq!(
+            parse_address("abc123@[2001:db8::1]:26656"),
+            Some(("abc123", "2001:db8::1", 26656))
+        );
+        assert_eq!(parse_address("seed.example.com:26656"), None);
+        assert_eq!(parse_address("abc123@seed.example.com"), None);
+        assert_eq!(parse_address("abc123@seed.example.com:port"), None);
+        assert_eq!(parse_address("@seed.example.com:26656"), None);
+    }
+
+    #[tokio::test]
+    async fn test_build() {
+        let checked_at: chrono::DateTime<chrono::Utc> = "2023-04-20T12:00:00Z".parse().unwrap();
+        let entry = |address: &str, last_success| Entry {
+            address: address.to_string(),
+            last_attempt: checked_at,
+            last_success,
+        };
+        let entries = vec![
+            entry("abc@203.0.113.7:26656", Some(checked_at)),
+            entry("abc@203.0.113.7:26656", Some(checked_at)),
+            entry("def@[2001:db8::1]:26656", None),
+            entry("not an address", None),
+        ];
+
+        let book = build("mainnet/cosmoshub", &entries, Duration::from_secs(1)).await;
+        assert_eq!(book.key.len(), 24);
+        assert_eq!(
+            book.key,
+            build("mainnet/cosmoshub", &[], Duration::from_secs(1))
+                .await
+                .key
+        );
+        assert_eq!(book.addrs.len(), 2);
+        assert!(book
+            .addrs
+            .iter()
+            .all(|a| a.buckets.len() == 1 && a.buckets[0] < OLD_BUCKET_COUNT));
+
+        let json = serde_json::to_value(&book).unwrap();
+        let first = &json["addrs"][0];
+        assert_eq!(
+            first["addr"],
+            serde_json::json!({"id": "abc", "ip": "203.0.113.7", "port": 26656})
+        );
+        assert_eq!(first["src"], first["addr"]);
+        assert_eq!(first["bucket_type"], 2);
+        assert_eq!(first["last_success"], "2023-04-20T12:00:00Z");
+        assert_eq!(json["addrs"][1]["addr"]["ip"], "2001:db8::1");
+        assert_eq!(json["addrs"][1]["last_success"], "0001-01-01T00:00:00Z");
+    }
+}
@@ -0,0 +1,135 @@
+use crate::api::{from_db_error, internal_error, APIError, Meta};
+use crate::db::{asset, commit};
+use crate::registry::Asset;
+use axum::{extract::Path, extract::Query, extract::State, Json};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPool;
+use utoipa::ToSchema;
+
+const DEFAULT_LIMIT: i64 = 100;
+const MAX_LIMIT: i64 = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct AssetParams {
+    symbol: Option<String>,
+    base: Option<String>,
+    coingecko_id: Option<String>,
+    type_asset: Option<String>,
+    trace_chain_name: Option<String>,
+    trace_base_denom: Option<String>,
+    limit: Option<i64>,
+    cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AssetSearchList {
+    meta: Meta,
+    result: Vec<AssetSearchItem>,
+    /// Pass as cursor to get the next page. Absent on the last page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AssetSearchItem {
+    #[schema(example = "osmosis")]
+    chain_name: String,
+    #[schema(example = "/v1/mainnet/osmosis/assetlist")]
+    path: String,
+    asset: Asset,
+}
+
+/// Search assets across all chains.
+///
+/// Finds which chains list an asset in their assetlist.json. Filters are combined. symbol is case
+/// insensitive; trace_chain_name and trace_base_denom match any hop of an asset's traces, e.g.
+/// trace_chain_name=cosmoshub&trace_base_denom=uatom finds ATOM on other chains, including assets that
+/// reached them through another chain. Only the latest commit is searched.
+#[utoipa::path(
+get,
+path = "/v1/{network}/assets",
+responses(
+(status = 200, description = "Search completed successfully; result may be empty", body = AssetSearchList),
+(status = 400, description = "Invalid limit or cursor"),
+(status = 404, description = "No commits have been fetched yet"),
+),
+params(
+("network" = String, Path, description = "mainnet or testnet"),
+("symbol" = Option<String>, Query, description = "e.g. ATOM"),
+("base" = Option<String>, Query, description = "Base denom, e.g. uatom or ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2"),
+("coingecko_id" = Option<String>, Query, description = "e.g. cosmos"),
+("type_asset" = Option<String>, Query, description = "e.g. sdk.coin, cw20, or ics20"),
+("trace_chain_name" = Option<String>, Query, description = "Chain in any of the asset's traces, e.g. cosmoshub"),
+("trace_base_denom" = Option<String>, Query, description = "Denom on that chain, e.g. uatom"),
+("limit" = Option<i64>, Query, description = "Page size, 1 to 500. Defaults to 100"),
+("cursor" = Option<String>, Query, description = "next_cursor from the previous page"),
+),
+tag = "Assets",
+)]
+pub async fn list_assets(
+    State(pool): State<PgPool>,
+    Path(network): Path<String>,
+    Query(params): Query<AssetParams>,
+) -> Result<Json<AssetSearchList>, APIError> {
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(APIError::BadRequest(format!(
+            "limit must be between 1 and {}",
+            MAX_LIMIT
+        )));
+    }
+    let after = match &params.cursor {
+        Some(cursor) => Some(
+            cursor
+                .parse::<i64>()
+                .map_err(|_| APIError::BadRequest("invalid cursor".to_string()))?,
+        ),
+        None => None,
+    };
+
+    let filter = asset::AssetFilter {
+        network: network.clone(),
+        symbol: params.symbol,
+        base: params.base,
+        coingecko_id: params.coingecko_id,
+        type_asset: params.type_asset,
+        trace_chain_name: params.trace_chain_name,
+        trace_base_denom: params.trace_base_denom,
+    };
+
+    let mut conn = pool.acquire().await.map_err(internal_error)?;
+    let latest = commit::find_commit_at(&mut conn, chrono::Utc::now())
+        .await
+        .map_err(from_db_error)?;
+    // One extra row tells whether there is a next page.
+    let mut assets = asset::list_assets(&mut conn, &filter, after, limit + 1)
+        .await
+        .map_err(from_db_error)?;
+
+    let next_cursor = if assets.len() as i64 > limit {
+        assets.truncate(limit as usize);
+        assets.last().map(|a| a.id.to_string())
+    } else {
+        None
+    };
+
+    let result = assets
+        .into_iter()
+        .map(|a| {
+            Ok(AssetSearchItem {
+                path: format!("/v1/{}/{}/assetlist", network, a.chain_name),
+                chain_name: a.chain_name,
+                asset: serde_json::from_value(a.data).map_err(internal_error)?,
+            })
+        })
+        .collect::<Result<Vec<_>, APIError>>()?;
+
+    Ok(Json(AssetSearchList {
+        meta: Meta {
+            commit: latest.commit,
+            updated_at: latest.created_at,
+        },
+        result,
+        next_cursor,
+    }))
+}
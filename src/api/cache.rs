@@ -0,0 +1,247 @@
+use crate::api::response_cache::CachedResponse;
+use axum::{
+    async_trait,
+    body::Full,
+    extract::{FromRequestParts, MatchedPath, State},
+    http::{header, request::Parts, HeaderMap, HeaderValue, Method, Request, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+
+/// Cache-Control values by route, e.g. /v1/:network/:chain_name/peers.
+#[derive(Debug, Clone)]
+pub struct CachePolicy {
+    default: HeaderValue,
+    routes: HashMap<String, HeaderValue>,
+}
+
+impl CachePolicy {
+    /// Parses route policies formatted as ROUTE=POLICY.
+    pub fn new(default: &str, routes: &[String]) -> anyhow::Result<CachePolicy> {
+        let routes = routes
+            .iter()
+            .map(|route| match route.split_once('=') {
+                Some((path, policy)) => Ok((
+                    path.trim().to_string(),
+                    HeaderValue::from_str(policy.trim())?,
+                )),
+                None => anyhow::bail!("route cache control {:?} is not ROUTE=POLICY", route),
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(CachePolicy {
+            default: HeaderValue::from_str(default)?,
+            routes,
+        })
+    }
+
+    fn for_route(&self, route: &str) -> &HeaderValue {
+        self.routes.get(route).unwrap_or(&self.default)
+    }
+}
+
+/// Middleware that adds Cache-Control to successful GET responses that do not set their own.
+pub async fn cache_control<B>(
+    State(policy): State<Arc<CachePolicy>>,
+    matched: Option<MatchedPath>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let cacheable = request.method() == Method::GET;
+    let mut response = next.run(request).await;
+    let status = response.status();
+    if cacheable && (status == StatusCode::OK || status == StatusCode::NOT_MODIFIED) {
+        if let Some(matched) = matched {
+            let policy = policy.for_route(matched.as_str()).clone();
+            response
+                .headers_mut()
+                .entry(header::CACHE_CONTROL)
+                .or_insert(policy);
+        }
+    }
+    response
+}
+
+/// A conditional GET. Handlers pass what their response was built from and get a 304 when the
+/// client's copy is current.
+#[derive(Debug, Clone)]
+pub struct Conditional {
+    uri: Uri,
+    if_none_match: Option<String>,
+    if_modified_since: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Conditional {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Conditional::new(parts.uri.clone(), &parts.headers))
+    }
+}
+
+impl Conditional {
+    pub(crate) fn new(uri: Uri, headers: &HeaderMap) -> Conditional {
+        let header = |name| headers.get(name).and_then(|v| v.to_str().ok());
+        Conditional {
+            uri,
+            if_none_match: header(header::IF_NONE_MATCH).map(str::to_string),
+            // Unparseable dates are ignored as RFC 9110 requires.
+            if_modified_since: header(header::IF_MODIFIED_SINCE)
+                .and_then(|v| chrono::DateTime::parse_from_rfc2822(v).ok())
+                .map(|v| v.with_timezone(&chrono::Utc)),
+        }
+    }
+
+    /// The ETag covers the request URI and the versions of the data behind the response, such as
+    /// the commit and updated_at.
+    fn etag(&self, versions: &[&str]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.uri.to_string().as_bytes());
+        for version in versions {
+            hasher.update([0]);
+            hasher.update(version.as_bytes());
+        }
+        format!("\"{}\"", &hex::encode(hasher.finalize())[..32])
+    }
+
+    fn is_fresh(&self, etag: &str, last_modified: chrono::DateTime<chrono::Utc>) -> bool {
+        // If-Modified-Since is only used without If-None-Match.
+        match (&self.if_none_match, self.if_modified_since) {
+            (Some(tags), _) => tags
+                .split(',')
+                .map(|tag| tag.trim().trim_start_matches("W/"))
+                .any(|tag| tag == "*" || tag == etag),
+            // HTTP dates have second precision.
+            (None, Some(since)) => last_modified.timestamp() <= since.timestamp(),
+            (None, None) => false,
+        }
+    }
+
+    /// Adds ETag and Last-Modified to the response, or returns 304 Not Modified instead.
+    pub fn respond(
+        &self,
+        versions: &[&str],
+        last_modified: chrono::DateTime<chrono::Utc>,
+        body: impl IntoResponse,
+    ) -> Response {
+        let etag = self.etag(versions);
+        let headers = [
+            (header::ETAG, etag.clone()),
+            (
+                header::LAST_MODIFIED,
+                last_modified
+                    .format("%a, %d %b %Y %H:%M:%S GMT")
+                    .to_string(),
+            ),
+        ];
+        if self.is_fresh(&etag, last_modified) {
+            return (StatusCode::NOT_MODIFIED, headers).into_response();
+        }
+        (headers, body).into_response()
+    }
+
+    /// Returns the cached response, or 304 Not Modified if its ETag or Last-Modified is current.
+    pub(crate) fn respond_cached(&self, cached: CachedResponse) -> Response {
+        let header = |name| cached.headers.get(name).and_then(|v| v.to_str().ok());
+        let last_modified = header(header::LAST_MODIFIED)
+            .and_then(|v| chrono::DateTime::parse_from_rfc2822(v).ok())
+            .map(|v| v.with_timezone(&chrono::Utc));
+        if let (Some(etag), Some(last_modified)) = (header(header::ETAG), last_modified) {
+            if self.is_fresh(etag, last_modified) {
+                let mut response = StatusCode::NOT_MODIFIED.into_response();
+                for name in [header::ETAG, header::LAST_MODIFIED] {
+                    if let Some(value) = cached.headers.get(&name) {
+                        response.headers_mut().insert(name, value.clone());
+                    }
+                }
+                return response;
+            }
+        }
+        let mut response = Full::from(cached.body).into_response();
+        *response.headers_mut() = cached.headers;
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conditional(headers: &[(header::HeaderName, &str)]) -> Conditional {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.insert(name, HeaderValue::from_str(value).unwrap());
+        }
+        Conditional::new(Uri::from_static("/v1/mainnet/cosmoshub"), &map)
+    }
+
+    #[test]
+    fn test_conditional() {
+        let updated_at = "2023-04-20T12:00:00.5Z".parse().unwrap();
+
+        let response = conditional(&[]).respond(&["abc123"], updated_at, "body");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::LAST_MODIFIED],
+            "Thu, 20 Apr 2023 12:00:00 GMT"
+        );
+        let etag = response.headers()[header::ETAG]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let response =
+            conditional(&[(header::IF_NONE_MATCH, &etag)]).respond(&["abc123"], updated_at, "body");
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag.as_str());
+
+        let weak = format!("\"other\", W/{}", etag);
+        let response =
+            conditional(&[(header::IF_NONE_MATCH, &weak)]).respond(&["abc123"], updated_at, "body");
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        // A new commit changes the ETag.
+        let response =
+            conditional(&[(header::IF_NONE_MATCH, &etag)]).respond(&["def456"], updated_at, "body");
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = conditional(&[(header::IF_MODIFIED_SINCE, "Thu, 20 Apr 2023 12:00:00 GMT")])
+            .respond(&["abc123"], updated_at, "body");
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        let response = conditional(&[(header::IF_MODIFIED_SINCE, "Thu, 20 Apr 2023 11:59:59 GMT")])
+            .respond(&["abc123"], updated_at, "body");
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // If-None-Match wins over If-Modified-Since.
+        let response = conditional(&[
+            (header::IF_NONE_MATCH, "\"other\""),
+            (header::IF_MODIFIED_SINCE, "Thu, 20 Apr 2023 12:00:00 GMT"),
+        ])
+        .respond(&["abc123"], updated_at, "body");
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_cache_policy() {
+        let policy = CachePolicy::new(
+            "public, max-age=300",
+            &["/v1/:network/:chain_name/peers=public, max-age=30".to_string()],
+        )
+        .unwrap();
+        assert_eq!(
+            policy.for_route("/v1/:network/:chain_name/peers"),
+            "public, max-age=30"
+        );
+        assert_eq!(
+            policy.for_route("/v1/:network/chains"),
+            "public, max-age=300"
+        );
+
+        assert!(CachePolicy::new("public", &["/v1/commits".to_string()]).is_err());
+    }
+}
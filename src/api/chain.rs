@@ -1,135 +1,639 @@
-use crate::api::{from_db_error, internal_error, APIError, APIResponse, Meta};
-use crate::db::chain;
-use axum::{extract::Path, extract::State, Json};
-use serde::Serialize;
-use sqlx::postgres::PgPool;
+use crate::api::cache::Conditional;
+use crate::api::{from_db_error, internal_error, APIError, Meta};
+use crate::db::{chain, commit};
+use crate::registry::{AssetList, ChainInfo};
+use crate::validation::ValidationError;
+use axum::{extract::Path, extract::Query, extract::State, response::Response, Json};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::{PgConnection, PgPool};
 use utoipa::ToSchema;
 
+/// Selects a retained commit instead of the latest.
+#[derive(Debug, Deserialize)]
+pub struct HistoryParams {
+    commit: Option<String>,
+    at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl HistoryParams {
+    pub(crate) async fn find_commit(
+        &self,
+        conn: &mut PgConnection,
+    ) -> Result<Option<commit::RegistryCommit>, APIError> {
+        match (&self.commit, self.at) {
+            (Some(_), Some(_)) => Err(APIError::BadRequest(
+                "commit and at cannot be used together".to_string(),
+            )),
+            (Some(sha), None) => commit::find_commit(conn, sha)
+                .await
+                .map(Some)
+                .map_err(from_db_error),
+            (None, Some(at)) => commit::find_commit_at(conn, at)
+                .await
+                .map(Some)
+                .map_err(from_db_error),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+async fn find_chain(
+    pool: &PgPool,
+    network: &str,
+    chain_name: &str,
+    params: &HistoryParams,
+) -> Result<chain::Chain, APIError> {
+    let mut conn = pool.acquire().await.map_err(internal_error)?;
+    let found = match params.find_commit(&mut conn).await? {
+        Some(commit) => chain::find_chain_at(&mut conn, network, chain_name, &commit).await,
+        None => chain::find_chain(&mut conn, network, chain_name).await,
+    };
+    found.map_err(from_db_error)
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChainInfoResponse {
+    meta: Meta,
+    result: ChainInfo,
+}
+
 /// Get chain's data.
 ///
 /// Fetches all metadata for a chain, such as the binary, bech32 prefix, genesis file, peers, rpc endpoints, etc.
-/// Currently, this OpenAPI spec does not include a schema because it may change without warning.
-/// The response should match the schema at: https://github.com/cosmos/chain-registry/blob/master/chain.schema.json.
+/// The schema covers the commonly used fields of https://github.com/cosmos/chain-registry/blob/master/chain.schema.json.
+/// Fields it does not cover are still returned as is.
 #[utoipa::path(
 get,
 path = "/v1/{network}/{chain_name}",
 responses(
-(status = 200, description = "Chain found successfully"),
-(status = 404, description = "Network or chain does not exist"),
+(status = 200, description = "Chain found successfully", body = ChainInfoResponse),
+(status = 304, description = "Chain has not changed since If-None-Match or If-Modified-Since"),
+(status = 400, description = "Both commit and at were given"),
+(status = 404, description = "Network, chain, or commit does not exist"),
 ),
 params(
 ("network" = String, Path, description = "mainnet or testnet"),
-("chain_name" = String, Path, description = "Chain name, e.g. cosmoshub")
+("chain_name" = String, Path, description = "Chain name, e.g. cosmoshub"),
+("commit" = Option<String>, Query, description = "Return the chain as of this retained commit. See /v1/commits"),
+("at" = Option<String>, Query, description = "Return the chain as of this RFC 3339 time, e.g. 2023-04-20T12:00:00Z"),
 ),
 tag = "Chains",
 )]
 pub async fn get_chain_data(
     State(pool): State<PgPool>,
+    conditional: Conditional,
     Path((network, chain_name)): Path<(String, String)>,
-) -> Result<Json<APIResponse<serde_json::Value>>, APIError> {
-    let mut conn = pool.acquire().await.map_err(internal_error)?;
-    let chain = chain::find_chain(&mut conn, network.as_str(), chain_name.as_str())
-        .await
-        .map_err(from_db_error)?;
+    Query(params): Query<HistoryParams>,
+) -> Result<Response, APIError> {
+    let chain = find_chain(&pool, network.as_str(), chain_name.as_str(), &params).await?;
+    let version = chain.version().to_string();
 
-    let resp = APIResponse {
+    let resp = ChainInfoResponse {
         meta: Meta {
-            commit: chain.commit,
+            commit: chain.commit.clone(),
             updated_at: chain.created_at,
         },
-        result: chain.chain_data,
+        result: serde_json::from_value(chain.chain_data).map_err(internal_error)?,
     };
 
-    Ok(Json(resp))
+    // meta.commit changes with every commit, even when the content does not.
+    Ok(conditional.respond(
+        &[&version, &chain.commit, &chain_name],
+        chain.created_at,
+        Json(resp),
+    ))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AssetListResponse {
+    meta: Meta,
+    result: AssetList,
 }
 
 /// Get chain's assetlist.
 ///
 /// Asset lists allow frontends and other UIs to fetch metadata associated with Cosmos SDK denoms, especially for assets sent over IBC.
-/// Currently, this OpenAPI spec does not include a schema because it may change without warning.
-/// The response should match the schema at: https://github.com/cosmos/chain-registry/blob/master/assetlist.schema.json
+/// The schema covers the commonly used fields of https://github.com/cosmos/chain-registry/blob/master/assetlist.schema.json.
+/// Fields it does not cover are still returned as is.
 #[utoipa::path(
 get,
 path = "/v1/{network}/{chain_name}/assetlist",
 responses(
-(status = 200, description = "Assetlist found successfully"),
-(status = 404, description = "Network, chain, or assetlist does not exist"),
+(status = 200, description = "Assetlist found successfully", body = AssetListResponse),
+(status = 304, description = "Assetlist has not changed since If-None-Match or If-Modified-Since"),
+(status = 400, description = "Both commit and at were given"),
+(status = 404, description = "Network, chain, assetlist, or commit does not exist"),
 ),
 params(
 ("network" = String, Path, description = "mainnet or testnet"),
-("chain_name" = String, Path, description = "Chain name, e.g. cosmoshub")
+("chain_name" = String, Path, description = "Chain name, e.g. cosmoshub"),
+("commit" = Option<String>, Query, description = "Return the chain as of this retained commit. See /v1/commits"),
+("at" = Option<String>, Query, description = "Return the chain as of this RFC 3339 time, e.g. 2023-04-20T12:00:00Z"),
 ),
 tag = "Chains",
 )]
 pub async fn get_chain_asset_list(
     State(pool): State<PgPool>,
+    conditional: Conditional,
     Path((network, chain_name)): Path<(String, String)>,
-) -> Result<Json<APIResponse<serde_json::Value>>, APIError> {
-    let mut conn = pool.acquire().await.map_err(internal_error)?;
-    let chain = chain::find_chain(&mut conn, network.as_str(), chain_name.as_str())
-        .await
-        .map_err(from_db_error)?;
+    Query(params): Query<HistoryParams>,
+) -> Result<Response, APIError> {
+    let chain = find_chain(&pool, network.as_str(), chain_name.as_str(), &params).await?;
+    let version = chain.version().to_string();
 
-    let resp = APIResponse {
+    let resp = AssetListResponse {
         meta: Meta {
-            commit: chain.commit,
+            commit: chain.commit.clone(),
             updated_at: chain.created_at,
         },
-        result: chain.asset_data,
+        result: serde_json::from_value(chain.asset_data).map_err(internal_error)?,
     };
 
-    Ok(Json(resp))
+    // meta.commit changes with every commit, even when the content does not.
+    Ok(conditional.respond(
+        &[&version, &chain.commit, &chain_name],
+        chain.created_at,
+        Json(resp),
+    ))
+}
+
+const MAX_CHAIN_LIMIT: i64 = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct ListParams {
+    status: Option<String>,
+    network_type: Option<String>,
+    key_algos: Option<String>,
+    fields: Option<String>,
+    limit: Option<i64>,
+    cursor: Option<String>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
 pub struct ChainList {
     meta: Meta,
     result: Vec<ChainListItem>,
+    /// Pass as cursor to get the next page. Absent on the last page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
 pub struct ChainListItem {
     name: String,
     path: String,
+    /// The chain.json keys requested with fields. Keys the chain does not have are omitted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    chain_data: Option<serde_json::Map<String, serde_json::Value>>,
 }
 
 /// List chains by network
+///
+/// Without limit, every chain is returned. Use fields to include chain.json keys with each chain,
+/// e.g. fields=chain_id,pretty_name,status,logo_URIs.
 #[utoipa::path(
 get,
 path = "/v1/{network}/chains",
 responses(
 (status = 200, description = "Chains found successfully", body = ChainList),
-(status = 404, description = "Network does not exist"),
+(status = 304, description = "Chains have not changed since If-None-Match or If-Modified-Since"),
+(status = 400, description = "Both commit and at were given, or limit is invalid"),
+(status = 404, description = "Network or commit does not exist"),
 ),
 params(
-("network" = String, Path, description = "mainnet or testnet")
+("network" = String, Path, description = "mainnet or testnet"),
+("commit" = Option<String>, Query, description = "List chains as of this retained commit. See /v1/commits"),
+("at" = Option<String>, Query, description = "List chains as of this RFC 3339 time, e.g. 2023-04-20T12:00:00Z"),
+("status" = Option<String>, Query, description = "live, upcoming, or killed"),
+("network_type" = Option<String>, Query, description = "mainnet, testnet, or devnet"),
+("key_algos" = Option<String>, Query, description = "Only chains supporting this key algorithm, e.g. secp256k1"),
+("fields" = Option<String>, Query, description = "Comma separated chain.json keys to include, e.g. chain_id,pretty_name"),
+("limit" = Option<i64>, Query, description = "Page size, 1 to 500"),
+("cursor" = Option<String>, Query, description = "next_cursor from the previous page"),
 ),
 tag = "Chains",
 )]
 pub async fn list_chains(
     State(pool): State<PgPool>,
+    conditional: Conditional,
     Path(network): Path<String>,
-) -> Result<Json<ChainList>, APIError> {
+    Query(history): Query<HistoryParams>,
+    Query(params): Query<ListParams>,
+) -> Result<Response, APIError> {
+    if let Some(limit) = params.limit {
+        if !(1..=MAX_CHAIN_LIMIT).contains(&limit) {
+            return Err(APIError::BadRequest(format!(
+                "limit must be between 1 and {}",
+                MAX_CHAIN_LIMIT
+            )));
+        }
+    }
+    let filter = chain::ChainFilter {
+        status: params.status,
+        network_type: params.network_type,
+        key_algo: params.key_algos,
+        after: params.cursor,
+        // One extra row tells whether there is a next page.
+        limit: params.limit.map(|limit| limit + 1),
+    };
+    let fields: Option<Vec<&str>> = params
+        .fields
+        .as_deref()
+        .map(|fields| fields.split(',').map(str::trim).collect());
+
     let mut conn = pool.acquire().await.map_err(internal_error)?;
-    let list = chain::list_chains(&mut conn, network.as_str())
-        .await
-        .map_err(from_db_error)?;
+    let mut list = match history.find_commit(&mut conn).await? {
+        Some(commit) => chain::list_chains_at(&mut conn, network.as_str(), &commit, &filter).await,
+        None => chain::list_chains(&mut conn, network.as_str(), &filter).await,
+    }
+    .map_err(from_db_error)?;
+
+    let next_cursor = match params.limit {
+        Some(limit) if list.chains.len() as i64 > limit => {
+            list.chains.truncate(limit as usize);
+            list.chains.last().map(|c| c.name.clone())
+        }
+        _ => None,
+    };
 
     let chain_list = list
-        .names
+        .chains
         .into_iter()
         .map(|chain| ChainListItem {
-            name: chain.clone(),
-            path: format!("/v1/{}/{}", network, chain),
+            path: format!("/v1/{}/{}", network, chain.name),
+            name: chain.name,
+            chain_data: fields.as_ref().map(|fields| {
+                fields
+                    .iter()
+                    .filter_map(|&field| {
+                        let value = chain.chain_data.get(field)?;
+                        Some((field.to_string(), value.clone()))
+                    })
+                    .collect()
+            }),
         })
         .collect();
 
     let resp = ChainList {
         meta: Meta {
-            commit: list.commit,
+            commit: list.commit.clone(),
             updated_at: list.created_at,
         },
         result: chain_list,
+        next_cursor,
+    };
+    Ok(conditional.respond(&[&list.commit], list.created_at, Json(resp)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiffParams {
+    from: Option<String>,
+    to: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChainDiffResponse {
+    meta: Meta,
+    result: ChainDiff,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChainDiff {
+    from: String,
+    to: String,
+    /// RFC 6902 JSON Patch that turns chain.json at the from commit into chain.json at the to commit.
+    #[schema(value_type = Vec<Object>)]
+    chain_data: json_patch::Patch,
+    /// RFC 6902 JSON Patch for assetlist.json.
+    #[schema(value_type = Vec<Object>)]
+    asset_data: json_patch::Patch,
+}
+
+/// Get what changed for a chain between two commits.
+///
+/// Returns RFC 6902 JSON Patches for the chain's data and assetlist. Without from and to, compares the
+/// two most recent commits. With only one of them, the other defaults to the previous or latest
+/// commit. Commits must be retained; see /v1/commits.
+#[utoipa::path(
+get,
+path = "/v1/{network}/{chain_name}/diff",
+responses(
+(status = 200, description = "Diff computed successfully", body = ChainDiffResponse),
+(status = 304, description = "Diff has not changed since If-None-Match or If-Modified-Since"),
+(status = 404, description = "Network or commit does not exist, or chain does not exist at one of the commits"),
+),
+params(
+("network" = String, Path, description = "mainnet or testnet"),
+("chain_name" = String, Path, description = "Chain name, e.g. cosmoshub"),
+("from" = Option<String>, Query, description = "Older commit"),
+("to" = Option<String>, Query, description = "Newer commit"),
+),
+tag = "Chains",
+)]
+pub async fn get_chain_diff(
+    State(pool): State<PgPool>,
+    conditional: Conditional,
+    Path((network, chain_name)): Path<(String, String)>,
+    Query(params): Query<DiffParams>,
+) -> Result<Response, APIError> {
+    let mut conn = pool.acquire().await.map_err(internal_error)?;
+    // Newest first
+    let commits = commit::list_commits(&mut conn)
+        .await
+        .map_err(from_db_error)?;
+    let position = |sha: &str| {
+        commits
+            .iter()
+            .position(|c| c.commit == sha)
+            .ok_or(APIError::NotFound)
     };
-    Ok(Json(resp))
+
+    let to = match &params.to {
+        Some(sha) => position(sha)?,
+        None => 0,
+    };
+    let from = match &params.from {
+        Some(sha) => position(sha)?,
+        None => to + 1,
+    };
+    let (from, to) = match (commits.get(from), commits.get(to)) {
+        (Some(from), Some(to)) => (from, to),
+        _ => return Err(APIError::NotFound),
+    };
+
+    let old = chain::find_chain_at(&mut conn, network.as_str(), chain_name.as_str(), from)
+        .await
+        .map_err(from_db_error)?;
+    let new = chain::find_chain_at(&mut conn, network.as_str(), chain_name.as_str(), to)
+        .await
+        .map_err(from_db_error)?;
+
+    let resp = ChainDiffResponse {
+        meta: Meta {
+            commit: to.commit.clone(),
+            updated_at: to.created_at,
+        },
+        result: ChainDiff {
+            from: from.commit.clone(),
+            to: to.commit.clone(),
+            chain_data: json_patch::diff(&old.chain_data, &new.chain_data),
+            asset_data: json_patch::diff(&old.asset_data, &new.asset_data),
+        },
+    };
+
+    Ok(conditional.respond(&[&from.commit, &to.commit], to.created_at, Json(resp)))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChainValidationResponse {
+    meta: Meta,
+    result: ChainValidation,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChainValidation {
+    valid: bool,
+    errors: Vec<ValidationError>,
+}
+
+/// Get chain's schema validation errors.
+///
+/// Lists where chain.json and assetlist.json do not match the registry's chain.schema.json and
+/// assetlist.schema.json. The chain and assetlist endpoints still serve files with errors.
+#[utoipa::path(
+get,
+path = "/v1/{network}/{chain_name}/validation",
+responses(
+(status = 200, description = "Validation found successfully", body = ChainValidationResponse),
+(status = 304, description = "Validation has not changed since If-None-Match or If-Modified-Since"),
+(status = 400, description = "Both commit and at were given"),
+(status = 404, description = "Network, chain, or commit does not exist"),
+),
+params(
+("network" = String, Path, description = "mainnet or testnet"),
+("chain_name" = String, Path, description = "Chain name, e.g. cosmoshub"),
+("commit" = Option<String>, Query, description = "Return validation as of this retained commit. See /v1/commits"),
+("at" = Option<String>, Query, description = "Return validation as of this RFC 3339 time, e.g. 2023-04-20T12:00:00Z"),
+),
+tag = "Chains",
+)]
+pub async fn get_chain_validation(
+    State(pool): State<PgPool>,
+    conditional: Conditional,
+    Path((network, chain_name)): Path<(String, String)>,
+    Query(params): Query<HistoryParams>,
+) -> Result<Response, APIError> {
+    let chain = find_chain(&pool, network.as_str(), chain_name.as_str(), &params).await?;
+    let version = chain.version().to_string();
+    // Errors are revalidated against the latest schemas, so they can change with the same content.
+    let errors_version = chain.validation_errors.to_string();
+    let errors: Vec<ValidationError> =
+        serde_json::from_value(chain.validation_errors).map_err(internal_error)?;
+
+    let resp = ChainValidationResponse {
+        meta: Meta {
+            commit: chain.commit.clone(),
+            updated_at: chain.created_at,
+        },
+        result: ChainValidation {
+            valid: errors.is_empty(),
+            errors,
+        },
+    };
+
+    Ok(conditional.respond(
+        &[&version, &chain.commit, &chain_name, &errors_version],
+        chain.created_at,
+        Json(resp),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::HttpBody;
+    use axum::http::{header, HeaderMap, StatusCode, Uri};
+    use sqlx::Executor;
+
+    async fn diff(
+        pool: &PgPool,
+        chain_name: &str,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<serde_json::Value, APIError> {
+        let resp = get_chain_diff(
+            State(pool.clone()),
+            Conditional::new(Uri::from_static("/v1/mainnet/diff"), &HeaderMap::new()),
+            Path(("mainnet".to_string(), chain_name.to_string())),
+            Query(DiffParams {
+                from: from.map(str::to_string),
+                to: to.map(str::to_string),
+            }),
+        )
+        .await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let mut body = resp.into_body();
+        let mut bytes = Vec::new();
+        while let Some(chunk) = body.data().await {
+            bytes.extend_from_slice(&chunk.unwrap());
+        }
+        Ok(serde_json::from_slice::<serde_json::Value>(&bytes).unwrap()["result"].take())
+    }
+
+    // sqlx::test only finds fixtures next to the test file.
+    async fn chain_history(pool: &PgPool) -> sqlx::Result<()> {
+        pool.execute(include_str!("../db/fixtures/chain_history.sql"))
+            .await?;
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_get_chain_diff(pool: PgPool) -> sqlx::Result<()> {
+        chain_history(&pool).await?;
+        // stargaze gains website and loses status at commit3.
+        sqlx::query!(
+            r#"
+            WITH old AS (
+                INSERT INTO chain (name, network, commit, asset_data, chain_data, created_at)
+                VALUES ('stargaze', 'mainnet', 'commit1', '{}', '{"chain_id": "stargaze-1", "status": "live"}', '2023-04-20 10:00:00+00')
+                RETURNING id
+            ), new AS (
+                INSERT INTO chain (name, network, commit, asset_data, chain_data, created_at)
+                VALUES ('stargaze', 'mainnet', 'commit3', '{}', '{"chain_id": "stargaze-1", "website": "https://stargaze.zone"}', '2023-04-20 12:00:00+00')
+                RETURNING id
+            )
+            INSERT INTO chain_commit (commit, chain_id_fk)
+            SELECT 'commit1', id FROM old
+            UNION ALL SELECT 'commit2', id FROM old
+            UNION ALL SELECT 'commit3', id FROM new
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        // Defaults to the two most recent commits. cosmoshub's chain_id was replaced.
+        let found = diff(&pool, "cosmoshub", None, None).await.unwrap();
+        assert_eq!(found["from"], "commit2");
+        assert_eq!(found["to"], "commit3");
+        assert_eq!(
+            found["chain_data"],
+            serde_json::json!([{"op": "replace", "path": "/chain_id", "value": "cosmoshub-4"}])
+        );
+        assert_eq!(found["asset_data"], serde_json::json!([]));
+
+        let found = diff(&pool, "stargaze", Some("commit1"), None)
+            .await
+            .unwrap();
+        assert_eq!(found["from"], "commit1");
+        assert_eq!(
+            found["chain_data"],
+            serde_json::json!([
+                {"op": "add", "path": "/website", "value": "https://stargaze.zone"},
+                {"op": "remove", "path": "/status"}
+            ])
+        );
+
+        // Reversed, the field is removed and the other added back.
+        let found = diff(&pool, "stargaze", Some("commit3"), Some("commit2"))
+            .await
+            .unwrap();
+        assert_eq!(
+            found["chain_data"],
+            serde_json::json!([
+                {"op": "add", "path": "/status", "value": "live"},
+                {"op": "remove", "path": "/website"}
+            ])
+        );
+
+        // Unchanged between commits
+        let found = diff(&pool, "osmosis", Some("commit1"), Some("commit3"))
+            .await
+            .unwrap();
+        assert_eq!(found["chain_data"], serde_json::json!([]));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_get_chain_diff_not_found(pool: PgPool) -> sqlx::Result<()> {
+        chain_history(&pool).await?;
+        assert!(matches!(
+            diff(&pool, "cosmoshub", Some("unknown"), None).await,
+            Err(APIError::NotFound)
+        ));
+        assert!(matches!(
+            diff(&pool, "cosmoshub", None, Some("unknown")).await,
+            Err(APIError::NotFound)
+        ));
+        // There is no commit before the oldest.
+        assert!(matches!(
+            diff(&pool, "cosmoshub", None, Some("commit1")).await,
+            Err(APIError::NotFound)
+        ));
+        // juno did not exist at commit2.
+        assert!(matches!(
+            diff(&pool, "juno", None, None).await,
+            Err(APIError::NotFound)
+        ));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_get_chain_data_etag(pool: PgPool) -> sqlx::Result<()> {
+        chain_history(&pool).await?;
+        sqlx::query!(
+            r#"UPDATE chain SET chain_data = '{"chain_name": "osmosis", "chain_id": "osmosis-1"}' WHERE id = 3"#
+        )
+        .execute(&pool)
+        .await?;
+        let get = |etag: Option<String>| {
+            let pool = pool.clone();
+            async move {
+                let mut headers = HeaderMap::new();
+                if let Some(etag) = etag {
+                    headers.insert(header::IF_NONE_MATCH, etag.parse().unwrap());
+                }
+                get_chain_data(
+                    State(pool),
+                    Conditional::new(Uri::from_static("/v1/mainnet/osmosis"), &headers),
+                    Path(("mainnet".to_string(), "osmosis".to_string())),
+                    Query(HistoryParams {
+                        commit: None,
+                        at: None,
+                    }),
+                )
+                .await
+                .unwrap()
+            }
+        };
+
+        let resp = get(None).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let etag = resp.headers()[header::ETAG].to_str().unwrap().to_string();
+        assert_eq!(
+            get(Some(etag.clone())).await.status(),
+            StatusCode::NOT_MODIFIED
+        );
+
+        // osmosis is unchanged at commit4, but the response's meta.commit is not.
+        sqlx::query!(
+            r#"
+            WITH new_commit AS (
+                INSERT INTO registry_commit (commit, created_at)
+                VALUES ('commit4', '2023-04-20 13:00:00+00')
+            )
+            INSERT INTO chain_commit (commit, chain_id_fk) VALUES ('commit4', 3)
+            "#
+        )
+        .execute(&pool)
+        .await?;
+        let resp = get(Some(etag.clone())).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_ne!(resp.headers()[header::ETAG], etag.as_str());
+
+        Ok(())
+    }
 }
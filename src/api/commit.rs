@@ -0,0 +1,58 @@
+use crate::api::{from_db_error, internal_error, APIError, Meta};
+use crate::db::commit;
+use axum::{extract::State, Json};
+use serde::Serialize;
+use sqlx::postgres::PgPool;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CommitList {
+    meta: Meta,
+    result: Vec<Commit>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Commit {
+    #[schema(example = "commit hash from https://github.com/cosmos/chain-registry")]
+    commit: String,
+    /// When the commit was fetched.
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// List retained commits.
+///
+/// Chain endpoints accept any of these commits with the commit query parameter. Newest first.
+#[utoipa::path(
+get,
+path = "/v1/commits",
+responses(
+(status = 200, description = "Commits found successfully", body = CommitList),
+(status = 404, description = "No commits have been fetched yet"),
+),
+tag = "Chains",
+)]
+pub async fn list_commits(State(pool): State<PgPool>) -> Result<Json<CommitList>, APIError> {
+    let mut conn = pool.acquire().await.map_err(internal_error)?;
+    let commits = commit::list_commits(&mut conn)
+        .await
+        .map_err(from_db_error)?;
+
+    let latest = match commits.first() {
+        Some(latest) => latest,
+        None => return Err(APIError::NotFound),
+    };
+    let meta = Meta {
+        commit: latest.commit.clone(),
+        updated_at: latest.created_at,
+    };
+
+    let result = commits
+        .into_iter()
+        .map(|c| Commit {
+            commit: c.commit,
+            created_at: c.created_at,
+        })
+        .collect();
+
+    Ok(Json(CommitList { meta, result }))
+}
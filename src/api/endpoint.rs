@@ -0,0 +1,94 @@
+use crate::api::{from_db_error, internal_error, APIError, Meta};
+use crate::db::endpoint::{filter_recent_endpoints, EndpointFilter, EndpointKind};
+use axum::{extract::Path, extract::Query, extract::State, Json};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPool;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EndpointList {
+    meta: Meta,
+    result: Vec<Endpoint>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Endpoint {
+    #[schema(example = "rpc")]
+    kind: String,
+    address: String,
+    provider: Option<String>,
+    last_liveness_check: chrono::DateTime<chrono::Utc>,
+    is_alive: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EndpointParams {
+    kind: Option<EndpointKind>,
+    #[serde(default)]
+    include_all: bool,
+}
+
+/// Get chain's live RPC, REST, and gRPC endpoints.
+/// A background process periodically checks endpoints for liveness. RPC endpoints must answer /status
+/// and REST endpoints must answer /cosmos/base/tendermint/v1beta1/node_info. If an endpoint cannot be
+/// reached, it is excluded from this response by default.
+#[utoipa::path(
+get,
+path = "/v1/{network}/{chain_name}/endpoints",
+responses(
+(status = 200, description = "Endpoints found successfully", body = EndpointList),
+(status = 404, description = "Network or chain does not exist, or chain does not have any endpoints"),
+),
+params(
+("network" = String, Path, description = "mainnet or testnet"),
+("chain_name" = String, Path, description = "Chain name, e.g. cosmoshub"),
+("kind" = Option<String>, Query, description = "Only include endpoints of this kind: rpc, rest, or grpc"),
+("include_all" = Option<bool>, Query, description = "If true, include all endpoints regardless of liveness"),
+),
+tag = "Endpoints",
+)]
+pub async fn list_endpoints(
+    State(pool): State<PgPool>,
+    Path((network, chain_name)): Path<(String, String)>,
+    Query(params): Query<EndpointParams>,
+) -> Result<Json<EndpointList>, APIError> {
+    let filter = EndpointFilter {
+        chain_name,
+        network,
+        kind: params.kind,
+        include_all: params.include_all,
+    };
+
+    let mut conn = pool.acquire().await.map_err(internal_error)?;
+    let endpoints = filter_recent_endpoints(&mut conn, &filter)
+        .await
+        .map_err(from_db_error)?;
+
+    let meta = Meta {
+        commit: endpoints
+            .first()
+            .map(|e| e.commit.clone())
+            .unwrap_or_default(),
+        updated_at: endpoints
+            .iter()
+            .map(|e| e.updated_at)
+            .max()
+            .unwrap_or_default(),
+    };
+
+    let resp = EndpointList {
+        meta,
+        result: endpoints
+            .into_iter()
+            .map(|e| Endpoint {
+                kind: e.kind,
+                address: e.address,
+                provider: e.provider,
+                last_liveness_check: e.updated_at,
+                is_alive: e.is_alive,
+            })
+            .collect(),
+    };
+
+    Ok(Json(resp))
+}
@@ -0,0 +1,135 @@
+use crate::api::chain::HistoryParams;
+use crate::api::{from_db_error, internal_error, APIError};
+use crate::db::{chain, commit};
+use crate::export::{Encoder, Format};
+use axum::{
+    body::{Bytes, StreamBody},
+    extract::Query,
+    extract::State,
+    http::header,
+    response::IntoResponse,
+};
+use futures::channel::mpsc::Sender;
+use futures::SinkExt;
+use serde::Deserialize;
+use sqlx::postgres::PgPool;
+use std::time::Duration;
+
+/// Chains read per query. The connection is released between pages.
+const PAGE_SIZE: i64 = 50;
+/// How long a client may stop reading before the export is aborted.
+const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Deserialize)]
+pub struct ExportParams {
+    network: Option<String>,
+    #[serde(default)]
+    format: Format,
+}
+
+/// Export every chain at a commit.
+///
+/// Returns each chain's chain.json, assetlist.json, and live peers in one response. The response is
+/// streamed, so it starts before all chains are read. json is {"meta", "result": [...]}; ndjson has one
+/// chain per line; tar.gz has network/chain_name/chain.json, assetlist.json, and peers.json. The commit
+/// is in the X-Registry-Commit header for every format.
+#[utoipa::path(
+get,
+path = "/v1/export",
+responses(
+(status = 200, description = "Export started successfully"),
+(status = 400, description = "Both commit and at were given, or format is invalid"),
+(status = 404, description = "Commit does not exist or no commits have been fetched yet"),
+),
+params(
+("network" = Option<String>, Query, description = "mainnet or testnet. Defaults to both"),
+("format" = Option<String>, Query, description = "json, ndjson, or tar.gz. Defaults to json"),
+("commit" = Option<String>, Query, description = "Export chains as of this retained commit. See /v1/commits"),
+("at" = Option<String>, Query, description = "Export chains as of this RFC 3339 time, e.g. 2023-04-20T12:00:00Z"),
+),
+tag = "Chains",
+)]
+pub async fn export(
+    State(pool): State<PgPool>,
+    Query(history): Query<HistoryParams>,
+    Query(params): Query<ExportParams>,
+) -> Result<impl IntoResponse, APIError> {
+    let found = {
+        let mut conn = pool.acquire().await.map_err(internal_error)?;
+        match history.find_commit(&mut conn).await? {
+            Some(commit) => commit,
+            None => commit::find_commit_at(&mut conn, chrono::Utc::now())
+                .await
+                .map_err(from_db_error)?,
+        }
+    };
+
+    let headers = [
+        (
+            header::CONTENT_TYPE,
+            params.format.content_type().to_string(),
+        ),
+        (
+            header::HeaderName::from_static("x-registry-commit"),
+            found.commit.clone(),
+        ),
+    ];
+
+    // The task stops once the client disconnects or stops reading because sends fail.
+    let (mut tx, rx) = futures::channel::mpsc::channel::<Result<Bytes, std::io::Error>>(16);
+    tokio::spawn(async move {
+        let mut encoder = Encoder::new(params.format, found.clone());
+        let mut after: Option<(String, String)> = None;
+        loop {
+            let page = chain::export_chains(
+                &pool,
+                params.network.as_deref(),
+                &found,
+                after
+                    .as_ref()
+                    .map(|(network, name)| (network.as_str(), name.as_str())),
+                PAGE_SIZE,
+            )
+            .await;
+            let page = match page {
+                Ok(page) => page,
+                Err(err) => {
+                    tracing::error!("Failed to export chains: {:?}", err);
+                    send(&mut tx, Err(std::io::Error::other(err))).await;
+                    return;
+                }
+            };
+            for chain in &page {
+                let chunk = encoder.chain(chain);
+                let failed = chunk.is_err();
+                if let Err(err) = &chunk {
+                    tracing::error!("Failed to export chains: {:?}", err);
+                }
+                if !send(&mut tx, chunk.map(Bytes::from)).await || failed {
+                    return;
+                }
+            }
+            if page.len() < PAGE_SIZE as usize {
+                break;
+            }
+            after = page.last().map(|c| (c.network.clone(), c.name.clone()));
+        }
+        send(&mut tx, encoder.finish().map(Bytes::from)).await;
+    });
+
+    Ok((headers, StreamBody::new(rx)))
+}
+
+// Returns false if the client disconnected or did not read within SEND_TIMEOUT.
+async fn send(
+    tx: &mut Sender<Result<Bytes, std::io::Error>>,
+    chunk: Result<Bytes, std::io::Error>,
+) -> bool {
+    match tokio::time::timeout(SEND_TIMEOUT, tx.send(chunk)).await {
+        Ok(sent) => sent.is_ok(),
+        Err(_) => {
+            tracing::warn!("Export client stopped reading, aborting");
+            false
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use crate::db::{job, peer};
+use axum::{extract::State, http::StatusCode, routing::get, Json, Router};
+use serde::Serialize;
+use sqlx::postgres::PgPool;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct HealthState {
+    pub pool: PgPool,
+    /// Not ready if the last successful hydrate finished longer ago than this.
+    pub max_chain_age: Duration,
+    /// Not ready if no peer was checked for liveness within this.
+    pub max_liveness_age: Duration,
+}
+
+/// Serves /healthz and /readyz for load balancers. They are not part of the versioned API.
+pub fn router<S>(state: HealthState) -> Router<S> {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .with_state(state)
+}
+
+/// The process is up.
+async fn healthz() -> &'static str {
+    "ok"
+}
+
+#[derive(Debug, Serialize)]
+struct Readiness {
+    ready: bool,
+    /// Why the instance is not ready.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<String>,
+    /// When the last successful hydrate finished. Hydrates that find no new commit count.
+    hydrated_at: Option<chrono::DateTime<chrono::Utc>>,
+    peer_updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// The database is reachable and its data is fresh. Returns 503 otherwise.
+async fn readyz(State(state): State<HealthState>) -> (StatusCode, Json<Readiness>) {
+    let mut readiness = Readiness {
+        ready: false,
+        errors: vec![],
+        hydrated_at: None,
+        peer_updated_at: None,
+    };
+    let mut conn = match state.pool.acquire().await {
+        Ok(conn) => conn,
+        Err(err) => {
+            readiness.errors.push(format!("database: {}", err));
+            return (StatusCode::SERVICE_UNAVAILABLE, Json(readiness));
+        }
+    };
+
+    match job::last_succeeded_at(&mut conn, "hydrate").await {
+        Ok(finished_at) => readiness.hydrated_at = finished_at,
+        Err(err) => readiness.errors.push(format!("database: {}", err)),
+    }
+    match peer::newest_updated_at(&mut conn).await {
+        Ok(updated_at) => readiness.peer_updated_at = updated_at,
+        Err(err) => readiness.errors.push(format!("database: {}", err)),
+    }
+    if readiness.errors.is_empty() {
+        let now = chrono::Utc::now();
+        readiness.errors.extend(stale(
+            "hydrate",
+            readiness.hydrated_at,
+            now,
+            state.max_chain_age,
+        ));
+        readiness.errors.extend(stale(
+            "peer liveness",
+            readiness.peer_updated_at,
+            now,
+            state.max_liveness_age,
+        ));
+    }
+
+    readiness.ready = readiness.errors.is_empty();
+    let status = match readiness.ready {
+        true => StatusCode::OK,
+        false => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (status, Json(readiness))
+}
+
+/// Returns an error if updated_at is missing or older than max_age.
+fn stale(
+    what: &str,
+    updated_at: Option<chrono::DateTime<chrono::Utc>>,
+    now: chrono::DateTime<chrono::Utc>,
+    max_age: Duration,
+) -> Option<String> {
+    let Some(updated_at) = updated_at else {
+        return Some(format!("{}: none found", what));
+    };
+    let age = (now - updated_at).to_std().unwrap_or_default();
+    if age > max_age {
+        return Some(format!(
+            "{}: last updated {}s ago, max is {}s",
+            what,
+            age.as_secs(),
+            max_age.as_secs()
+        ));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale() {
+        let now: chrono::DateTime<chrono::Utc> = "2023-04-20T12:00:00Z".parse().unwrap();
+        let max_age = Duration::from_secs(3600);
+
+        assert_eq!(
+            stale("chain data", None, now, max_age),
+            Some("chain data: none found".to_string())
+        );
+        let fresh = now - chrono::Duration::seconds(3600);
+        assert_eq!(stale("chain data", Some(fresh), now, max_age), None);
+        // Clock skew between the database and server
+        let future = now + chrono::Duration::seconds(5);
+        assert_eq!(stale("chain data", Some(future), now, max_age), None);
+
+        let old = now - chrono::Duration::seconds(3601);
+        assert_eq!(
+            stale("peer liveness", Some(old), now, max_age),
+            Some("peer liveness: last updated 3601s ago, max is 3600s".to_string())
+        );
+    }
+}
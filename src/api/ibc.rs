@@ -0,0 +1,272 @@
+use crate::api::{from_db_error, internal_error, APIError, Meta};
+use crate::db::{asset, chain, ibc};
+use crate::denom;
+use crate::registry::{self, AssetList};
+use axum::{extract::Path, extract::State, Json};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPool;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IbcPathResponse {
+    meta: Meta,
+    result: IbcPath,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IbcPathList {
+    meta: Meta,
+    result: Vec<IbcPath>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct IbcPath {
+    chain_1: IbcChain,
+    chain_2: IbcChain,
+    #[serde(default)]
+    channels: Vec<IbcChannel>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct IbcChain {
+    #[schema(example = "cosmoshub")]
+    chain_name: String,
+    #[schema(example = "07-tendermint-259")]
+    client_id: String,
+    #[schema(example = "connection-257")]
+    connection_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct IbcChannel {
+    chain_1: IbcChannelEnd,
+    chain_2: IbcChannelEnd,
+    #[schema(example = "unordered")]
+    ordering: Option<String>,
+    #[schema(example = "ics20-1")]
+    version: Option<String>,
+    #[schema(value_type = Option<Object>)]
+    tags: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct IbcChannelEnd {
+    #[schema(example = "channel-141")]
+    channel_id: String,
+    #[schema(example = "transfer")]
+    port_id: String,
+}
+
+impl IbcPath {
+    fn from_row(row: &ibc::IbcPath, chain_name: &str) -> Result<IbcPath, APIError> {
+        let path: IbcPath = serde_json::from_value(row.ibc_data.clone()).map_err(internal_error)?;
+        Ok(path.oriented(chain_name))
+    }
+
+    /// Swaps sides so chain_1 is always the given chain. The registry stores each path once in
+    /// alphabetical order, so callers should not need to know which side their chain is on.
+    fn oriented(mut self, chain_name: &str) -> IbcPath {
+        if self.chain_1.chain_name == chain_name {
+            return self;
+        }
+        std::mem::swap(&mut self.chain_1, &mut self.chain_2);
+        for channel in self.channels.iter_mut() {
+            std::mem::swap(&mut channel.chain_1, &mut channel.chain_2);
+        }
+        self
+    }
+}
+
+/// Get the IBC path between two chains.
+///
+/// Returns the client, connection, and channel ids for both chains. The response is oriented so
+/// that chain_1 is always chain_a from the request.
+#[utoipa::path(
+get,
+path = "/v1/{network}/ibc/{chain_a}/{chain_b}",
+responses(
+(status = 200, description = "IBC path found successfully", body = IbcPathResponse),
+(status = 404, description = "Network or IBC path does not exist"),
+),
+params(
+("network" = String, Path, description = "mainnet or testnet"),
+("chain_a" = String, Path, description = "Chain name, e.g. cosmoshub"),
+("chain_b" = String, Path, description = "Counterparty chain name, e.g. osmosis"),
+),
+tag = "IBC",
+)]
+pub async fn get_ibc_path(
+    State(pool): State<PgPool>,
+    Path((network, chain_a, chain_b)): Path<(String, String, String)>,
+) -> Result<Json<IbcPathResponse>, APIError> {
+    let mut conn = pool.acquire().await.map_err(internal_error)?;
+    let row = ibc::find_ibc_path(
+        &mut conn,
+        network.as_str(),
+        chain_a.as_str(),
+        chain_b.as_str(),
+    )
+    .await
+    .map_err(from_db_error)?;
+
+    let resp = IbcPathResponse {
+        result: IbcPath::from_row(&row, chain_a.as_str())?,
+        meta: Meta {
+            commit: row.commit,
+            updated_at: row.created_at,
+        },
+    };
+
+    Ok(Json(resp))
+}
+
+/// List a chain's IBC paths.
+///
+/// Returns every IBC path where the chain is a counterparty. Each path is oriented so that chain_1
+/// is always the requested chain.
+#[utoipa::path(
+get,
+path = "/v1/{network}/{chain_name}/ibc",
+responses(
+(status = 200, description = "IBC paths found successfully", body = IbcPathList),
+(status = 404, description = "Network or chain does not exist, or chain does not have any IBC paths"),
+),
+params(
+("network" = String, Path, description = "mainnet or testnet"),
+("chain_name" = String, Path, description = "Chain name, e.g. cosmoshub"),
+),
+tag = "IBC",
+)]
+pub async fn list_chain_ibc_paths(
+    State(pool): State<PgPool>,
+    Path((network, chain_name)): Path<(String, String)>,
+) -> Result<Json<IbcPathList>, APIError> {
+    let mut conn = pool.acquire().await.map_err(internal_error)?;
+    let rows = ibc::list_ibc_paths(&mut conn, network.as_str(), chain_name.as_str())
+        .await
+        .map_err(from_db_error)?;
+
+    let first = match rows.first() {
+        Some(row) => row,
+        None => return Err(APIError::NotFound),
+    };
+    let meta = Meta {
+        commit: first.commit.clone(),
+        updated_at: first.created_at,
+    };
+
+    let result = rows
+        .iter()
+        .map(|row| IbcPath::from_row(row, chain_name.as_str()))
+        .collect::<Result<Vec<IbcPath>, APIError>>()?;
+
+    Ok(Json(IbcPathList { meta, result }))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DenomTraceResponse {
+    meta: Meta,
+    result: DenomTrace,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DenomTrace {
+    #[schema(example = "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2")]
+    denom: String,
+    /// Trace path whose SHA-256 is the denom's hash.
+    #[schema(example = "transfer/channel-0/uatom")]
+    path: String,
+    #[schema(example = "uatom")]
+    base_denom: String,
+    /// Chain the asset was sent from. For multi-hop traces this is the chain that issued it.
+    #[schema(example = "cosmoshub")]
+    origin_chain_name: String,
+    /// Channel on the requested chain the asset arrived on.
+    #[schema(example = "channel-0")]
+    channel_id: String,
+}
+
+impl From<denom::DenomTrace> for DenomTrace {
+    fn from(trace: denom::DenomTrace) -> Self {
+        DenomTrace {
+            denom: trace.denom,
+            path: trace.path,
+            base_denom: trace.base_denom,
+            origin_chain_name: trace.origin_chain_name,
+            channel_id: trace.channel_id,
+        }
+    }
+}
+
+/// Resolve an IBC denom.
+///
+/// Finds the asset behind an ibc/{hash} denom on the chain. Candidates are the chain's own assetlist
+/// entries with IBC traces and every asset of chains connected to it by a transfer channel in the
+/// registry's IBC data. Assets not in any assetlist cannot be resolved.
+#[utoipa::path(
+get,
+path = "/v1/{network}/{chain_name}/denoms/ibc/{hash}",
+responses(
+(status = 200, description = "Denom resolved successfully", body = DenomTraceResponse),
+(status = 404, description = "Network or chain does not exist, or no known asset has this hash"),
+),
+params(
+("network" = String, Path, description = "mainnet or testnet"),
+("chain_name" = String, Path, description = "Chain holding the denom, e.g. osmosis"),
+("hash" = String, Path, description = "Hash after ibc/, e.g. 27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2"),
+),
+tag = "IBC",
+)]
+pub async fn get_ibc_denom(
+    State(pool): State<PgPool>,
+    Path((network, chain_name, hash)): Path<(String, String, String)>,
+) -> Result<Json<DenomTraceResponse>, APIError> {
+    let mut conn = pool.acquire().await.map_err(internal_error)?;
+    let found = chain::find_chain(&mut conn, network.as_str(), chain_name.as_str())
+        .await
+        .map_err(from_db_error)?;
+    let own: AssetList = serde_json::from_value(found.asset_data).map_err(internal_error)?;
+
+    let rows = ibc::list_ibc_paths(&mut conn, network.as_str(), chain_name.as_str())
+        .await
+        .map_err(from_db_error)?;
+    let mut channels = vec![];
+    for row in rows.iter() {
+        let path = IbcPath::from_row(row, chain_name.as_str())?;
+        channels.extend(
+            path.channels
+                .into_iter()
+                .filter(|c| c.chain_1.port_id == "transfer" && c.chain_2.port_id == "transfer")
+                .map(|c| denom::TransferChannel {
+                    counterparty: path.chain_2.chain_name.clone(),
+                    channel_id: c.chain_1.channel_id,
+                }),
+        );
+    }
+
+    let mut counterparties: Vec<String> = channels.iter().map(|c| c.counterparty.clone()).collect();
+    counterparties.sort();
+    counterparties.dedup();
+    let counterparty_assets =
+        asset::list_chain_assets(&mut conn, network.as_str(), &counterparties)
+            .await
+            .map_err(from_db_error)?
+            .into_iter()
+            .map(|row| {
+                let asset: registry::Asset =
+                    serde_json::from_value(row.data).map_err(internal_error)?;
+                Ok((row.chain_name, asset))
+            })
+            .collect::<Result<Vec<_>, APIError>>()?;
+
+    let trace = denom::resolve(hash.as_str(), &own.assets, &channels, &counterparty_assets)
+        .ok_or(APIError::NotFound)?;
+
+    Ok(Json(DenomTraceResponse {
+        meta: Meta {
+            commit: found.commit,
+            updated_at: found.created_at,
+        },
+        result: trace.into(),
+    }))
+}
@@ -0,0 +1,106 @@
+use crate::api::{from_db_error, internal_error, APIError, Meta};
+use crate::db::{chain, commit};
+use axum::{extract::Query, extract::State, Json};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPool;
+use utoipa::ToSchema;
+
+#[derive(Debug, Deserialize)]
+pub struct LookupParams {
+    chain_id: Option<String>,
+    bech32_prefix: Option<String>,
+    slip44: Option<i32>,
+    denom: Option<String>,
+}
+
+impl LookupParams {
+    fn key(self) -> Result<chain::LookupKey, APIError> {
+        let mut keys = vec![];
+        if let Some(chain_id) = self.chain_id {
+            keys.push(chain::LookupKey::ChainId(chain_id));
+        }
+        if let Some(prefix) = self.bech32_prefix {
+            keys.push(chain::LookupKey::Bech32Prefix(prefix));
+        }
+        if let Some(slip44) = self.slip44 {
+            keys.push(chain::LookupKey::Slip44(slip44));
+        }
+        if let Some(denom) = self.denom {
+            keys.push(chain::LookupKey::Denom(denom));
+        }
+        match keys.len() {
+            1 => Ok(keys.remove(0)),
+            _ => Err(APIError::BadRequest(
+                "exactly one of chain_id, bech32_prefix, slip44, or denom is required".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LookupList {
+    meta: Meta,
+    result: Vec<LookupItem>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LookupItem {
+    #[schema(example = "mainnet")]
+    network: String,
+    #[schema(example = "cosmoshub")]
+    chain_name: String,
+    #[schema(example = "/v1/mainnet/cosmoshub")]
+    path: String,
+}
+
+/// Find chains by chain id, bech32 prefix, slip44, or denom.
+///
+/// Every other endpoint needs the chain's directory name in the registry. Use this endpoint to find it
+/// from what a wallet or client already knows. Exactly one query parameter is required. Only the latest
+/// commit is searched. Several chains may match, e.g. mainnets and testnets share bech32 prefixes.
+#[utoipa::path(
+get,
+path = "/v1/lookup",
+responses(
+(status = 200, description = "Lookup completed successfully; result may be empty", body = LookupList),
+(status = 400, description = "Zero or more than one query parameter was given"),
+(status = 404, description = "No commits have been fetched yet"),
+),
+params(
+("chain_id" = Option<String>, Query, description = "Chain id, e.g. osmosis-1"),
+("bech32_prefix" = Option<String>, Query, description = "Bech32 address prefix, e.g. osmo"),
+("slip44" = Option<i32>, Query, description = "SLIP-0044 coin type, e.g. 118"),
+("denom" = Option<String>, Query, description = "Base denom or denom unit of an asset, e.g. uosmo or osmo"),
+),
+tag = "Chains",
+)]
+pub async fn lookup_chains(
+    State(pool): State<PgPool>,
+    Query(params): Query<LookupParams>,
+) -> Result<Json<LookupList>, APIError> {
+    let key = params.key()?;
+    let mut conn = pool.acquire().await.map_err(internal_error)?;
+    let latest = commit::find_commit_at(&mut conn, chrono::Utc::now())
+        .await
+        .map_err(from_db_error)?;
+    let chains = chain::lookup_chains(&mut conn, &key)
+        .await
+        .map_err(from_db_error)?;
+
+    let result = chains
+        .into_iter()
+        .map(|chain| LookupItem {
+            path: format!("/v1/{}/{}", chain.network, chain.name),
+            network: chain.network,
+            chain_name: chain.name,
+        })
+        .collect();
+
+    Ok(Json(LookupList {
+        meta: Meta {
+            commit: latest.commit,
+            updated_at: latest.created_at,
+        },
+        result,
+    }))
+}
@@ -0,0 +1,200 @@
+use crate::api::response_cache::ResponseCache;
+use crate::api::{internal_error, APIError};
+use crate::db::{chain, commit, job, peer};
+use crate::metrics::{Encoder, HttpMetrics};
+use axum::{
+    extract::{MatchedPath, State},
+    http::{header, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use sqlx::postgres::PgPool;
+use std::sync::Arc;
+use std::time::Instant;
+
+#[derive(Debug, Clone)]
+pub struct MetricsState {
+    pub pool: PgPool,
+    pub max_connections: u32,
+    pub http: Arc<HttpMetrics>,
+    pub cache: ResponseCache,
+}
+
+/// Serves /metrics. It is not part of the versioned API.
+pub fn router<S>(state: MetricsState) -> Router<S> {
+    Router::new()
+        .route("/metrics", get(metrics))
+        .with_state(state)
+}
+
+/// Middleware that counts requests and their latency by route.
+pub async fn track_requests<B>(
+    State(http): State<Arc<HttpMetrics>>,
+    matched: Option<MatchedPath>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let method = request.method().to_string();
+    let start = Instant::now();
+    let response = next.run(request).await;
+    let route = matched.as_ref().map_or("unmatched", |m| m.as_str());
+    http.observe(route, &method, response.status().as_u16(), start.elapsed());
+    response
+}
+
+async fn metrics(State(state): State<MetricsState>) -> Result<impl IntoResponse, APIError> {
+    let mut encoder = Encoder::default();
+    state.http.encode(&mut encoder);
+
+    encoder.family(
+        "db_pool_connections",
+        "gauge",
+        "Open Postgres connections by state.",
+    );
+    let (size, idle) = (state.pool.size(), state.pool.num_idle() as u32);
+    encoder.sample("db_pool_connections", &[("state", "idle")], idle as f64);
+    encoder.sample(
+        "db_pool_connections",
+        &[("state", "in_use")],
+        size.saturating_sub(idle) as f64,
+    );
+    encoder.family(
+        "db_pool_max_connections",
+        "gauge",
+        "Max Postgres connections.",
+    );
+    encoder.sample("db_pool_max_connections", &[], state.max_connections as f64);
+
+    let stats = state.cache.stats();
+    for (name, help, value) in [
+        (
+            "response_cache_hits_total",
+            "Responses served from the cache.",
+            stats.hits,
+        ),
+        (
+            "response_cache_misses_total",
+            "Responses not in the cache.",
+            stats.misses,
+        ),
+        (
+            "response_cache_evictions_total",
+            "Responses evicted to make room.",
+            stats.evictions,
+        ),
+    ] {
+        encoder.family(name, "counter", help);
+        encoder.sample(name, &[], value as f64);
+    }
+    encoder.family("response_cache_entries", "gauge", "Cached responses.");
+    encoder.sample("response_cache_entries", &[], stats.entries as f64);
+
+    let mut conn = state.pool.acquire().await.map_err(internal_error)?;
+
+    encoder.family(
+        "registry_chains",
+        "gauge",
+        "Chains at the latest commit by network.",
+    );
+    for count in chain::count_chains(&mut conn)
+        .await
+        .map_err(internal_error)?
+    {
+        encoder.sample(
+            "registry_chains",
+            &[("network", &count.network)],
+            count.chains as f64,
+        );
+    }
+
+    encoder.family(
+        "registry_peers",
+        "gauge",
+        "Peers at the latest commit by chain and liveness.",
+    );
+    for count in peer::count_peers(&mut conn).await.map_err(internal_error)? {
+        for (status, value) in [("alive", count.alive), ("dead", count.dead)] {
+            encoder.sample(
+                "registry_peers",
+                &[
+                    ("network", &count.network),
+                    ("chain_name", &count.chain_name),
+                    ("status", status),
+                ],
+                value as f64,
+            );
+        }
+    }
+
+    encoder.family(
+        "registry_commit_age_seconds",
+        "gauge",
+        "Seconds since the latest commit was hydrated.",
+    );
+    match commit::find_commit_at(&mut conn, chrono::Utc::now()).await {
+        Ok(latest) => {
+            let age = chrono::Utc::now() - latest.created_at;
+            encoder.sample(
+                "registry_commit_age_seconds",
+                &[],
+                age.num_milliseconds() as f64 / 1000.0,
+            );
+        }
+        Err(sqlx::Error::RowNotFound) => {}
+        Err(err) => return Err(internal_error(err)),
+    }
+
+    let runs = job::latest_runs(&mut conn).await.map_err(internal_error)?;
+    encoder.family(
+        "job_last_run_duration_seconds",
+        "gauge",
+        "Duration of the last hydrate, liveness, or crawl run.",
+    );
+    for run in &runs {
+        let duration = run.finished_at - run.started_at;
+        encoder.sample(
+            "job_last_run_duration_seconds",
+            &[("job", &run.job)],
+            duration.num_milliseconds() as f64 / 1000.0,
+        );
+    }
+    encoder.family(
+        "job_last_run_timestamp_seconds",
+        "gauge",
+        "Unix time the last run finished.",
+    );
+    for run in &runs {
+        encoder.sample(
+            "job_last_run_timestamp_seconds",
+            &[("job", &run.job)],
+            run.finished_at.timestamp() as f64,
+        );
+    }
+    encoder.family(
+        "job_last_run_success",
+        "gauge",
+        "1 if the last run succeeded.",
+    );
+    for run in &runs {
+        encoder.sample(
+            "job_last_run_success",
+            &[("job", &run.job)],
+            if run.succeeded { 1.0 } else { 0.0 },
+        );
+    }
+    encoder.family(
+        "job_last_run_items",
+        "gauge",
+        "Chains inserted by the last hydrate, peers and endpoints checked by the last liveness run, or peers discovered by the last crawl.",
+    );
+    for run in &runs {
+        encoder.sample("job_last_run_items", &[("job", &run.job)], run.items as f64);
+    }
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        encoder.finish(),
+    ))
+}
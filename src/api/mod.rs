@@ -7,8 +7,19 @@ use serde::Serialize;
 use serde_json::json;
 use utoipa::ToSchema;
 
+pub(crate) mod asset;
+pub(crate) mod cache;
 pub(crate) mod chain;
+pub(crate) mod commit;
+pub(crate) mod endpoint;
+pub(crate) mod export;
+pub(crate) mod health;
+pub(crate) mod ibc;
+pub(crate) mod lookup;
+pub(crate) mod metrics;
+pub(crate) mod node_config;
 pub(crate) mod peer;
+pub(crate) mod response_cache;
 pub(crate) mod router;
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -18,14 +29,9 @@ struct Meta {
     updated_at: chrono::DateTime<chrono::Utc>,
 }
 
-#[derive(Debug, Serialize)]
-pub struct APIResponse<T> {
-    meta: Meta,
-    result: T,
-}
-
 #[derive(Debug)]
 pub enum APIError {
+    BadRequest(String),
     NotFound,
     InternalServerError(String),
 }
@@ -44,6 +50,7 @@ fn from_db_error(err: sqlx::Error) -> APIError {
 impl IntoResponse for APIError {
     fn into_response(self) -> Response {
         let (status, message) = match self {
+            APIError::BadRequest(err) => (StatusCode::BAD_REQUEST, err),
             APIError::NotFound => (StatusCode::NOT_FOUND, "Resource not found".to_string()),
             // TODO: don't leak internal error messages to end user
             APIError::InternalServerError(err) => (StatusCode::INTERNAL_SERVER_ERROR, err),
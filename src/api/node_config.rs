@@ -0,0 +1,153 @@
+use crate::api::cache::Conditional;
+use crate::api::{from_db_error, internal_error, APIError};
+use crate::db::endpoint::{filter_recent_endpoints, EndpointFilter, EndpointKind};
+use crate::db::peer::{filter_by_type, filter_recent_peers, PeerFilter, PeerType};
+use crate::db::{chain, peer};
+use crate::node_config::{self, Source};
+use crate::registry::ChainInfo;
+use axum::{extract::Path, extract::State, http::header, response::Response};
+use sqlx::postgres::PgPool;
+
+const CONTENT_TYPE: &str = "application/toml";
+
+// No live peers or endpoints renders empty lists instead of 404.
+fn or_empty<T>(found: sqlx::Result<Vec<T>>) -> Result<Vec<T>, APIError> {
+    match found {
+        Err(sqlx::Error::RowNotFound) => Ok(vec![]),
+        found => found.map_err(from_db_error),
+    }
+}
+
+/// Get a config.toml snippet for a chain.
+///
+/// Renders [p2p] seeds and persistent_peers from live peers and [statesync] rpc_servers from live RPC
+/// endpoints. State sync is left disabled because trust_height and trust_hash must come from a
+/// recent block.
+#[utoipa::path(
+get,
+path = "/v1/{network}/{chain_name}/config.toml",
+responses(
+(status = 200, description = "Snippet rendered successfully", body = String, content_type = "application/toml"),
+(status = 304, description = "Snippet has not changed since If-None-Match or If-Modified-Since"),
+(status = 404, description = "Network or chain does not exist"),
+),
+params(
+("network" = String, Path, description = "mainnet or testnet"),
+("chain_name" = String, Path, description = "Chain name, e.g. cosmoshub"),
+),
+tag = "Node Config",
+)]
+pub async fn config_toml(
+    State(pool): State<PgPool>,
+    conditional: Conditional,
+    Path((network, chain_name)): Path<(String, String)>,
+) -> Result<Response, APIError> {
+    let mut conn = pool.acquire().await.map_err(internal_error)?;
+    let found = chain::find_chain(&mut conn, &network, &chain_name)
+        .await
+        .map_err(from_db_error)?;
+
+    let peers = or_empty(
+        filter_recent_peers(
+            &mut conn,
+            &PeerFilter {
+                chain_name: chain_name.clone(),
+                network: network.clone(),
+                include_all: false,
+                min_uptime: None,
+                sort: None,
+                include_discovered: false,
+            },
+        )
+        .await,
+    )?;
+    let rpcs = or_empty(
+        filter_recent_endpoints(
+            &mut conn,
+            &EndpointFilter {
+                chain_name: chain_name.clone(),
+                network: network.clone(),
+                kind: Some(EndpointKind::Rpc),
+                include_all: false,
+            },
+        )
+        .await,
+    )?;
+
+    let addresses = |peer_type| {
+        filter_by_type(&peers, peer_type)
+            .into_iter()
+            .map(|p| p.address)
+            .collect::<Vec<_>>()
+    };
+    let rpc_servers: Vec<String> = rpcs.iter().map(|e| e.address.clone()).collect();
+    let source = Source {
+        network: &network,
+        chain_name: &chain_name,
+        commit: &found.commit,
+    };
+    let body = node_config::config_toml(
+        &source,
+        &addresses(PeerType::Seed),
+        &addresses(PeerType::Persistent),
+        &rpc_servers,
+    );
+
+    // Liveness checks change the snippet between commits.
+    let peers_updated_at = peer::find_updated_at(&peers);
+    let rpcs_updated_at = rpcs.iter().map(|e| e.updated_at).max();
+    let last_modified = [Some(found.created_at), peers_updated_at, rpcs_updated_at]
+        .into_iter()
+        .flatten()
+        .max()
+        .unwrap_or(found.created_at);
+    Ok(conditional.respond(
+        &[&found.commit, &chain_name, &last_modified.to_rfc3339()],
+        last_modified,
+        ([(header::CONTENT_TYPE, CONTENT_TYPE)], body),
+    ))
+}
+
+/// Get an app.toml snippet for a chain.
+///
+/// Renders minimum-gas-prices from the fee tokens in chain.json, using each token's
+/// fixed_min_gas_price or else its low_gas_price.
+#[utoipa::path(
+get,
+path = "/v1/{network}/{chain_name}/app.toml",
+responses(
+(status = 200, description = "Snippet rendered successfully", body = String, content_type = "application/toml"),
+(status = 304, description = "Snippet has not changed since If-None-Match or If-Modified-Since"),
+(status = 404, description = "Network or chain does not exist"),
+),
+params(
+("network" = String, Path, description = "mainnet or testnet"),
+("chain_name" = String, Path, description = "Chain name, e.g. cosmoshub"),
+),
+tag = "Node Config",
+)]
+pub async fn app_toml(
+    State(pool): State<PgPool>,
+    conditional: Conditional,
+    Path((network, chain_name)): Path<(String, String)>,
+) -> Result<Response, APIError> {
+    let mut conn = pool.acquire().await.map_err(internal_error)?;
+    let found = chain::find_chain(&mut conn, &network, &chain_name)
+        .await
+        .map_err(from_db_error)?;
+    let info: ChainInfo = serde_json::from_value(found.chain_data).map_err(internal_error)?;
+
+    let source = Source {
+        network: &network,
+        chain_name: &chain_name,
+        commit: &found.commit,
+    };
+    let fee_tokens = info.fees.map(|fees| fees.fee_tokens).unwrap_or_default();
+    let body = node_config::app_toml(&source, &fee_tokens);
+
+    Ok(conditional.respond(
+        &[&found.commit, &chain_name],
+        found.created_at,
+        ([(header::CONTENT_TYPE, CONTENT_TYPE)], body),
+    ))
+}
@@ -1,12 +1,25 @@
+use crate::addrbook::{self, Entry};
+use crate::api::cache::Conditional;
 use crate::api::{from_db_error, internal_error, APIError, Meta};
 use crate::db::peer::{
-    filter_by_type, filter_recent_peers, find_commit, find_updated_at, PeerFilter, PeerType,
+    filter_by_type, filter_recent_peers, find_commit, find_updated_at, PeerFilter, PeerSort,
+    PeerType,
+};
+use axum::{
+    extract::Path,
+    extract::Query,
+    extract::State,
+    response::{IntoResponse, Response},
+    Json,
 };
-use axum::{extract::Path, extract::Query, extract::State, Json};
 use serde::{Deserialize, Serialize};
 use sqlx::postgres::PgPool;
+use std::time::Duration;
 use utoipa::ToSchema;
 
+// Per hostname while building an address book.
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(2);
+
 #[derive(Debug, Serialize, ToSchema)]
 pub struct PeerList {
     meta: Meta,
@@ -18,6 +31,43 @@ pub struct Peer {
     address: String,
     last_liveness_check: chrono::DateTime<chrono::Utc>,
     is_alive: bool,
+    /// Network (chain id) the peer reported during the p2p handshake, if ever checked that way.
+    #[schema(example = "cosmoshub-4")]
+    advertised_network: Option<String>,
+    /// True if the advertised network differs from the chain's chain_id. Mismatched peers are
+    /// excluded by default.
+    network_mismatch: bool,
+    /// Percentage of successful liveness checks over the last 30 days.
+    #[schema(example = 98.5)]
+    uptime: Option<f64>,
+    last_seen_alive: Option<chrono::DateTime<chrono::Utc>>,
+    /// Median time to connect over the last 30 days.
+    #[schema(example = 85.0)]
+    median_latency_ms: Option<f64>,
+    /// registry if listed in chain.json, or discovered if found by crawling the chain's RPC nodes.
+    #[schema(example = "registry")]
+    source: String,
+    /// Consecutive failed liveness checks, 0 if the last check succeeded. A peer is only marked dead
+    /// once this reaches the liveness job's threshold.
+    #[schema(example = 0)]
+    failure_streak: i32,
+}
+
+impl From<crate::db::peer::Peer> for Peer {
+    fn from(p: crate::db::peer::Peer) -> Self {
+        Peer {
+            address: p.address,
+            last_liveness_check: p.updated_at,
+            is_alive: p.is_alive,
+            advertised_network: p.advertised_network,
+            network_mismatch: p.network_mismatch,
+            uptime: p.uptime,
+            last_seen_alive: p.last_seen_alive,
+            median_latency_ms: p.median_latency_ms,
+            source: p.source,
+            failure_streak: p.failure_streak,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -26,38 +76,92 @@ pub struct PeerResult {
     pub persistent: Vec<Peer>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct PeerParams {
+    #[serde(default)]
     include_all: bool,
+    min_uptime: Option<f64>,
+    sort: Option<PeerSort>,
+    #[serde(default)]
+    include_discovered: bool,
 }
 
 /// Get chain's live seeds and persistent peers.
 /// A background process periodically checks peers for liveness. If a peer cannot be reached,
-/// it is excluded from this response by default.
+/// or it advertises a network other than the chain's chain_id, it is excluded from this response by default.
+/// Peers found by the crawler are only included with include_discovered.
 #[utoipa::path(
 get,
 path = "/v1/{network}/{chain_name}/peers",
 responses(
 (status = 200, description = "Peers found successfully", body = PeerList),
+(status = 304, description = "Peers have not changed since If-None-Match or If-Modified-Since"),
 (status = 404, description = "Network or chain does not exist, or chain does not have any peers"),
 ),
 params(
 ("network" = String, Path, description = "mainnet or testnet"),
 ("chain_name" = String, Path, description = "Chain name, e.g. cosmoshub"),
 ("include_all" = Option<bool>, Query, description = "If true, include all peers regardless of liveness"),
+("min_uptime" = Option<f64>, Query, description = "Only include peers with at least this uptime percentage over the last 30 days, e.g. 90"),
+("sort" = Option<String>, Query, description = "Set to latency to order peers by median latency, fastest first"),
+("include_discovered" = Option<bool>, Query, description = "If true, also include persistent peers found by crawling the chain's RPC nodes"),
 ),
 tag = "Peers",
 )]
 pub async fn list_peers(
     State(pool): State<PgPool>,
+    conditional: Conditional,
     Path((network, chain_name)): Path<(String, String)>,
     params: Option<Query<PeerParams>>,
-) -> Result<Json<PeerList>, APIError> {
-    let include_all = params.map(|p| p.include_all).unwrap_or(false);
+) -> Result<Response, APIError> {
+    let found = find_peers(&pool, network, chain_name, params).await?;
+    Ok(found.respond(&conditional, Json))
+}
+
+struct FoundPeers {
+    list: PeerList,
+    chain_name: String,
+}
+
+impl FoundPeers {
+    /// Peers change between commits whenever a liveness check updates them.
+    fn respond<B: IntoResponse>(
+        self,
+        conditional: &Conditional,
+        body: impl FnOnce(PeerList) -> B,
+    ) -> Response {
+        let commit = self.list.meta.commit.clone();
+        let updated_at = self.list.meta.updated_at;
+        conditional.respond(
+            &[&commit, &self.chain_name, &updated_at.to_rfc3339()],
+            updated_at,
+            body(self.list),
+        )
+    }
+}
+
+fn join_addresses(peers: Vec<Peer>) -> String {
+    peers
+        .into_iter()
+        .map(|p| p.address)
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+async fn find_peers(
+    pool: &PgPool,
+    network: String,
+    chain_name: String,
+    params: Option<Query<PeerParams>>,
+) -> Result<FoundPeers, APIError> {
+    let params = params.map(|p| p.0).unwrap_or_default();
     let filter = PeerFilter {
-        chain_name,
+        chain_name: chain_name.clone(),
         network,
-        include_all,
+        include_all: params.include_all,
+        min_uptime: params.min_uptime,
+        sort: params.sort,
+        include_discovered: params.include_discovered,
     };
 
     let mut conn = pool.acquire().await.map_err(internal_error)?;
@@ -73,24 +177,19 @@ pub async fn list_peers(
         result: PeerResult {
             seeds: filter_by_type(&peers, PeerType::Seed)
                 .into_iter()
-                .map(|p| Peer {
-                    address: p.address,
-                    last_liveness_check: p.updated_at,
-                    is_alive: p.is_alive,
-                })
+                .map(Peer::from)
                 .collect(),
             persistent: filter_by_type(&peers, PeerType::Persistent)
                 .into_iter()
-                .map(|p| Peer {
-                    address: p.address,
-                    last_liveness_check: p.updated_at,
-                    is_alive: p.is_alive,
-                })
+                .map(Peer::from)
                 .collect(),
         },
     };
 
-    Ok(Json(resp))
+    Ok(FoundPeers {
+        list: resp,
+        chain_name,
+    })
 }
 
 /// Get a chain's live seeds as a comma-separated string for use in config.toml.
@@ -101,32 +200,27 @@ get,
 path = "/v1/{network}/{chain_name}/peers/seed_string",
 responses(
 (status = 200, description = "Seeds found successfully", body = String),
+(status = 304, description = "Seeds have not changed since If-None-Match or If-Modified-Since"),
 (status = 404, description = "Network or chain does not exist, or chain does not have any seeds"),
 ),
 params(
 ("network" = String, Path, description = "mainnet or testnet"),
 ("chain_name" = String, Path, description = "Chain name, e.g. cosmoshub"),
 ("include_all" = Option<bool>, Query, description = "If true, include all seeds regardless of liveness"),
+("min_uptime" = Option<f64>, Query, description = "Only include seeds with at least this uptime percentage over the last 30 days, e.g. 90"),
+("sort" = Option<String>, Query, description = "Set to latency to order seeds by median latency, fastest first"),
+("include_discovered" = Option<bool>, Query, description = "If true, also include peers found by crawling the chain's RPC nodes. These are always persistent peers, so seeds are unaffected"),
 ),
 tag = "Peers",
 )]
 pub async fn seed_string(
     State(pool): State<PgPool>,
+    conditional: Conditional,
     Path((network, chain_name)): Path<(String, String)>,
     params: Option<Query<PeerParams>>,
-) -> Result<String, APIError> {
-    let seeds = list_peers(State(pool), Path((network, chain_name)), params)
-        .await?
-        .0
-        .result;
-
-    let seeds = seeds
-        .seeds
-        .into_iter()
-        .map(|p| p.address)
-        .collect::<Vec<String>>();
-
-    Ok(seeds.join(","))
+) -> Result<Response, APIError> {
+    let found = find_peers(&pool, network, chain_name, params).await?;
+    Ok(found.respond(&conditional, |list| join_addresses(list.result.seeds)))
 }
 
 /// Get a chain's live persistent peers as a comma-separated string for use in config.toml.
@@ -137,30 +231,72 @@ get,
 path = "/v1/{network}/{chain_name}/peers/peer_string",
 responses(
 (status = 200, description = "Peers found successfully", body = String),
+(status = 304, description = "Peers have not changed since If-None-Match or If-Modified-Since"),
 (status = 404, description = "Network or chain does not exist, or chain does not have any persistent peers"),
 ),
 params(
 ("network" = String, Path, description = "mainnet or testnet"),
 ("chain_name" = String, Path, description = "Chain name, e.g. cosmoshub"),
 ("include_all" = Option<bool>, Query, description = "If true, include all peers regardless of liveness"),
+("min_uptime" = Option<f64>, Query, description = "Only include peers with at least this uptime percentage over the last 30 days, e.g. 90"),
+("sort" = Option<String>, Query, description = "Set to latency to order peers by median latency, fastest first"),
+("include_discovered" = Option<bool>, Query, description = "If true, also include persistent peers found by crawling the chain's RPC nodes"),
 ),
 tag = "Peers",
 )]
 pub async fn persistent_peer_string(
     State(pool): State<PgPool>,
+    conditional: Conditional,
     Path((network, chain_name)): Path<(String, String)>,
     params: Option<Query<PeerParams>>,
-) -> Result<String, APIError> {
-    let peers = list_peers(State(pool), Path((network, chain_name)), params)
-        .await?
-        .0
-        .result;
-
-    let peers = peers
-        .persistent
-        .into_iter()
-        .map(|p| p.address)
-        .collect::<Vec<String>>();
+) -> Result<Response, APIError> {
+    let found = find_peers(&pool, network, chain_name, params).await?;
+    Ok(found.respond(&conditional, |list| join_addresses(list.result.persistent)))
+}
+
+/// Get a chain's live peers as a CometBFT address book.
+/// Save the response as config/addrbook.json so a new node starts with known-good peers. Seeds and
+/// persistent peers are both included. Hostnames are resolved to IPs because the address book only
+/// accepts IPs; peers whose hostname does not resolve are left out.
+#[utoipa::path(
+get,
+path = "/v1/{network}/{chain_name}/peers/addrbook.json",
+responses(
+(status = 200, description = "Address book built successfully", body = AddrBook),
+(status = 304, description = "Peers have not changed since If-None-Match or If-Modified-Since"),
+(status = 404, description = "Network or chain does not exist, or chain does not have any peers"),
+),
+params(
+("network" = String, Path, description = "mainnet or testnet"),
+("chain_name" = String, Path, description = "Chain name, e.g. cosmoshub"),
+("include_all" = Option<bool>, Query, description = "If true, include all peers regardless of liveness"),
+("min_uptime" = Option<f64>, Query, description = "Only include peers with at least this uptime percentage over the last 30 days, e.g. 90"),
+("sort" = Option<String>, Query, description = "Set to latency to order peers by median latency, fastest first"),
+("include_discovered" = Option<bool>, Query, description = "If true, also include persistent peers found by crawling the chain's RPC nodes"),
+),
+tag = "Peers",
+)]
+pub async fn addrbook(
+    State(pool): State<PgPool>,
+    conditional: Conditional,
+    Path((network, chain_name)): Path<(String, String)>,
+    params: Option<Query<PeerParams>>,
+) -> Result<Response, APIError> {
+    let name = format!("{}/{}", network, chain_name);
+    let found = find_peers(&pool, network, chain_name, params).await?;
+
+    let result = &found.list.result;
+    let entries: Vec<Entry> = result
+        .seeds
+        .iter()
+        .chain(result.persistent.iter())
+        .map(|p| Entry {
+            address: p.address.clone(),
+            last_attempt: p.last_liveness_check,
+            last_success: p.last_seen_alive,
+        })
+        .collect();
+    let book = addrbook::build(&name, &entries, RESOLVE_TIMEOUT).await;
 
-    Ok(peers.join(","))
+    Ok(found.respond(&conditional, |_| Json(book)))
 }
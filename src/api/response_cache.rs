@@ -0,0 +1,291 @@
+//! Caches successful GET responses in memory so repeated requests skip Postgres. Hydrate and
+//! liveness send a notification when they change data, which clears the cache.
+
+use crate::api::cache::Conditional;
+use crate::db;
+use axum::{
+    body::{Bytes, HttpBody},
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, Method, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sqlx::postgres::PgPool;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+#[derive(Debug)]
+struct Entry {
+    response: CachedResponse,
+    inserted: Instant,
+    // Position in Entries::recent
+    used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    entries: HashMap<String, Entry>,
+    // Keys by last use, least recent first.
+    recent: BTreeMap<u64, String>,
+    uses: u64,
+    // Bumped on every clear so responses built from old data are not stored.
+    generation: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub entries: usize,
+}
+
+/// A least recently used cache of responses keyed on path and query.
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    max_entries: usize,
+    ttl: Duration,
+    state: Arc<Mutex<Entries>>,
+    counters: Arc<Counters>,
+}
+
+impl ResponseCache {
+    /// A max_entries of 0 disables caching.
+    pub fn new(max_entries: usize, ttl: Duration) -> ResponseCache {
+        ResponseCache {
+            max_entries,
+            ttl,
+            state: Arc::new(Mutex::new(Entries::default())),
+            counters: Arc::new(Counters::default()),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<CachedResponse> {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        let found = match state.entries.get_mut(key) {
+            Some(entry) if entry.inserted.elapsed() < self.ttl => {
+                state.recent.remove(&entry.used);
+                state.uses += 1;
+                entry.used = state.uses;
+                state.recent.insert(entry.used, key.to_string());
+                Some(entry.response.clone())
+            }
+            Some(_) => {
+                if let Some(entry) = state.entries.remove(key) {
+                    state.recent.remove(&entry.used);
+                }
+                None
+            }
+            None => None,
+        };
+        let counter = match found {
+            Some(_) => &self.counters.hits,
+            None => &self.counters.misses,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        found
+    }
+
+    fn generation(&self) -> u64 {
+        self.state.lock().unwrap().generation
+    }
+
+    /// Stores the response unless the cache was cleared since generation was read.
+    fn insert(&self, key: String, response: CachedResponse, generation: u64) {
+        if self.max_entries == 0 {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        if state.generation != generation {
+            return;
+        }
+        if let Some(old) = state.entries.remove(&key) {
+            state.recent.remove(&old.used);
+        }
+        while state.entries.len() >= self.max_entries {
+            let Some((_, oldest)) = state.recent.pop_first() else {
+                break;
+            };
+            state.entries.remove(&oldest);
+            self.counters.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+        state.uses += 1;
+        let used = state.uses;
+        state.recent.insert(used, key.clone());
+        state.entries.insert(
+            key,
+            Entry {
+                response,
+                inserted: Instant::now(),
+                used,
+            },
+        );
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            evictions: self.counters.evictions.load(Ordering::Relaxed),
+            entries: self.state.lock().unwrap().entries.len(),
+        }
+    }
+
+    /// Removes every entry. reason is logged.
+    pub fn clear(&self, reason: &str) {
+        let mut state = self.state.lock().unwrap();
+        let cleared = state.entries.len();
+        state.entries.clear();
+        state.recent.clear();
+        state.generation += 1;
+        drop(state);
+        tracing::info!("Cleared {} cached responses after {}", cleared, reason);
+    }
+}
+
+/// Middleware that serves GET requests from the cache. Conditional requests are answered from the
+/// cached ETag and Last-Modified.
+pub async fn response_cache<B>(
+    State(cache): State<ResponseCache>,
+    mut request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    if request.method() != Method::GET || cache.max_entries == 0 {
+        return next.run(request).await;
+    }
+    let key = request.uri().to_string();
+    let conditional = Conditional::new(request.uri().clone(), request.headers());
+
+    let (cached, status) = match cache.get(&key) {
+        Some(cached) => (cached, "hit"),
+        None => {
+            let generation = cache.generation();
+            // The cached response must be the full one, not a 304 for this client.
+            request.headers_mut().remove(header::IF_NONE_MATCH);
+            request.headers_mut().remove(header::IF_MODIFIED_SINCE);
+            let response = next.run(request).await;
+            if response.status() != StatusCode::OK {
+                return response;
+            }
+            let (parts, mut body) = response.into_parts();
+            let mut buf = Vec::new();
+            while let Some(chunk) = body.data().await {
+                match chunk {
+                    Ok(chunk) => buf.extend_from_slice(&chunk),
+                    Err(err) => {
+                        tracing::error!("Failed to read response body: {:?}", err);
+                        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+                    }
+                }
+            }
+            let cached = CachedResponse {
+                headers: parts.headers,
+                body: Bytes::from(buf),
+            };
+            cache.insert(key, cached.clone(), generation);
+            (cached, "miss")
+        }
+    };
+
+    let mut response = conditional.respond_cached(cached);
+    response
+        .headers_mut()
+        .insert("x-cache", HeaderValue::from_static(status));
+    response
+}
+
+/// Clears the cache whenever hydrate or liveness change data. Runs until the server stops.
+pub async fn invalidate_on_update(pool: PgPool, cache: ResponseCache) {
+    loop {
+        let mut listener = match db::notify::listen_updates(&pool).await {
+            Ok(listener) => listener,
+            Err(err) => {
+                tracing::error!("Failed to listen for updates, retrying: {:?}", err);
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                continue;
+            }
+        };
+        loop {
+            match listener.try_recv().await {
+                Ok(Some(notification)) => cache.clear(notification.payload()),
+                // Updates may have been missed while reconnecting.
+                Ok(None) => cache.clear("lost listener connection"),
+                Err(err) => {
+                    tracing::error!("Failed to receive updates: {:?}", err);
+                    cache.clear("listener error");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(body: &'static str) -> CachedResponse {
+        CachedResponse {
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(body.as_bytes()),
+        }
+    }
+
+    fn body(found: Option<CachedResponse>) -> Option<Bytes> {
+        found.map(|r| r.body)
+    }
+
+    #[test]
+    fn test_response_cache() {
+        let cache = ResponseCache::new(2, Duration::from_secs(60));
+        let generation = cache.generation();
+        cache.insert("/a".to_string(), response("a"), generation);
+        cache.insert("/b".to_string(), response("b"), generation);
+        assert_eq!(body(cache.get("/a")), Some(Bytes::from("a")));
+
+        // /b is the least recently used.
+        cache.insert("/c".to_string(), response("c"), generation);
+        assert_eq!(body(cache.get("/b")), None);
+        assert_eq!(body(cache.get("/a")), Some(Bytes::from("a")));
+        assert_eq!(body(cache.get("/c")), Some(Bytes::from("c")));
+
+        cache.clear("test");
+        assert_eq!(body(cache.get("/a")), None);
+
+        // A response built before the clear is dropped.
+        cache.insert("/a".to_string(), response("a"), generation);
+        assert_eq!(body(cache.get("/a")), None);
+
+        assert_eq!(cache.counters.hits.load(Ordering::Relaxed), 3);
+        assert_eq!(cache.counters.misses.load(Ordering::Relaxed), 3);
+        assert_eq!(cache.counters.evictions.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_response_cache_ttl() {
+        let cache = ResponseCache::new(2, Duration::ZERO);
+        cache.insert("/a".to_string(), response("a"), cache.generation());
+        assert_eq!(body(cache.get("/a")), None);
+        assert!(cache.state.lock().unwrap().entries.is_empty());
+
+        let disabled = ResponseCache::new(0, Duration::from_secs(60));
+        disabled.insert("/a".to_string(), response("a"), disabled.generation());
+        assert_eq!(body(disabled.get("/a")), None);
+    }
+}
@@ -1,11 +1,34 @@
+use crate::addrbook::{AddrBook, KnownAddress, NetAddress};
+use crate::api::asset::{list_assets, AssetSearchItem, AssetSearchList};
+use crate::api::cache::{cache_control, CachePolicy};
 use crate::api::chain::{
-    get_chain_asset_list, get_chain_data, list_chains, ChainList, ChainListItem,
+    get_chain_asset_list, get_chain_data, get_chain_diff, get_chain_validation, list_chains,
+    AssetListResponse, ChainDiff, ChainDiffResponse, ChainInfoResponse, ChainList, ChainListItem,
+    ChainValidation, ChainValidationResponse,
 };
+use crate::api::commit::{list_commits, Commit, CommitList};
+use crate::api::endpoint::{list_endpoints, Endpoint, EndpointList};
+use crate::api::export::export;
+use crate::api::ibc::{
+    get_ibc_denom, get_ibc_path, list_chain_ibc_paths, DenomTrace, DenomTraceResponse, IbcChain,
+    IbcChannel, IbcChannelEnd, IbcPath, IbcPathList, IbcPathResponse,
+};
+use crate::api::lookup::{lookup_chains, LookupItem, LookupList};
+use crate::api::metrics::track_requests;
+use crate::api::node_config::{app_toml, config_toml};
 use crate::api::peer::{
-    list_peers, persistent_peer_string, seed_string, Peer, PeerList, PeerResult,
+    addrbook, list_peers, persistent_peer_string, seed_string, Peer, PeerList, PeerResult,
 };
+use crate::api::response_cache::{response_cache, ResponseCache};
 use crate::api::Meta;
-use axum::{routing::get, Router};
+use crate::metrics::HttpMetrics;
+use crate::registry::{
+    ApiEndpoint, Apis, Asset, AssetList, AssetTrace, ChainInfo, DenomUnit, Explorer, FeeToken,
+    Fees, LogoUris, PeerInfo, Peers, Staking, StakingToken, TraceChain, TraceCounterparty,
+};
+use crate::validation::ValidationError;
+use axum::{middleware, routing::get, Router};
+use std::sync::Arc;
 use tower_http::trace::{self, TraceLayer};
 use tracing::Level;
 use utoipa::OpenApi;
@@ -14,28 +37,118 @@ use utoipa_swagger_ui::SwaggerUi;
 #[derive(OpenApi)]
 #[openapi(
     paths(
+        crate::api::asset::list_assets,
         crate::api::chain::get_chain_asset_list,
         crate::api::chain::get_chain_data,
+        crate::api::chain::get_chain_diff,
+        crate::api::chain::get_chain_validation,
         crate::api::chain::list_chains,
+        crate::api::commit::list_commits,
+        crate::api::endpoint::list_endpoints,
+        crate::api::export::export,
+        crate::api::ibc::get_ibc_denom,
+        crate::api::ibc::get_ibc_path,
+        crate::api::ibc::list_chain_ibc_paths,
+        crate::api::lookup::lookup_chains,
+        crate::api::node_config::app_toml,
+        crate::api::node_config::config_toml,
+        crate::api::peer::addrbook,
         crate::api::peer::list_peers,
         crate::api::peer::persistent_peer_string,
         crate::api::peer::seed_string,
     ),
-    components(schemas(Peer, PeerList, PeerResult, Meta, ChainList, ChainListItem))
+    components(schemas(
+        AddrBook,
+        KnownAddress,
+        NetAddress,
+        Peer,
+        PeerList,
+        PeerResult,
+        Meta,
+        ChainDiff,
+        ChainDiffResponse,
+        ChainInfoResponse,
+        AssetListResponse,
+        ChainInfo,
+        Fees,
+        FeeToken,
+        Staking,
+        StakingToken,
+        Peers,
+        PeerInfo,
+        Apis,
+        ApiEndpoint,
+        Explorer,
+        AssetList,
+        Asset,
+        AssetTrace,
+        TraceCounterparty,
+        TraceChain,
+        AssetSearchItem,
+        AssetSearchList,
+        DenomUnit,
+        LogoUris,
+        ChainList,
+        ChainListItem,
+        ChainValidation,
+        ChainValidationResponse,
+        Commit,
+        CommitList,
+        Endpoint,
+        EndpointList,
+        IbcPath,
+        IbcPathList,
+        IbcPathResponse,
+        IbcChain,
+        IbcChannel,
+        IbcChannelEnd,
+        DenomTrace,
+        DenomTraceResponse,
+        LookupItem,
+        LookupList,
+        ValidationError
+    ))
 )]
 struct ApiDoc;
 
-pub fn new() -> Router<sqlx::postgres::PgPool> {
+pub fn new(
+    cache_policy: CachePolicy,
+    cache: ResponseCache,
+    http_metrics: Arc<HttpMetrics>,
+) -> Router<sqlx::postgres::PgPool> {
     let v1_routes = Router::new()
+        .route("/commits", get(list_commits))
+        .route("/lookup", get(lookup_chains))
         .route("/:network/chains", get(list_chains))
+        .route("/:network/assets", get(list_assets))
         .route("/:network/:chain_name", get(get_chain_data))
         .route("/:network/:chain_name/assetlist", get(get_chain_asset_list))
+        .route("/:network/:chain_name/diff", get(get_chain_diff))
+        .route(
+            "/:network/:chain_name/validation",
+            get(get_chain_validation),
+        )
+        .route("/:network/:chain_name/endpoints", get(list_endpoints))
+        .route("/:network/:chain_name/config.toml", get(config_toml))
+        .route("/:network/:chain_name/app.toml", get(app_toml))
+        .route("/:network/:chain_name/ibc", get(list_chain_ibc_paths))
+        .route("/:network/ibc/:chain_a/:chain_b", get(get_ibc_path))
+        .route("/:network/:chain_name/denoms/ibc/:hash", get(get_ibc_denom))
         .route("/:network/:chain_name/peers", get(list_peers))
         .route("/:network/:chain_name/peers/seed_string", get(seed_string))
         .route(
             "/:network/:chain_name/peers/peer_string",
             get(persistent_peer_string),
         )
+        .route("/:network/:chain_name/peers/addrbook.json", get(addrbook))
+        .route_layer(middleware::from_fn_with_state(cache, response_cache))
+        // Added after the response cache so streamed exports are not buffered.
+        .route("/export", get(export))
+        .route_layer(middleware::from_fn_with_state(
+            Arc::new(cache_policy),
+            cache_control,
+        ))
+        .route_layer(middleware::from_fn_with_state(http_metrics, track_requests))
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(trace::DefaultMakeSpan::new().level(Level::INFO))
@@ -0,0 +1,401 @@
+//! Discovers peers beyond the ones chain.json lists by asking RPC nodes who they are connected to.
+//!
+//! Crawling starts from a chain's alive RPC endpoints and the default RPC port on the hosts of its
+//! known peers. Each node's /net_info lists its peers, whose RPCs are crawled in turn until
+//! max_rpcs requests were made. A new peer is only kept if it passes the handshake check and
+//! advertises the chain's chain_id.
+
+use crate::addrbook::parse_address;
+use crate::db::peer::CrawlChain;
+use crate::liveness;
+use futures::stream::{self, StreamExt};
+use serde::Deserialize;
+use std::collections::{HashSet, VecDeque};
+use std::net::IpAddr;
+use std::time::Duration;
+
+// CometBFT's default rpc.laddr port.
+const DEFAULT_RPC_PORT: u16 = 26657;
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Max /net_info requests per chain.
+    pub max_rpcs: usize,
+    /// Max new peers per chain.
+    pub max_peers: usize,
+    /// For each request and handshake.
+    pub timeout: Duration,
+    /// Max requests or handshakes in flight.
+    pub concurrency: usize,
+    /// Max handshakes in flight against one host.
+    pub per_host_limit: usize,
+    /// Min time between the starts of two handshakes against one host.
+    pub per_host_interval: Duration,
+}
+
+impl Options {
+    /// A checker for candidate peers. Handshakes go through it so they get the same per host
+    /// limits as liveness checks.
+    pub fn checker(&self) -> anyhow::Result<liveness::Checker> {
+        liveness::Checker::new(liveness::Options {
+            check_mode: liveness::CheckMode::Handshake,
+            concurrency: self.concurrency,
+            timeout: self.timeout,
+            retries: 0,
+            per_host_limit: self.per_host_limit,
+            per_host_interval: self.per_host_interval,
+            thresholds: Default::default(),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct NetInfoResponse {
+    result: NetInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct NetInfo {
+    #[serde(default)]
+    peers: Vec<NetInfoPeer>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct NetInfoPeer {
+    node_info: NodeInfo,
+    remote_ip: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct NodeInfo {
+    id: String,
+    listen_addr: String,
+    network: String,
+    #[serde(default)]
+    other: NodeInfoOther,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct NodeInfoOther {
+    #[serde(default)]
+    rpc_address: String,
+}
+
+/// Crawls the chain's RPC nodes and returns the addresses of new peers that passed the handshake
+/// check. Returns nothing if chain.json has no chain_id to verify peers against.
+pub async fn crawl(
+    client: &reqwest::Client,
+    checker: &liveness::Checker,
+    chain: &CrawlChain,
+    opts: &Options,
+) -> Vec<String> {
+    let Some(chain_id) = chain.chain_id.as_deref() else {
+        return vec![];
+    };
+    let candidates = discover(client, chain, chain_id, opts).await;
+    tracing::info!(
+        "Found {} candidate peers for {} {}, checking...",
+        candidates.len(),
+        chain.network,
+        chain.chain_name
+    );
+
+    stream::iter(candidates)
+        .map(|address| async move {
+            match checker.check_peer(&address).await {
+                Ok(check) if check.advertised_network.as_deref() == Some(chain_id) => Some(address),
+                Ok(check) => {
+                    tracing::debug!(
+                        "Peer {} advertised {:?}, not {}",
+                        address,
+                        check.advertised_network,
+                        chain_id
+                    );
+                    None
+                }
+                Err(err) => {
+                    tracing::debug!("Peer {} is not alive: {}", address, err);
+                    None
+                }
+            }
+        })
+        .buffer_unordered(opts.concurrency)
+        .filter_map(|address| async move { address })
+        .take(opts.max_peers)
+        .collect()
+        .await
+}
+
+// Breadth first over /net_info. Returns unchecked addresses of peers the chain does not have yet.
+async fn discover(
+    client: &reqwest::Client,
+    chain: &CrawlChain,
+    chain_id: &str,
+    opts: &Options,
+) -> Vec<String> {
+    let mut queued: HashSet<String> = HashSet::new();
+    let mut queue: VecDeque<String> = VecDeque::new();
+    let starts = chain
+        .rpcs
+        .iter()
+        .map(|rpc| rpc.trim_end_matches('/').to_string())
+        .chain(chain.peers.iter().filter_map(|peer| host_rpc_url(peer)));
+    for rpc in starts {
+        if queued.insert(rpc.clone()) {
+            queue.push_back(rpc);
+        }
+    }
+
+    let known: HashSet<&str> = chain.peers.iter().map(String::as_str).collect();
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut candidates = vec![];
+    let mut crawled = 0;
+    while !queue.is_empty() && crawled < opts.max_rpcs {
+        let batch: Vec<String> = queue
+            .drain(..queue.len().min(opts.max_rpcs - crawled))
+            .collect();
+        crawled += batch.len();
+
+        let results: Vec<_> = stream::iter(batch)
+            .map(|rpc| async move {
+                let result = net_info(client, &rpc).await;
+                (rpc, result)
+            })
+            .buffer_unordered(opts.concurrency)
+            .collect()
+            .await;
+
+        for (rpc, result) in results {
+            let peers = match result {
+                Ok(peers) => peers,
+                Err(err) => {
+                    tracing::debug!("Failed to get net_info from {}: {}", rpc, err);
+                    continue;
+                }
+            };
+            for peer in peers {
+                if peer.node_info.network != chain_id {
+                    continue;
+                }
+                if let Some(url) = rpc_url(&peer) {
+                    if queued.insert(url.clone()) {
+                        queue.push_back(url);
+                    }
+                }
+                if let Some(address) = peer_address(&peer) {
+                    if !known.contains(address.as_str()) && seen.insert(address.clone()) {
+                        candidates.push(address);
+                    }
+                }
+            }
+        }
+    }
+    candidates
+}
+
+async fn net_info(client: &reqwest::Client, rpc: &str) -> anyhow::Result<Vec<NetInfoPeer>> {
+    let resp = client
+        .get(format!("{}/net_info", rpc))
+        .send()
+        .await?
+        .error_for_status()?;
+    let body: NetInfoResponse = resp.json().await?;
+    Ok(body.result.peers)
+}
+
+// id@ip:port from the IP the node sees the peer connecting from and the port the peer listens on.
+// listen_addr is often 0.0.0.0, so its host cannot be used.
+fn peer_address(peer: &NetInfoPeer) -> Option<String> {
+    let ip = public_ip(&peer.remote_ip)?;
+    let (_, port) = split_port(&peer.node_info.listen_addr)?;
+    if peer.node_info.id.is_empty() {
+        return None;
+    }
+    Some(format!("{}@{}", peer.node_info.id, socket_addr(ip, port)))
+}
+
+// The peer's RPC, unless it only listens on localhost.
+fn rpc_url(peer: &NetInfoPeer) -> Option<String> {
+    let ip = public_ip(&peer.remote_ip)?;
+    let (host, port) = split_port(&peer.node_info.other.rpc_address)?;
+    if host == "localhost" || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback()) {
+        return None;
+    }
+    Some(format!("http://{}", socket_addr(ip, port)))
+}
+
+// A registry peer's host often runs an RPC on the default port.
+fn host_rpc_url(peer: &str) -> Option<String> {
+    let (_, host, _) = parse_address(peer)?;
+    match host.parse::<IpAddr>() {
+        Ok(ip) => Some(format!("http://{}", socket_addr(ip, DEFAULT_RPC_PORT))),
+        Err(_) => Some(format!("http://{}:{}", host, DEFAULT_RPC_PORT)),
+    }
+}
+
+// Splits tcp://host:port or host:port.
+fn split_port(addr: &str) -> Option<(&str, u16)> {
+    let addr = addr.split_once("://").map_or(addr, |(_, addr)| addr);
+    let (host, port) = addr.rsplit_once(':')?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    Some((host, port.parse().ok()?))
+}
+
+fn socket_addr(ip: IpAddr, port: u16) -> String {
+    std::net::SocketAddr::new(ip, port).to_string()
+}
+
+// Private and local addresses are only reachable from the node's own network.
+fn public_ip(ip: &str) -> Option<IpAddr> {
+    let ip: IpAddr = ip.parse().ok()?;
+    let local = match ip {
+        IpAddr::V4(ip) => {
+            ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+        }
+        IpAddr::V6(ip) => {
+            let first = ip.segments()[0];
+            ip.is_loopback()
+                || ip.is_unspecified()
+                // Unique local fc00::/7 and link local fe80::/10
+                || first & 0xfe00 == 0xfc00
+                || first & 0xffc0 == 0xfe80
+        }
+    };
+    (!local).then_some(ip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Json, Router};
+    use serde_json::json;
+
+    fn net_info_peer(id: &str, remote_ip: &str, network: &str, rpc_address: &str) -> NetInfoPeer {
+        serde_json::from_value(json!({
+            "node_info": {
+                "id": id,
+                "listen_addr": "tcp://0.0.0.0:26656",
+                "network": network,
+                "other": {"rpc_address": rpc_address}
+            },
+            "remote_ip": remote_ip
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_peer_address() {
+        let peer = net_info_peer("abc", "203.0.113.7", "cosmoshub-4", "");
+        assert_eq!(
+            peer_address(&peer),
+            Some("abc@203.0.113.7:26656".to_string())
+        );
+        let peer = net_info_peer("abc", "2001:db8::1", "cosmoshub-4", "");
+        assert_eq!(
+            peer_address(&peer),
+            Some("abc@[2001:db8::1]:26656".to_string())
+        );
+
+        for remote_ip in [
+            "10.0.0.5",
+            "192.168.1.2",
+            "127.0.0.1",
+            "fd00::1",
+            "not an ip",
+        ] {
+            let peer = net_info_peer("abc", remote_ip, "cosmoshub-4", "");
+            assert_eq!(peer_address(&peer), None, "{}", remote_ip);
+        }
+        let peer = net_info_peer("", "203.0.113.7", "cosmoshub-4", "");
+        assert_eq!(peer_address(&peer), None);
+    }
+
+    #[test]
+    fn test_rpc_url() {
+        let peer = net_info_peer("abc", "203.0.113.7", "cosmoshub-4", "tcp://0.0.0.0:36657");
+        assert_eq!(rpc_url(&peer), Some("http://203.0.113.7:36657".to_string()));
+
+        let peer = net_info_peer("abc", "203.0.113.7", "cosmoshub-4", "tcp://127.0.0.1:26657");
+        assert_eq!(rpc_url(&peer), None);
+        let peer = net_info_peer("abc", "203.0.113.7", "cosmoshub-4", "");
+        assert_eq!(rpc_url(&peer), None);
+
+        assert_eq!(
+            host_rpc_url("abc@seed.example.com:26656"),
+            Some("http://seed.example.com:26657".to_string())
+        );
+        assert_eq!(
+            host_rpc_url("abc@[2001:db8::1]:26656"),
+            Some("http://[2001:db8::1]:26657".to_string())
+        );
+        assert_eq!(host_rpc_url("not an address"), None);
+    }
+
+    async fn serve_stub(router: Router) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(router.into_make_service())
+                .await
+                .unwrap();
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_discover() {
+        let router = Router::new().route(
+            "/net_info",
+            get(|| async {
+                // Loopback RPC addresses keep the crawl from leaving the stub.
+                let peer = |id: &str, remote_ip: &str, network: &str| {
+                    json!({
+                        "node_info": {
+                            "id": id,
+                            "listen_addr": "tcp://0.0.0.0:26656",
+                            "network": network,
+                            "other": {"rpc_address": "tcp://127.0.0.1:26657"}
+                        },
+                        "remote_ip": remote_ip
+                    })
+                };
+                Json(json!({"result": {"peers": [
+                    peer("new", "203.0.113.7", "cosmoshub-4"),
+                    peer("new", "203.0.113.7", "cosmoshub-4"),
+                    peer("known", "203.0.113.8", "cosmoshub-4"),
+                    peer("other", "203.0.113.9", "theta-testnet-001"),
+                    peer("private", "10.0.0.5", "cosmoshub-4"),
+                ]}}))
+            }),
+        );
+        let base = serve_stub(router).await;
+        let timeout = Duration::from_secs(3);
+        let client = reqwest::Client::builder().timeout(timeout).build().unwrap();
+        let chain = CrawlChain {
+            id: 1,
+            network: "mainnet".to_string(),
+            chain_name: "cosmoshub".to_string(),
+            chain_id: Some("cosmoshub-4".to_string()),
+            rpcs: vec![format!("{}/", base)],
+            peers: vec!["known@203.0.113.8:26656".to_string()],
+        };
+        let opts = Options {
+            // Only the stub. The known peer's host is never reached.
+            max_rpcs: 1,
+            max_peers: 10,
+            timeout,
+            concurrency: 2,
+            per_host_limit: 1,
+            per_host_interval: Duration::ZERO,
+        };
+
+        let found = discover(&client, &chain, "cosmoshub-4", &opts).await;
+        assert_eq!(found, vec!["new@203.0.113.7:26656"]);
+    }
+}
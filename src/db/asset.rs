@@ -0,0 +1,279 @@
+use sqlx::{types::JsonValue, PgExecutor};
+
+/// Copies the assets of a newly inserted chain from its asset_data. Unchanged chains keep theirs.
+pub async fn insert_assets(executor: impl PgExecutor<'_>, chain_id: i64) -> sqlx::Result<u64> {
+    // Assets without a base cannot be searched, so they are skipped. ON CONFLICT ignores assets
+    // listed twice. Every trace is indexed, not just the first, so multi-hop assets are found by
+    // any chain they passed through.
+    let row = sqlx::query!(
+        r#"
+        WITH inserted AS (
+            INSERT INTO asset (chain_id_fk, base, symbol, coingecko_id, type_asset, data)
+            SELECT chain.id,
+            a->>'base',
+            a->>'symbol',
+            a->>'coingecko_id',
+            a->>'type_asset',
+            a
+            FROM chain,
+            jsonb_array_elements(CASE
+                WHEN jsonb_typeof(chain.asset_data->'assets') = 'array' THEN chain.asset_data->'assets'
+                ELSE '[]' END) a
+            WHERE chain.id = $1 AND a->>'base' IS NOT NULL
+            ON CONFLICT (chain_id_fk, base) DO NOTHING
+            RETURNING id, data
+        ), traces AS (
+            INSERT INTO asset_trace (asset_id_fk, position, chain_name, base_denom)
+            SELECT inserted.id,
+            t.position - 1,
+            t.trace->'counterparty'->>'chain_name',
+            t.trace->'counterparty'->>'base_denom'
+            FROM inserted,
+            jsonb_array_elements(CASE
+                WHEN jsonb_typeof(inserted.data->'traces') = 'array' THEN inserted.data->'traces'
+                ELSE '[]' END) WITH ORDINALITY t(trace, position)
+            WHERE t.trace->'counterparty'->>'chain_name' IS NOT NULL
+        )
+        SELECT COUNT(*) as "inserted!" FROM inserted
+        "#,
+        chain_id,
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(row.inserted as u64)
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct AssetFilter {
+    pub network: String,
+    /// Case insensitive
+    pub symbol: Option<String>,
+    pub base: Option<String>,
+    pub coingecko_id: Option<String>,
+    pub type_asset: Option<String>,
+    /// Counterparty chain of any of the asset's traces
+    pub trace_chain_name: Option<String>,
+    /// Counterparty denom of the same trace
+    pub trace_base_denom: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Asset {
+    pub id: i64,
+    pub chain_name: String,
+    pub data: JsonValue,
+}
+
+/// Lists assets of the latest commit ordered by id. Pass the last id of the previous page as after.
+pub async fn list_assets(
+    executor: impl PgExecutor<'_>,
+    filter: &AssetFilter,
+    after: Option<i64>,
+    limit: i64,
+) -> sqlx::Result<Vec<Asset>> {
+    sqlx::query_as!(
+        Asset,
+        r#"
+        SELECT asset.id, chain.name as chain_name, asset.data
+        FROM asset INNER JOIN chain ON chain.id = asset.chain_id_fk
+        INNER JOIN chain_commit ON chain_commit.chain_id_fk = chain.id
+        WHERE chain.network = $1
+        AND chain_commit.commit = (SELECT commit FROM registry_commit ORDER BY created_at DESC LIMIT 1)
+        AND ($2::text IS NULL OR lower(asset.symbol) = lower($2))
+        AND ($3::text IS NULL OR asset.base = $3)
+        AND ($4::text IS NULL OR asset.coingecko_id = $4)
+        AND ($5::text IS NULL OR asset.type_asset = $5)
+        AND (($6::text IS NULL AND $7::text IS NULL) OR EXISTS (
+            SELECT 1 FROM asset_trace
+            WHERE asset_trace.asset_id_fk = asset.id
+            AND ($6::text IS NULL OR asset_trace.chain_name = $6)
+            AND ($7::text IS NULL OR asset_trace.base_denom = $7)
+        ))
+        AND ($8::bigint IS NULL OR asset.id > $8)
+        ORDER BY asset.id
+        LIMIT $9
+        "#,
+        filter.network,
+        filter.symbol,
+        filter.base,
+        filter.coingecko_id,
+        filter.type_asset,
+        filter.trace_chain_name,
+        filter.trace_base_denom,
+        after,
+        limit,
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Lists all assets of the given chains at the latest commit.
+pub async fn list_chain_assets(
+    executor: impl PgExecutor<'_>,
+    network: &str,
+    chain_names: &[String],
+) -> sqlx::Result<Vec<Asset>> {
+    sqlx::query_as!(
+        Asset,
+        r#"
+        SELECT asset.id, chain.name as chain_name, asset.data
+        FROM asset INNER JOIN chain ON chain.id = asset.chain_id_fk
+        INNER JOIN chain_commit ON chain_commit.chain_id_fk = chain.id
+        WHERE chain.network = $1
+        AND chain_commit.commit = (SELECT commit FROM registry_commit ORDER BY created_at DESC LIMIT 1)
+        AND chain.name = ANY($2)
+        ORDER BY asset.id
+        "#,
+        network,
+        chain_names,
+    )
+    .fetch_all(executor)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::PgPool;
+
+    #[sqlx::test(fixtures("lookup_chains"))]
+    async fn test_insert_assets(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+        let chain = sqlx::query!("SELECT id FROM chain WHERE name = 'osmosis'")
+            .fetch_one(&mut conn)
+            .await?;
+
+        assert_eq!(insert_assets(&mut conn, chain.id).await?, 1);
+        // Idempotent
+        assert_eq!(insert_assets(&mut conn, chain.id).await?, 0);
+
+        let chain = sqlx::query!("SELECT id FROM chain WHERE name = 'evmos'")
+            .fetch_one(&mut conn)
+            .await?;
+        assert_eq!(insert_assets(&mut conn, chain.id).await?, 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("lookup_chains"))]
+    async fn test_insert_assets_traces(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+        // ATOM that reached juno through osmosis.
+        let chain = sqlx::query!(
+            r#"
+            WITH chain AS (
+                INSERT INTO chain (name, network, commit, asset_data, chain_data)
+                VALUES ('juno', 'mainnet', 'commit2', '{"assets": [{"base": "ibc/C4CFF46F", "traces": [
+                    {"type": "ibc", "counterparty": {"chain_name": "cosmoshub", "base_denom": "uatom"}},
+                    {"type": "ibc", "counterparty": {"chain_name": "osmosis", "base_denom": "ibc/27394"}}
+                ]}]}', '{}')
+                RETURNING id
+            )
+            INSERT INTO chain_commit (commit, chain_id_fk) SELECT 'commit2', id FROM chain
+            RETURNING chain_id_fk
+            "#
+        )
+        .fetch_one(&mut conn)
+        .await?;
+        assert_eq!(insert_assets(&mut conn, chain.chain_id_fk).await?, 1);
+
+        let search = |chain_name: &str, base_denom: Option<&str>| AssetFilter {
+            network: "mainnet".to_string(),
+            trace_chain_name: Some(chain_name.to_string()),
+            trace_base_denom: base_denom.map(str::to_string),
+            ..Default::default()
+        };
+        let bases = |assets: Vec<Asset>| {
+            assets
+                .into_iter()
+                .map(|a| a.data["base"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        // Found by either hop.
+        let found = list_assets(&mut conn, &search("cosmoshub", Some("uatom")), None, 10).await?;
+        assert_eq!(bases(found), vec!["ibc/C4CFF46F"]);
+        let found = list_assets(&mut conn, &search("osmosis", Some("ibc/27394")), None, 10).await?;
+        assert_eq!(bases(found), vec!["ibc/C4CFF46F"]);
+        let found = list_assets(&mut conn, &search("osmosis", None), None, 10).await?;
+        assert_eq!(bases(found), vec!["ibc/C4CFF46F"]);
+
+        // The chain and denom must belong to the same hop.
+        let found = list_assets(&mut conn, &search("osmosis", Some("uatom")), None, 10).await?;
+        assert!(found.is_empty());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("lookup_chains", "assets"))]
+    async fn test_list_assets(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+        let chains = |assets: Vec<Asset>| {
+            assets
+                .into_iter()
+                .map(|a| format!("{} {}", a.chain_name, a.data["base"].as_str().unwrap()))
+                .collect::<Vec<_>>()
+        };
+
+        let mut filter = AssetFilter {
+            network: "mainnet".to_string(),
+            ..Default::default()
+        };
+        let found = list_assets(&mut conn, &filter, None, 10).await?;
+        // Assets of cosmoshub's old row are not listed.
+        assert_eq!(
+            chains(found.clone()),
+            vec!["cosmoshub uatom", "osmosis uosmo", "osmosis ibc/27394"]
+        );
+
+        // Pagination
+        let page = list_assets(&mut conn, &filter, None, 2).await?;
+        assert_eq!(page.len(), 2);
+        let page = list_assets(&mut conn, &filter, Some(page[1].id), 2).await?;
+        assert_eq!(chains(page), vec!["osmosis ibc/27394"]);
+
+        filter.symbol = Some("atom".to_string());
+        let found = list_assets(&mut conn, &filter, None, 10).await?;
+        assert_eq!(chains(found), vec!["cosmoshub uatom", "osmosis ibc/27394"]);
+
+        filter.symbol = None;
+        filter.trace_chain_name = Some("cosmoshub".to_string());
+        filter.trace_base_denom = Some("uatom".to_string());
+        let found = list_assets(&mut conn, &filter, None, 10).await?;
+        assert_eq!(chains(found), vec!["osmosis ibc/27394"]);
+
+        let filter = AssetFilter {
+            network: "mainnet".to_string(),
+            coingecko_id: Some("osmosis".to_string()),
+            type_asset: Some("sdk.coin".to_string()),
+            ..Default::default()
+        };
+        let found = list_assets(&mut conn, &filter, None, 10).await?;
+        assert_eq!(chains(found), vec!["osmosis uosmo"]);
+
+        let filter = AssetFilter {
+            network: "testnet".to_string(),
+            base: Some("uatom".to_string()),
+            ..Default::default()
+        };
+        assert!(list_assets(&mut conn, &filter, None, 10).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("lookup_chains", "assets"))]
+    async fn test_list_chain_assets(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+
+        let names = vec!["cosmoshub".to_string(), "juno".to_string()];
+        let found = list_chain_assets(&mut conn, "mainnet", &names).await?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].data["base"], "uatom");
+
+        let found = list_chain_assets(&mut conn, "mainnet", &["osmosis".to_string()]).await?;
+        assert_eq!(found.len(), 2);
+
+        Ok(())
+    }
+}
@@ -1,13 +1,24 @@
+use crate::db::commit::RegistryCommit;
+use crate::validation::ValidationError;
+use sha2::{Digest, Sha256};
 use sqlx::{types::JsonValue, PgExecutor};
 use std::fs;
 use std::path::PathBuf;
 
+/// Result of saving a chain for a commit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SavedChain {
+    Inserted(i64),
+    /// The chain's files match its most recent row, so that row is mapped to the new commit too.
+    Unchanged(i64),
+}
+
 pub async fn insert_chain(
     executor: impl PgExecutor<'_>,
     path: PathBuf,
     network: String,
     commit: &String,
-) -> anyhow::Result<i64> {
+) -> anyhow::Result<SavedChain> {
     let chain_name = path.file_name().unwrap().to_str().unwrap();
     let chain_json = match fs::read_to_string(path.join("chain.json")) {
         Ok(c) => c,
@@ -18,30 +29,71 @@ pub async fn insert_chain(
             err,
         ),
     };
-    let chain_json: serde_json::Value = serde_json::from_str(&chain_json)?;
-
     let assets_json = fs::read_to_string(path.join("assetlist.json")).unwrap_or("{}".to_string());
-    let assets_json: serde_json::Value = serde_json::from_str(&assets_json)?;
+    let content_hash = content_hash(&chain_json, &assets_json);
+
+    // The files are stored as they are. Typed models are only used when reading, so a field with
+    // an unexpected shape does not keep the chain out of the database.
+    let chain_json: serde_json::Value = serde_json::from_str(&chain_json).map_err(|err| {
+        anyhow::anyhow!(
+            "failed to parse chain.json for chain {} {}: {}",
+            chain_name,
+            network,
+            err
+        )
+    })?;
+    let assets_json: serde_json::Value = serde_json::from_str(&assets_json).map_err(|err| {
+        anyhow::anyhow!(
+            "failed to parse assetlist.json for chain {} {}: {}",
+            chain_name,
+            network,
+            err
+        )
+    })?;
 
+    // If the most recent row has the same content, map it to this commit instead of inserting a
+    // copy. Peers and endpoints reference the row, so their liveness history carries over. The row
+    // itself is never rewritten, so older commits still resolve to it through chain_commit.
     // DO NOTHING causes a RowNotFound error. We want the updated_at triggers to fire as well, so
     // we update.
     match sqlx::query!(
         r#"
-        INSERT INTO chain (name, network, chain_data, asset_data, commit)
-        VALUES ($1, $2, $3, $4, $5)
-        ON CONFLICT (name, network, commit) DO UPDATE SET commit = $5
-        RETURNING id
+        WITH prior AS (
+            SELECT id, content_hash FROM chain
+            WHERE name = $1 AND network = $2
+            ORDER BY created_at DESC, id DESC
+            LIMIT 1
+        ), reused AS (
+            SELECT id FROM prior WHERE content_hash = $6
+        ), inserted AS (
+            INSERT INTO chain (name, network, chain_data, asset_data, commit, content_hash)
+            SELECT $1, $2, $3, $4, $5, $6
+            WHERE NOT EXISTS (SELECT 1 FROM reused)
+            ON CONFLICT (name, network, commit) DO UPDATE SET commit = $5
+            RETURNING id
+        ), saved AS (
+            SELECT id, true as reused FROM reused
+            UNION ALL
+            SELECT id, false FROM inserted
+        ), mapped AS (
+            INSERT INTO chain_commit (commit, chain_id_fk)
+            SELECT $5, id FROM saved
+            ON CONFLICT DO NOTHING
+        )
+        SELECT id as "id!", reused as "reused!" FROM saved
         "#,
         chain_name,
         network,
         chain_json,
         assets_json,
         commit,
+        content_hash,
     )
     .fetch_one(executor)
     .await
     {
-        Ok(row) => Ok(row.id),
+        Ok(row) if row.reused => Ok(SavedChain::Unchanged(row.id)),
+        Ok(row) => Ok(SavedChain::Inserted(row.id)),
         Err(err) => anyhow::bail!(
             "failed to insert chain {} {}: {:?}",
             chain_name,
@@ -51,12 +103,34 @@ pub async fn insert_chain(
     }
 }
 
+fn content_hash(chain_json: &str, assets_json: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(chain_json.as_bytes());
+    // Separates the files so moving bytes between them changes the hash.
+    hasher.update([0]);
+    hasher.update(assets_json.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
 #[derive(Debug, Clone)]
 pub struct Chain {
+    /// The latest commit the chain is part of.
     pub commit: String,
+    /// When the chain's content last changed.
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub chain_data: JsonValue,
     pub asset_data: JsonValue,
+    // Array of validation::ValidationError
+    pub validation_errors: JsonValue,
+    /// Hash of chain.json and assetlist.json. None for rows hydrated before hashes were stored.
+    pub content_hash: Option<String>,
+}
+
+impl Chain {
+    /// Identifies the chain's content, so it only changes when chain.json or assetlist.json do.
+    pub fn version(&self) -> &str {
+        self.content_hash.as_deref().unwrap_or(&self.commit)
+    }
 }
 
 pub async fn find_chain(
@@ -67,26 +141,86 @@ pub async fn find_chain(
     sqlx::query_as!(
         Chain,
         r#"
-        SELECT commit, created_at, chain_data, asset_data FROM chain WHERE name = $1 AND network = $2 ORDER BY created_at DESC LIMIT 1
+        SELECT COALESCE(latest.commit, chain.commit) as "commit!", chain.created_at, chain_data,
+        asset_data, COALESCE(latest.validation_errors, '[]') as "validation_errors!", content_hash
+        FROM chain LEFT JOIN LATERAL (
+            SELECT chain_commit.commit, chain_commit.validation_errors FROM chain_commit
+            INNER JOIN registry_commit ON registry_commit.commit = chain_commit.commit
+            WHERE chain_commit.chain_id_fk = chain.id
+            ORDER BY registry_commit.created_at DESC LIMIT 1
+        ) latest ON true
+        WHERE name = $1 AND network = $2 ORDER BY chain.created_at DESC, id DESC LIMIT 1
         "#,
         chain_name,
         network,
     )
     .fetch_one(executor)
-        .await
+    .await
+}
+
+/// Replaces the chain's validation errors at the commit. Errors are stored for unchanged chains
+/// too because the schemas may have changed, but errors at older commits are kept as they were.
+pub async fn update_validation_errors(
+    executor: impl PgExecutor<'_>,
+    chain_id: i64,
+    commit: &str,
+    errors: &[ValidationError],
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE chain_commit SET validation_errors = $1 WHERE chain_id_fk = $2 AND commit = $3
+        "#,
+        serde_json::to_value(errors)?,
+        chain_id,
+        commit,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Finds the chain as it was at the given commit. The returned commit and created_at are those of
+/// the requested commit rather than the row's latest commit.
+pub async fn find_chain_at(
+    executor: impl PgExecutor<'_>,
+    network: &str,
+    chain_name: &str,
+    commit: &RegistryCommit,
+) -> sqlx::Result<Chain> {
+    sqlx::query_as!(
+        Chain,
+        r#"
+        SELECT $3::text as "commit!", $4::timestamptz as "created_at!", chain_data, asset_data,
+        chain_commit.validation_errors, content_hash
+        FROM chain INNER JOIN chain_commit ON chain_commit.chain_id_fk = chain.id
+        WHERE name = $1 AND network = $2 AND chain_commit.commit = $3
+        LIMIT 1
+        "#,
+        chain_name,
+        network,
+        commit.commit,
+        commit.created_at,
+    )
+    .fetch_one(executor)
+    .await
 }
 
-pub async fn truncate_old_chains(executor: impl PgExecutor<'_>, keep: i64) -> sqlx::Result<()> {
+/// Deletes all but the most recent commits along with chains that are only part of deleted
+/// commits. Peers and endpoints of deleted chains cascade.
+pub async fn truncate_old_chains(executor: impl PgExecutor<'_>, keep: u32) -> sqlx::Result<()> {
     sqlx::query!(
         r#"
-        WITH recent AS (SELECT commit, max(created_at) AS created_at
-                FROM chain
-                GROUP BY commit
-                ORDER BY created_at DESC
-                LIMIT $1)
-        DELETE FROM chain WHERE commit NOT IN (SELECT commit from recent)
+        WITH kept AS (
+            SELECT commit FROM registry_commit ORDER BY created_at DESC LIMIT $1
+        ), deleted AS (
+            DELETE FROM registry_commit WHERE commit NOT IN (SELECT commit FROM kept)
+        )
+        DELETE FROM chain WHERE id NOT IN (
+            SELECT chain_id_fk FROM chain_commit WHERE commit IN (SELECT commit FROM kept)
+        )
         "#,
-        keep,
+        i64::from(keep),
     )
     .execute(executor)
     .await?;
@@ -98,29 +232,301 @@ pub async fn truncate_old_chains(executor: impl PgExecutor<'_>, keep: i64) -> sq
 pub struct ChainList {
     pub commit: String,
     pub created_at: chrono::DateTime<chrono::Utc>,
-    pub names: Vec<String>,
+    /// Ordered by name
+    pub chains: Vec<ChainSummary>,
 }
 
-pub async fn list_chains(executor: impl PgExecutor<'_>, network: &str) -> sqlx::Result<ChainList> {
-    let row = sqlx::query!(
+#[derive(Debug, Clone)]
+pub struct ChainSummary {
+    pub name: String,
+    pub chain_data: JsonValue,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ChainFilter {
+    /// live, upcoming, or killed
+    pub status: Option<String>,
+    /// mainnet, testnet, or devnet
+    pub network_type: Option<String>,
+    /// Chains whose key_algos include this one, e.g. secp256k1
+    pub key_algo: Option<String>,
+    /// Only chains whose name sorts after this one
+    pub after: Option<String>,
+    pub limit: Option<i64>,
+}
+
+pub async fn list_chains(
+    executor: impl PgExecutor<'_>,
+    network: &str,
+    filter: &ChainFilter,
+) -> sqlx::Result<ChainList> {
+    // The left join returns the commit even when no chains match the filter.
+    let rows = sqlx::query!(
         r#"
-        SELECT commit, 
-        array_agg(name order by name) as names, 
-        MAX(created_at) as created_at 
-        FROM chain WHERE network = $1 GROUP BY commit ORDER BY MAX(created_at) DESC LIMIT 1;
+        WITH latest AS (
+            SELECT commit, created_at FROM registry_commit
+            WHERE EXISTS (
+                SELECT 1 FROM chain_commit INNER JOIN chain ON chain.id = chain_commit.chain_id_fk
+                WHERE chain_commit.commit = registry_commit.commit AND chain.network = $1
+            )
+            ORDER BY created_at DESC LIMIT 1
+        ), page AS (
+            SELECT chain.name, chain.chain_data FROM chain
+            INNER JOIN chain_commit ON chain_commit.chain_id_fk = chain.id
+            INNER JOIN latest ON latest.commit = chain_commit.commit
+            WHERE network = $1
+            AND ($2::text IS NULL OR chain_data->>'status' = $2)
+            AND ($3::text IS NULL OR chain_data->>'network_type' = $3)
+            AND ($4::text IS NULL OR chain_data->'key_algos' @> jsonb_build_array($4::text))
+            AND ($5::text IS NULL OR name > $5)
+            ORDER BY name
+            LIMIT $6
+        )
+        SELECT latest.commit, latest.created_at, page.name as "name?", page.chain_data as "chain_data?"
+        FROM latest LEFT JOIN page ON true
+        ORDER BY page.name
         "#,
         network,
+        filter.status,
+        filter.network_type,
+        filter.key_algo,
+        filter.after,
+        filter.limit,
     )
-    .fetch_one(executor)
+    .fetch_all(executor)
     .await?;
 
+    let first = rows.first().ok_or(sqlx::Error::RowNotFound)?;
     Ok(ChainList {
-        commit: row.commit,
-        created_at: row.created_at.unwrap_or(chrono::Utc::now()),
-        names: row.names.unwrap_or(vec![]),
+        commit: first.commit.clone(),
+        created_at: first.created_at,
+        chains: rows
+            .into_iter()
+            .filter_map(|row| {
+                Some(ChainSummary {
+                    name: row.name?,
+                    chain_data: row.chain_data?,
+                })
+            })
+            .collect(),
     })
 }
 
+/// Lists chains as they were at the given commit.
+pub async fn list_chains_at(
+    executor: impl PgExecutor<'_>,
+    network: &str,
+    commit: &RegistryCommit,
+    filter: &ChainFilter,
+) -> sqlx::Result<ChainList> {
+    let rows = sqlx::query!(
+        r#"
+        WITH valid AS (
+            SELECT name, chain_data
+            FROM chain INNER JOIN chain_commit ON chain_commit.chain_id_fk = chain.id
+            WHERE network = $1 AND chain_commit.commit = $2
+        ), page AS (
+            SELECT name, chain_data FROM valid
+            WHERE ($3::text IS NULL OR chain_data->>'status' = $3)
+            AND ($4::text IS NULL OR chain_data->>'network_type' = $4)
+            AND ($5::text IS NULL OR chain_data->'key_algos' @> jsonb_build_array($5::text))
+            AND ($6::text IS NULL OR name > $6)
+            ORDER BY name
+            LIMIT $7
+        )
+        SELECT EXISTS (SELECT 1 FROM valid) as "exists!", page.name as "name?", page.chain_data as "chain_data?"
+        FROM (SELECT 1) one LEFT JOIN page ON true
+        ORDER BY page.name
+        "#,
+        network,
+        commit.commit,
+        filter.status,
+        filter.network_type,
+        filter.key_algo,
+        filter.after,
+        filter.limit,
+    )
+    .fetch_all(executor)
+    .await?;
+
+    // The network did not exist at the commit.
+    if !rows.first().is_some_and(|row| row.exists) {
+        return Err(sqlx::Error::RowNotFound);
+    }
+    Ok(ChainList {
+        commit: commit.commit.clone(),
+        created_at: commit.created_at,
+        chains: rows
+            .into_iter()
+            .filter_map(|row| {
+                Some(ChainSummary {
+                    name: row.name?,
+                    chain_data: row.chain_data?,
+                })
+            })
+            .collect(),
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportedChain {
+    pub network: String,
+    pub name: String,
+    pub chain_data: JsonValue,
+    pub asset_data: JsonValue,
+    /// Array of {"address", "type"} for peers that passed their latest liveness check.
+    pub peers: JsonValue,
+}
+
+/// A page of chains as they were at the commit, ordered by network and name. Pass the network and
+/// name of the previous page's last chain as after. Exports read page by page so they neither hold
+/// all chains in memory nor keep a connection while the client reads.
+pub async fn export_chains(
+    executor: impl PgExecutor<'_>,
+    network: Option<&str>,
+    commit: &RegistryCommit,
+    after: Option<(&str, &str)>,
+    limit: i64,
+) -> sqlx::Result<Vec<ExportedChain>> {
+    let (after_network, after_name) = after.unzip();
+    sqlx::query_as!(
+        ExportedChain,
+        r#"
+        SELECT network, name, chain_data, asset_data,
+        COALESCE(
+            (SELECT jsonb_agg(jsonb_build_object('address', peer.address, 'type', peer.type) ORDER BY peer.type DESC, peer.address)
+            FROM peer WHERE peer.chain_id_fk = chain.id AND peer.is_alive),
+            '[]'
+        ) as "peers!"
+        FROM chain INNER JOIN chain_commit ON chain_commit.chain_id_fk = chain.id
+        WHERE ($1::text IS NULL OR network = $1) AND chain_commit.commit = $2
+        AND ($3::text IS NULL OR (network, name) > ($3, $4::text))
+        ORDER BY network, name
+        LIMIT $5
+        "#,
+        network,
+        commit.commit,
+        after_network,
+        after_name,
+        limit,
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// What a client knows about a chain. Each key has its own index.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LookupKey {
+    ChainId(String),
+    Bech32Prefix(String),
+    Slip44(i32),
+    /// An asset's base denom or any of its denom units.
+    Denom(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainRef {
+    pub network: String,
+    pub name: String,
+}
+
+/// Finds chains of the latest commit matching the key, ordered by network and name.
+pub async fn lookup_chains(
+    executor: impl PgExecutor<'_>,
+    key: &LookupKey,
+) -> sqlx::Result<Vec<ChainRef>> {
+    match key {
+        LookupKey::ChainId(chain_id) => {
+            sqlx::query_as!(
+                ChainRef,
+                r#"
+                SELECT network, name FROM chain
+                INNER JOIN chain_commit ON chain_commit.chain_id_fk = chain.id
+                WHERE chain_commit.commit = (SELECT commit FROM registry_commit ORDER BY created_at DESC LIMIT 1)
+                AND chain_data ->> 'chain_id' = $1
+                ORDER BY network, name
+                "#,
+                chain_id,
+            )
+            .fetch_all(executor)
+            .await
+        }
+        LookupKey::Bech32Prefix(prefix) => {
+            sqlx::query_as!(
+                ChainRef,
+                r#"
+                SELECT network, name FROM chain
+                INNER JOIN chain_commit ON chain_commit.chain_id_fk = chain.id
+                WHERE chain_commit.commit = (SELECT commit FROM registry_commit ORDER BY created_at DESC LIMIT 1)
+                AND chain_data ->> 'bech32_prefix' = $1
+                ORDER BY network, name
+                "#,
+                prefix,
+            )
+            .fetch_all(executor)
+            .await
+        }
+        LookupKey::Slip44(slip44) => {
+            sqlx::query_as!(
+                ChainRef,
+                r#"
+                SELECT network, name FROM chain
+                INNER JOIN chain_commit ON chain_commit.chain_id_fk = chain.id
+                WHERE chain_commit.commit = (SELECT commit FROM registry_commit ORDER BY created_at DESC LIMIT 1)
+                AND chain_data -> 'slip44' = to_jsonb($1::int)
+                ORDER BY network, name
+                "#,
+                slip44,
+            )
+            .fetch_all(executor)
+            .await
+        }
+        LookupKey::Denom(denom) => {
+            sqlx::query_as!(
+                ChainRef,
+                r#"
+                SELECT network, name FROM chain
+                INNER JOIN chain_commit ON chain_commit.chain_id_fk = chain.id
+                WHERE chain_commit.commit = (SELECT commit FROM registry_commit ORDER BY created_at DESC LIMIT 1)
+                AND (
+                    asset_data @> jsonb_build_object('assets', jsonb_build_array(
+                        jsonb_build_object('base', $1::text)))
+                    OR asset_data @> jsonb_build_object('assets', jsonb_build_array(
+                        jsonb_build_object('denom_units', jsonb_build_array(
+                            jsonb_build_object('denom', $1::text)))))
+                )
+                ORDER BY network, name
+                "#,
+                denom,
+            )
+            .fetch_all(executor)
+            .await
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkCount {
+    pub network: String,
+    pub chains: i64,
+}
+
+/// Number of chains per network at the latest commit.
+pub async fn count_chains(executor: impl PgExecutor<'_>) -> sqlx::Result<Vec<NetworkCount>> {
+    sqlx::query_as!(
+        NetworkCount,
+        r#"
+        SELECT network, COUNT(*) as "chains!"
+        FROM chain INNER JOIN chain_commit ON chain_commit.chain_id_fk = chain.id
+        WHERE chain_commit.commit = (SELECT commit FROM registry_commit ORDER BY created_at DESC LIMIT 1)
+        GROUP BY network
+        ORDER BY network
+        "#,
+    )
+    .fetch_all(executor)
+    .await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -129,19 +535,24 @@ mod tests {
     use std::io::Write;
     use tempfile::TempDir;
 
+    fn names(list: &ChainList) -> Vec<&str> {
+        list.chains.iter().map(|c| c.name.as_str()).collect()
+    }
+
     #[sqlx::test]
     async fn test_insert_chain(pool: PgPool) -> sqlx::Result<()> {
         let test_path = TempDir::new().unwrap().into_path().join("cosmos");
         fs::create_dir(test_path.clone()).unwrap();
         let mut file = File::create(test_path.clone().join("chain.json"))?;
-        file.write_all(r#"{"chain_id":"cosmoshub-4"}"#.as_bytes())?;
+        file.write_all(r#"{"chain_name":"cosmos","chain_id":"cosmoshub-4"}"#.as_bytes())?;
         let mut file = File::create(test_path.clone().join("assetlist.json"))?;
         let stub_asset_data = r#"{"stub":"data"}"#;
         file.write_all(stub_asset_data.as_bytes())?;
 
         let mut conn = pool.acquire().await?;
+        crate::db::commit::insert_commit(&mut conn, "stub commit").await?;
 
-        let id = insert_chain(
+        let saved = insert_chain(
             &mut conn,
             test_path.clone(),
             "testnet".to_string(),
@@ -149,6 +560,10 @@ mod tests {
         )
         .await
         .unwrap();
+        let id = match saved {
+            SavedChain::Inserted(id) => id,
+            SavedChain::Unchanged(_) => panic!("expected new chain"),
+        };
 
         assert_ne!(id, 0);
 
@@ -190,6 +605,171 @@ mod tests {
         Ok(())
     }
 
+    #[sqlx::test]
+    async fn test_insert_chain_raw_json(pool: PgPool) -> sqlx::Result<()> {
+        let test_path = TempDir::new().unwrap().into_path().join("cosmos");
+        fs::create_dir(test_path.clone()).unwrap();
+        // Neither file matches the typed models.
+        let chain_json = r#"{"chain_name":"cosmos","chain_id":1,"extra":{"nested":[1,2]}}"#;
+        fs::write(test_path.join("chain.json"), chain_json)?;
+        fs::write(test_path.join("assetlist.json"), r#"{"assets":"none"}"#)?;
+
+        let mut conn = pool.acquire().await?;
+        crate::db::commit::insert_commit(&mut conn, "commit1").await?;
+        insert_chain(
+            &mut conn,
+            test_path.clone(),
+            "mainnet".into(),
+            &"commit1".into(),
+        )
+        .await
+        .unwrap();
+
+        let chain = find_chain(&mut conn, "mainnet", "cosmos").await?;
+        assert_eq!(
+            chain.chain_data,
+            serde_json::from_str::<JsonValue>(chain_json).unwrap()
+        );
+        assert_eq!(chain.asset_data, serde_json::json!({"assets": "none"}));
+
+        // Files that are not JSON still fail.
+        fs::write(test_path.join("chain.json"), "{")?;
+        let err = insert_chain(&mut conn, test_path, "mainnet".into(), &"commit1".into())
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("failed to parse chain.json"),
+            "{}",
+            err
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_insert_unchanged_chain(pool: PgPool) -> sqlx::Result<()> {
+        let test_path = TempDir::new().unwrap().into_path().join("cosmos");
+        fs::create_dir(test_path.clone()).unwrap();
+        fs::write(
+            test_path.join("chain.json"),
+            r#"{"chain_name":"cosmos","chain_id":"cosmoshub-4"}"#,
+        )?;
+
+        let mut conn = pool.acquire().await?;
+        for commit in ["commit1", "commit2", "commit3"] {
+            crate::db::commit::insert_commit(&mut conn, commit).await?;
+        }
+
+        let mainnet = "mainnet".to_string();
+        let first = insert_chain(
+            &mut conn,
+            test_path.clone(),
+            mainnet.clone(),
+            &"commit1".into(),
+        )
+        .await
+        .unwrap();
+        let first_id = match first {
+            SavedChain::Inserted(id) => id,
+            SavedChain::Unchanged(_) => panic!("expected new chain"),
+        };
+
+        let second = insert_chain(
+            &mut conn,
+            test_path.clone(),
+            mainnet.clone(),
+            &"commit2".into(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(second, SavedChain::Unchanged(first_id));
+
+        fs::write(test_path.join("assetlist.json"), r#"{"assets":[]}"#)?;
+        let third = insert_chain(&mut conn, test_path.clone(), mainnet, &"commit3".into())
+            .await
+            .unwrap();
+        assert!(matches!(third, SavedChain::Inserted(id) if id != first_id));
+
+        // The reused row is not rewritten.
+        let rows = sqlx::query!("SELECT commit, content_hash FROM chain ORDER BY id")
+            .fetch_all(&mut conn)
+            .await?;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].commit, "commit1");
+        assert_eq!(rows[1].commit, "commit3");
+        assert_ne!(rows[0].content_hash, rows[1].content_hash);
+
+        let mapped = sqlx::query!(
+            "SELECT commit, chain_id_fk FROM chain_commit ORDER BY commit, chain_id_fk"
+        )
+        .fetch_all(&mut conn)
+        .await?;
+        let mapped: Vec<(&str, i64)> = mapped
+            .iter()
+            .map(|m| (m.commit.as_str(), m.chain_id_fk))
+            .collect();
+        assert_eq!(
+            mapped,
+            vec![
+                ("commit1", first_id),
+                ("commit2", first_id),
+                ("commit3", first_id + 1)
+            ]
+        );
+
+        let commit2 = crate::db::commit::find_commit(&mut conn, "commit2").await?;
+        let chain = find_chain_at(&mut conn, "mainnet", "cosmos", &commit2).await?;
+        assert_eq!(chain.chain_data["chain_id"], "cosmoshub-4");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let hash = content_hash(r#"{"chain_id":"cosmoshub-4"}"#, "{}");
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, content_hash(r#"{"chain_id":"cosmoshub-4"}"#, "{}"));
+        assert_ne!(hash, content_hash(r#"{"chain_id":"cosmoshub-4"}"#, "{ }"));
+        assert_ne!(content_hash("ab", "c"), content_hash("a", "bc"));
+    }
+
+    #[sqlx::test(fixtures("chains"))]
+    async fn test_update_validation_errors(pool: PgPool) -> anyhow::Result<()> {
+        let mut conn = pool.acquire().await?;
+
+        let chain = find_chain(&mut conn, "mainnet", "cosmoshub").await?;
+        assert_eq!(chain.validation_errors, serde_json::json!([]));
+
+        let errors = vec![ValidationError {
+            file: "chain.json".to_string(),
+            path: "/chain_id".to_string(),
+            message: "4 is not of type \"string\"".to_string(),
+        }];
+        update_validation_errors(&mut conn, 1, "stubcommit", &errors).await?;
+
+        let chain = find_chain(&mut conn, "mainnet", "cosmoshub").await?;
+        let found: Vec<ValidationError> = serde_json::from_value(chain.validation_errors)?;
+        assert_eq!(found, errors);
+
+        // The row is reused by a new commit whose schemas it passes.
+        crate::db::commit::insert_commit(&mut conn, "newcommit").await?;
+        sqlx::query!("INSERT INTO chain_commit (commit, chain_id_fk) VALUES ('newcommit', 1)")
+            .execute(&mut conn)
+            .await?;
+        update_validation_errors(&mut conn, 1, "newcommit", &[]).await?;
+
+        let chain = find_chain(&mut conn, "mainnet", "cosmoshub").await?;
+        assert_eq!(chain.commit, "newcommit");
+        assert_eq!(chain.validation_errors, serde_json::json!([]));
+        // The older commit still reports its own errors.
+        let commit = crate::db::commit::find_commit(&mut conn, "stubcommit").await?;
+        let chain = find_chain_at(&mut conn, "mainnet", "cosmoshub", &commit).await?;
+        let found: Vec<ValidationError> = serde_json::from_value(chain.validation_errors)?;
+        assert_eq!(found, errors);
+
+        Ok(())
+    }
+
     #[sqlx::test(fixtures("chains"))]
     async fn test_find_chain(pool: PgPool) -> sqlx::Result<()> {
         let mut conn = pool.acquire().await?;
@@ -217,10 +797,102 @@ mod tests {
     async fn test_list_chains(pool: PgPool) -> sqlx::Result<()> {
         let mut conn = pool.acquire().await?;
 
-        let list = list_chains(&mut conn, "mainnet").await?;
+        let list = list_chains(&mut conn, "mainnet", &ChainFilter::default()).await?;
+
+        assert_eq!(list.commit, "stubcommit");
+        assert_eq!(names(&list), vec!["cosmoshub"]);
+        assert_eq!(list.chains[0].chain_data["chain_id"], "cosmoshub-4");
+
+        let mut filter = ChainFilter {
+            status: Some("live".to_string()),
+            network_type: Some("mainnet".to_string()),
+            key_algo: Some("secp256k1".to_string()),
+            ..Default::default()
+        };
+        let list = list_chains(&mut conn, "mainnet", &filter).await?;
+        assert_eq!(names(&list), vec!["cosmoshub"]);
 
+        // The commit is returned even when nothing matches.
+        filter.key_algo = Some("ethsecp256k1".to_string());
+        let list = list_chains(&mut conn, "mainnet", &filter).await?;
         assert_eq!(list.commit, "stubcommit");
-        assert_eq!(list.names, vec!["cosmoshub"]);
+        assert!(list.chains.is_empty());
+
+        assert!(matches!(
+            list_chains(&mut conn, "testnet", &ChainFilter::default()).await,
+            Err(sqlx::Error::RowNotFound)
+        ));
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("chain_history"))]
+    async fn test_find_chain_at(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+        let commits = crate::db::commit::list_commits(&mut conn).await?;
+        let (commit3, commit2, commit1) = (&commits[0], &commits[1], &commits[2]);
+
+        let chain = find_chain_at(&mut conn, "mainnet", "cosmoshub", commit1).await?;
+        assert_eq!(chain.commit, "commit1");
+        assert_eq!(chain.created_at, commit1.created_at);
+        assert_eq!(chain.chain_data["chain_id"], "cosmoshub-3");
+
+        let chain = find_chain_at(&mut conn, "mainnet", "cosmoshub", commit2).await?;
+        assert_eq!(chain.commit, "commit2");
+        assert_eq!(chain.chain_data["chain_id"], "cosmoshub-3");
+
+        let chain = find_chain_at(&mut conn, "mainnet", "cosmoshub", commit3).await?;
+        assert_eq!(chain.chain_data["chain_id"], "cosmoshub-4");
+
+        let chain = find_chain_at(&mut conn, "mainnet", "osmosis", commit1).await?;
+        assert_eq!(chain.chain_data["chain_id"], "osmosis-1");
+
+        assert!(matches!(
+            find_chain_at(&mut conn, "mainnet", "juno", commit2).await,
+            Err(sqlx::Error::RowNotFound)
+        ));
+        assert!(find_chain_at(&mut conn, "mainnet", "juno", commit3)
+            .await
+            .is_ok());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("chain_history"))]
+    async fn test_list_chains_at(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+        let commits = crate::db::commit::list_commits(&mut conn).await?;
+
+        let all = ChainFilter::default();
+
+        let list = list_chains_at(&mut conn, "mainnet", &commits[2], &all).await?;
+        assert_eq!(list.commit, "commit1");
+        assert_eq!(names(&list), vec!["cosmoshub", "osmosis"]);
+        assert_eq!(list.chains[0].chain_data["chain_id"], "cosmoshub-3");
+
+        let list = list_chains_at(&mut conn, "mainnet", &commits[0], &all).await?;
+        assert_eq!(list.commit, "commit3");
+        assert_eq!(names(&list), vec!["cosmoshub", "juno", "osmosis"]);
+        assert_eq!(list.chains[0].chain_data["chain_id"], "cosmoshub-4");
+
+        // Pagination
+        let mut filter = ChainFilter {
+            limit: Some(2),
+            ..Default::default()
+        };
+        let list = list_chains_at(&mut conn, "mainnet", &commits[0], &filter).await?;
+        assert_eq!(names(&list), vec!["cosmoshub", "juno"]);
+        filter.after = Some("juno".to_string());
+        let list = list_chains_at(&mut conn, "mainnet", &commits[0], &filter).await?;
+        assert_eq!(names(&list), vec!["osmosis"]);
+        filter.after = Some("osmosis".to_string());
+        let list = list_chains_at(&mut conn, "mainnet", &commits[0], &filter).await?;
+        assert!(list.chains.is_empty());
+
+        assert!(matches!(
+            list_chains_at(&mut conn, "testnet", &commits[0], &all).await,
+            Err(sqlx::Error::RowNotFound)
+        ));
 
         Ok(())
     }
@@ -241,4 +913,134 @@ mod tests {
 
         Ok(())
     }
+
+    #[sqlx::test(fixtures("truncate_chains"))]
+    async fn test_truncate_reused_chains(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+
+        // commit2's chain is still part of commit3.
+        sqlx::query!("DELETE FROM chain WHERE commit = 'commit3'")
+            .execute(&mut conn)
+            .await?;
+        sqlx::query!(
+            "INSERT INTO chain_commit (commit, chain_id_fk) SELECT 'commit3', id FROM chain WHERE commit = 'commit2'"
+        )
+        .execute(&mut conn)
+        .await?;
+
+        truncate_old_chains(&mut conn, 2).await?;
+
+        let commits = crate::db::commit::list_commits(&mut conn).await?;
+        let names: Vec<&str> = commits.iter().map(|c| c.commit.as_str()).collect();
+        assert_eq!(names, vec!["commit4", "commit3"]);
+
+        let chains = sqlx::query!("SELECT commit FROM chain ORDER BY created_at")
+            .fetch_all(&mut conn)
+            .await?;
+        let chains: Vec<&str> = chains.iter().map(|c| c.commit.as_str()).collect();
+        assert_eq!(chains, vec!["commit2", "commit4"]);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("lookup_chains"))]
+    async fn test_lookup_chains(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+        let names = |chains: Vec<ChainRef>| {
+            chains
+                .into_iter()
+                .map(|c| format!("{}/{}", c.network, c.name))
+                .collect::<Vec<_>>()
+        };
+
+        let found = lookup_chains(&mut conn, &LookupKey::ChainId("cosmoshub-4".into())).await?;
+        assert_eq!(names(found), vec!["mainnet/cosmoshub"]);
+
+        // Older commits are not searched.
+        let found = lookup_chains(&mut conn, &LookupKey::ChainId("cosmoshub-3".into())).await?;
+        assert!(found.is_empty());
+
+        let found = lookup_chains(&mut conn, &LookupKey::Bech32Prefix("cosmos".into())).await?;
+        assert_eq!(
+            names(found),
+            vec!["mainnet/cosmoshub", "testnet/cosmoshubtestnet"]
+        );
+
+        let found = lookup_chains(&mut conn, &LookupKey::Slip44(60)).await?;
+        assert_eq!(names(found), vec!["mainnet/evmos"]);
+
+        let found = lookup_chains(&mut conn, &LookupKey::Denom("uosmo".into())).await?;
+        assert_eq!(names(found), vec!["mainnet/osmosis"]);
+        let found = lookup_chains(&mut conn, &LookupKey::Denom("atom".into())).await?;
+        assert_eq!(names(found), vec!["mainnet/cosmoshub"]);
+
+        let found = lookup_chains(&mut conn, &LookupKey::Denom("ujuno".into())).await?;
+        assert!(found.is_empty());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("chain_history"))]
+    async fn test_export_chains(pool: PgPool) -> sqlx::Result<()> {
+        let commits = crate::db::commit::list_commits(&pool).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO peer (chain_id_fk, type, address, is_alive)
+            SELECT id, 'seed', 'abc@seed.com:26656', true FROM chain WHERE chain_data->>'chain_id' = 'osmosis-1'
+            UNION ALL
+            SELECT id, 'persistent', 'def@dead.com:26656', false FROM chain WHERE chain_data->>'chain_id' = 'osmosis-1'
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        let chains = export_chains(&pool, None, &commits[0], None, 10).await?;
+        let names: Vec<&str> = chains.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["cosmoshub", "juno", "osmosis"]);
+        assert_eq!(chains[0].chain_data["chain_id"], "cosmoshub-4");
+        assert_eq!(chains[0].peers, serde_json::json!([]));
+        assert_eq!(
+            chains[2].peers,
+            serde_json::json!([{"address": "abc@seed.com:26656", "type": "seed"}])
+        );
+
+        // Pagination
+        let page = export_chains(&pool, None, &commits[0], None, 2).await?;
+        let names: Vec<&str> = page.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["cosmoshub", "juno"]);
+        let page = export_chains(&pool, None, &commits[0], Some(("mainnet", "juno")), 2).await?;
+        let names: Vec<&str> = page.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["osmosis"]);
+
+        let chains = export_chains(&pool, Some("mainnet"), &commits[2], None, 10).await?;
+        assert_eq!(chains.len(), 2);
+        assert_eq!(chains[0].chain_data["chain_id"], "cosmoshub-3");
+
+        let chains = export_chains(&pool, Some("testnet"), &commits[0], None, 10).await?;
+        assert!(chains.is_empty());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("lookup_chains"))]
+    async fn test_count_chains(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+        let counts = count_chains(&mut conn).await?;
+
+        assert_eq!(
+            counts,
+            vec![
+                NetworkCount {
+                    network: "mainnet".to_string(),
+                    chains: 3,
+                },
+                NetworkCount {
+                    network: "testnet".to_string(),
+                    chains: 1,
+                },
+            ]
+        );
+
+        Ok(())
+    }
 }
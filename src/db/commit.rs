@@ -0,0 +1,124 @@
+use sqlx::PgExecutor;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegistryCommit {
+    pub commit: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Records a hydrated commit. Hydrating the same commit again keeps the original time.
+pub async fn insert_commit(executor: impl PgExecutor<'_>, commit: &str) -> sqlx::Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO registry_commit (commit) VALUES ($1)
+        ON CONFLICT (commit) DO NOTHING
+        "#,
+        commit,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// The commit of the most recent hydrate, if any.
+pub async fn latest_commit(executor: impl PgExecutor<'_>) -> sqlx::Result<Option<String>> {
+    let row = sqlx::query!(
+        r#"
+        SELECT commit FROM registry_commit ORDER BY created_at DESC LIMIT 1
+        "#,
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(row.map(|row| row.commit))
+}
+
+/// Retained commits, newest first.
+pub async fn list_commits(executor: impl PgExecutor<'_>) -> sqlx::Result<Vec<RegistryCommit>> {
+    sqlx::query_as!(
+        RegistryCommit,
+        r#"
+        SELECT commit, created_at FROM registry_commit ORDER BY created_at DESC
+        "#,
+    )
+    .fetch_all(executor)
+    .await
+}
+
+pub async fn find_commit(
+    executor: impl PgExecutor<'_>,
+    commit: &str,
+) -> sqlx::Result<RegistryCommit> {
+    sqlx::query_as!(
+        RegistryCommit,
+        r#"
+        SELECT commit, created_at FROM registry_commit WHERE commit = $1
+        "#,
+        commit,
+    )
+    .fetch_one(executor)
+    .await
+}
+
+/// The commit that was current at the given time, i.e. the latest one hydrated at or before it.
+pub async fn find_commit_at(
+    executor: impl PgExecutor<'_>,
+    at: chrono::DateTime<chrono::Utc>,
+) -> sqlx::Result<RegistryCommit> {
+    sqlx::query_as!(
+        RegistryCommit,
+        r#"
+        SELECT commit, created_at FROM registry_commit
+        WHERE created_at <= $1
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#,
+        at,
+    )
+    .fetch_one(executor)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::PgPool;
+
+    #[sqlx::test(fixtures("truncate_chains"))]
+    async fn test_commits(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+
+        assert_eq!(latest_commit(&mut conn).await?.as_deref(), Some("commit4"));
+
+        let commits = list_commits(&mut conn).await?;
+        let names: Vec<&str> = commits.iter().map(|c| c.commit.as_str()).collect();
+        assert_eq!(names, vec!["commit4", "commit3", "commit2", "commit1"]);
+
+        let found = find_commit(&mut conn, "commit2").await?;
+        assert_eq!(found, commits[2]);
+        assert!(matches!(
+            find_commit(&mut conn, "nope").await,
+            Err(sqlx::Error::RowNotFound)
+        ));
+
+        let between = commits[2].created_at + chrono::Duration::minutes(30);
+        assert_eq!(find_commit_at(&mut conn, between).await?, commits[2]);
+        assert_eq!(
+            find_commit_at(&mut conn, commits[1].created_at).await?,
+            commits[1]
+        );
+        let before = commits[3].created_at - chrono::Duration::minutes(1);
+        assert!(matches!(
+            find_commit_at(&mut conn, before).await,
+            Err(sqlx::Error::RowNotFound)
+        ));
+
+        insert_commit(&mut conn, "commit4").await?;
+        assert_eq!(list_commits(&mut conn).await?, commits);
+        insert_commit(&mut conn, "commit5").await?;
+        assert_eq!(latest_commit(&mut conn).await?.as_deref(), Some("commit5"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,321 @@
+use serde::Deserialize;
+use sqlx::PgExecutor;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RawEndpoint {
+    address: Option<String>,
+    provider: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Endpoint {
+    pub id: i64,
+    pub address: String,
+    pub provider: Option<String>,
+    pub commit: String,
+    pub is_alive: bool,
+    pub kind: String,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+pub type Endpoints = Vec<Endpoint>;
+
+#[derive(Debug, Clone, PartialEq, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EndpointKind {
+    Rpc,
+    Rest,
+    Grpc,
+}
+
+impl EndpointKind {
+    pub fn from_str(s: &str) -> Option<EndpointKind> {
+        match s {
+            "rpc" => Some(EndpointKind::Rpc),
+            "rest" => Some(EndpointKind::Rest),
+            "grpc" => Some(EndpointKind::Grpc),
+            _ => None,
+        }
+    }
+
+    // Also the field name under chain.json's "apis" object.
+    pub fn as_str(&self) -> &str {
+        match self {
+            EndpointKind::Rpc => "rpc",
+            EndpointKind::Rest => "rest",
+            EndpointKind::Grpc => "grpc",
+        }
+    }
+}
+
+pub async fn find_endpoints(
+    executor: impl PgExecutor<'_>,
+    chain_id: i64,
+    kind: EndpointKind,
+) -> sqlx::Result<Vec<RawEndpoint>> {
+    sqlx::query_as!(
+        RawEndpoint,
+        r#"
+        select
+        jsonb_array_elements(chain_data->'apis'->$1)->>'address' as address,
+        jsonb_array_elements(chain_data->'apis'->$1)->>'provider' as provider
+        from chain where id = $2
+        "#,
+        kind.as_str(),
+        chain_id,
+    )
+    .fetch_all(executor)
+    .await
+}
+
+pub async fn insert_endpoint(
+    executor: impl PgExecutor<'_>,
+    chain_id: i64,
+    kind: EndpointKind,
+    endpoint: RawEndpoint,
+) -> anyhow::Result<()> {
+    let address = match endpoint.address {
+        Some(address) if !address.trim().is_empty() => address.trim().to_string(),
+        _ => anyhow::bail!("endpoint is missing address"),
+    };
+
+    // The bogus DO UPDATE SET ensures we don't get a RowNotFound error.
+    match sqlx::query!(
+        r#"
+        INSERT INTO endpoint (chain_id_fk, address, kind, provider)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (chain_id_fk, address, kind) DO UPDATE SET is_alive = endpoint.is_alive
+        "#,
+        chain_id,
+        address,
+        kind.as_str(),
+        endpoint.provider,
+    )
+    .execute(executor)
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(err) => anyhow::bail!(err),
+    }
+}
+
+pub async fn all_recent_endpoints(executor: impl PgExecutor<'_>) -> sqlx::Result<Endpoints> {
+    sqlx::query_as!(
+        Endpoint,
+        r#"
+        WITH recent_chain AS (
+            SELECT commit FROM registry_commit ORDER BY created_at DESC LIMIT 1
+        )
+        SELECT endpoint.id, address, provider, kind, chain_commit.commit, endpoint.is_alive, endpoint.updated_at
+        FROM endpoint INNER JOIN chain ON chain.id = endpoint.chain_id_fk
+        INNER JOIN chain_commit ON chain_commit.chain_id_fk = chain.id
+        WHERE chain_commit.commit IN (SELECT commit FROM recent_chain)
+        "#,
+    )
+    .fetch_all(executor)
+    .await
+}
+
+pub async fn recent_endpoints(
+    executor: impl PgExecutor<'_>,
+    chain_name: &str,
+    network: &str,
+) -> sqlx::Result<Endpoints> {
+    sqlx::query_as!(
+        Endpoint,
+        r#"
+        WITH recent_chain AS (
+            SELECT commit FROM registry_commit ORDER BY created_at DESC LIMIT 1
+        )
+        SELECT endpoint.id, endpoint.address, endpoint.provider, endpoint.kind, endpoint.is_alive, chain_commit.commit, endpoint.updated_at
+        FROM endpoint INNER JOIN chain ON chain.id = endpoint.chain_id_fk
+        INNER JOIN chain_commit ON chain_commit.chain_id_fk = chain.id
+        WHERE chain_commit.commit IN (SELECT commit FROM recent_chain) AND
+        chain.name = $1 AND
+        chain.network = $2
+        ORDER BY endpoint.kind, endpoint.id
+        "#,
+        chain_name,
+        network,
+    )
+    .fetch_all(executor)
+    .await
+}
+
+pub struct EndpointFilter {
+    pub chain_name: String,
+    pub network: String,
+    pub kind: Option<EndpointKind>,
+    pub include_all: bool,
+}
+
+pub async fn filter_recent_endpoints(
+    executor: impl PgExecutor<'_>,
+    filter: &EndpointFilter,
+) -> sqlx::Result<Endpoints> {
+    let endpoints = recent_endpoints(executor, &filter.chain_name, &filter.network).await?;
+
+    let filtered: Endpoints = endpoints
+        .into_iter()
+        .filter(|e| match filter.kind {
+            Some(kind) => EndpointKind::from_str(e.kind.as_str()) == Some(kind),
+            None => true,
+        })
+        .filter(|e| filter.include_all || e.is_alive)
+        .collect();
+
+    if filtered.is_empty() {
+        return Err(sqlx::Error::RowNotFound);
+    }
+
+    Ok(filtered)
+}
+
+/// Sets is_alive for (endpoint id, alive) pairs in one round trip.
+pub async fn update_liveness(
+    executor: impl PgExecutor<'_>,
+    results: &[(i64, bool)],
+) -> sqlx::Result<u64> {
+    let (ids, alive): (Vec<i64>, Vec<bool>) = results.iter().copied().unzip();
+    let result = sqlx::query!(
+        r#"
+        UPDATE endpoint SET is_alive = result.alive
+        FROM UNNEST($1::bigint[], $2::bool[]) AS result(id, alive)
+        WHERE endpoint.id = result.id
+        "#,
+        &ids,
+        &alive,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::PgPool;
+    use tokio_test::*;
+
+    #[sqlx::test(fixtures("chains"))]
+    async fn test_find_endpoints(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+
+        let rpcs = find_endpoints(&mut conn, 1, EndpointKind::Rpc).await?;
+        assert_eq!(rpcs.len(), 19);
+        assert_eq!(
+            rpcs[0].address.as_deref(),
+            Some("https://rpc-cosmoshub.blockapsis.com")
+        );
+        assert_eq!(rpcs[0].provider.as_deref(), Some("chainapsis"));
+
+        let rests = find_endpoints(&mut conn, 1, EndpointKind::Rest).await?;
+        assert_eq!(rests.len(), 13);
+
+        let grpcs = find_endpoints(&mut conn, 1, EndpointKind::Grpc).await?;
+        assert_eq!(grpcs.len(), 7);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("chains"))]
+    async fn test_insert_endpoint(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+
+        let endpoint = RawEndpoint {
+            address: Some("https://rpc.example.com".to_string()),
+            provider: Some("Example".to_string()),
+        };
+
+        assert_ok!(insert_endpoint(&mut conn, 1, EndpointKind::Rpc, endpoint.clone()).await);
+        assert_ok!(insert_endpoint(&mut conn, 1, EndpointKind::Rpc, endpoint.clone()).await);
+
+        let inserted = sqlx::query!(
+            r#"
+            SELECT * FROM endpoint
+            WHERE chain_id_fk = 1
+            "#,
+        )
+        .fetch_all(&mut conn)
+        .await?;
+
+        assert_eq!(inserted.len(), 1);
+        assert_eq!(inserted[0].address, "https://rpc.example.com");
+        assert_eq!(inserted[0].kind, "rpc");
+        assert_eq!(inserted[0].provider.as_deref(), Some("Example"));
+        assert!(inserted[0].is_alive);
+
+        let missing = RawEndpoint {
+            address: None,
+            provider: None,
+        };
+        assert_err!(insert_endpoint(&mut conn, 1, EndpointKind::Rpc, missing).await);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("recent_endpoints"))]
+    async fn test_all_recent_endpoints(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+        let found = all_recent_endpoints(&mut conn).await?;
+
+        assert_eq!(found.len(), 4);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("recent_endpoints"))]
+    async fn test_filter_recent_endpoints(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+        let mut filter = EndpointFilter {
+            chain_name: "cosmoshub".to_string(),
+            network: "mainnet".to_string(),
+            kind: None,
+            include_all: true,
+        };
+        let found = filter_recent_endpoints(&mut conn, &filter).await?;
+        assert_eq!(found.len(), 3);
+        assert_eq!(found[0].kind, "grpc");
+        assert_eq!(found[0].commit, "new_commit");
+
+        filter.include_all = false;
+        let found = filter_recent_endpoints(&mut conn, &filter).await?;
+        assert_eq!(found.len(), 2);
+
+        filter.kind = Some(EndpointKind::Rpc);
+        let found = filter_recent_endpoints(&mut conn, &filter).await?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].address, "https://rpc.cosmos.example.com");
+
+        filter.kind = Some(EndpointKind::Rest);
+        let found = filter_recent_endpoints(&mut conn, &filter).await;
+        assert!(matches!(found, Err(sqlx::Error::RowNotFound)));
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("recent_endpoints"))]
+    async fn test_update_liveness(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+
+        assert_eq!(
+            update_liveness(&mut conn, &[(1, false), (2, true)]).await?,
+            2
+        );
+
+        let updated = sqlx::query!(
+            r#"
+            SELECT id, is_alive FROM endpoint WHERE id IN (1, 2) ORDER BY id
+            "#,
+        )
+        .fetch_all(&mut conn)
+        .await?;
+
+        assert!(!updated[0].is_alive);
+        assert!(updated[1].is_alive);
+        assert_eq!(update_liveness(&mut conn, &[]).await?, 0);
+
+        Ok(())
+    }
+}
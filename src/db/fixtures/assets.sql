@@ -0,0 +1,19 @@
+INSERT INTO asset (chain_id_fk, base, symbol, coingecko_id, type_asset, data)
+SELECT id, 'uatom', 'ATOM', 'cosmos', 'sdk.coin', '{"base": "uatom"}'
+FROM chain WHERE name = 'cosmoshub' AND commit = 'commit1';
+
+INSERT INTO asset (chain_id_fk, base, symbol, coingecko_id, type_asset, data)
+SELECT id, 'uatom', 'ATOM', 'cosmos', 'sdk.coin', '{"base": "uatom"}'
+FROM chain WHERE name = 'cosmoshub' AND commit = 'commit2';
+
+INSERT INTO asset (chain_id_fk, base, symbol, coingecko_id, type_asset, data)
+SELECT id, 'uosmo', 'OSMO', 'osmosis', 'sdk.coin', '{"base": "uosmo"}'
+FROM chain WHERE name = 'osmosis';
+
+INSERT INTO asset (chain_id_fk, base, symbol, coingecko_id, type_asset, data)
+SELECT id, 'ibc/27394', 'ATOM', NULL, 'ics20', '{"base": "ibc/27394"}'
+FROM chain WHERE name = 'osmosis';
+
+INSERT INTO asset_trace (asset_id_fk, position, chain_name, base_denom)
+SELECT id, 0, 'cosmoshub', 'uatom'
+FROM asset WHERE base = 'ibc/27394';
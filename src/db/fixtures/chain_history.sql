@@ -0,0 +1,55 @@
+INSERT INTO registry_commit (commit, created_at)
+VALUES ('commit1', '2023-04-20 10:00:00+00'),
+       ('commit2', '2023-04-20 11:00:00+00'),
+       ('commit3', '2023-04-20 12:00:00+00');
+
+-- cosmoshub changed at commit3. The old row is part of commit1 and commit2.
+INSERT INTO chain (id, name, network, commit, asset_data, chain_data, created_at)
+VALUES (1,
+        'cosmoshub',
+        'mainnet',
+        'commit1',
+        '{}',
+        '{"chain_id": "cosmoshub-3"}',
+        '2023-04-20 10:00:00+00');
+
+INSERT INTO chain (id, name, network, commit, asset_data, chain_data, created_at)
+VALUES (2,
+        'cosmoshub',
+        'mainnet',
+        'commit3',
+        '{}',
+        '{"chain_id": "cosmoshub-4"}',
+        '2023-04-20 12:00:00+00');
+
+-- osmosis never changed.
+INSERT INTO chain (id, name, network, commit, asset_data, chain_data, created_at)
+VALUES (3,
+        'osmosis',
+        'mainnet',
+        'commit1',
+        '{}',
+        '{"chain_id": "osmosis-1"}',
+        '2023-04-20 10:00:00+00');
+
+-- juno was added at commit3.
+INSERT INTO chain (id, name, network, commit, asset_data, chain_data, created_at)
+VALUES (4,
+        'juno',
+        'mainnet',
+        'commit3',
+        '{}',
+        '{"chain_id": "juno-1"}',
+        '2023-04-20 12:00:00+00');
+
+INSERT INTO chain_commit (commit, chain_id_fk)
+VALUES ('commit1', 1),
+       ('commit2', 1),
+       ('commit3', 2),
+       ('commit1', 3),
+       ('commit2', 3),
+       ('commit3', 3),
+       ('commit3', 4);
+
+-- Rows inserted later must not collide with the ids above.
+SELECT setval('chain_id_seq', (SELECT MAX(id) FROM chain));
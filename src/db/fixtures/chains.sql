@@ -15,3 +15,13 @@ VALUES (2,
         'previous_commit',
         '{}',
         '{}');
+
+-- Each chain row is only part of its own commit.
+INSERT INTO registry_commit (commit, created_at)
+SELECT commit, MAX(created_at)
+FROM chain
+GROUP BY commit;
+
+INSERT INTO chain_commit (commit, chain_id_fk)
+SELECT commit, id
+FROM chain;
@@ -0,0 +1,33 @@
+-- old path
+INSERT INTO ibc_path (network, chain_1, chain_2, commit, ibc_data, created_at)
+VALUES ('mainnet',
+        'cosmoshub',
+        'osmosis',
+        'old_commit',
+        '{}',
+        now() - interval '1 hour');
+
+-- removed after the old commit
+INSERT INTO ibc_path (network, chain_1, chain_2, commit, ibc_data, created_at)
+VALUES ('mainnet',
+        'cosmoshub',
+        'stargaze',
+        'old_commit',
+        '{}',
+        now() - interval '1 hour');
+
+-- new paths
+INSERT INTO ibc_path (network, chain_1, chain_2, commit, ibc_data)
+VALUES ('mainnet',
+        'cosmoshub',
+        'osmosis',
+        'new_commit',
+           -- cat tmp-chain-registry/_IBC/cosmoshub-osmosis.json | jq -c | pbcopy
+        '{"$schema":"../ibc_data.schema.json","chain_1":{"chain_name":"cosmoshub","client_id":"07-tendermint-259","connection_id":"connection-257"},"chain_2":{"chain_name":"osmosis","client_id":"07-tendermint-1","connection_id":"connection-1"},"channels":[{"chain_1":{"channel_id":"channel-141","port_id":"transfer"},"chain_2":{"channel_id":"channel-0","port_id":"transfer"},"ordering":"unordered","version":"ics20-1","tags":{"status":"live","preferred":true,"dex":"osmosis"}}]}');
+
+INSERT INTO ibc_path (network, chain_1, chain_2, commit, ibc_data)
+VALUES ('mainnet',
+        'juno',
+        'osmosis',
+        'new_commit',
+        '{"chain_1":{"chain_name":"juno","client_id":"07-tendermint-0","connection_id":"connection-0"},"chain_2":{"chain_name":"osmosis","client_id":"07-tendermint-1457","connection_id":"connection-1142"},"channels":[{"chain_1":{"channel_id":"channel-0","port_id":"transfer"},"chain_2":{"channel_id":"channel-42","port_id":"transfer"},"ordering":"unordered","version":"ics20-1"}]}');
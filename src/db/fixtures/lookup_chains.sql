@@ -0,0 +1,48 @@
+INSERT INTO registry_commit (commit, created_at)
+VALUES ('commit1', '2023-04-20 10:00:00+00'),
+       ('commit2', '2023-04-20 11:00:00+00');
+
+-- Only rows of the latest commit are found.
+INSERT INTO chain (name, network, commit, asset_data, chain_data, created_at)
+VALUES ('cosmoshub',
+        'mainnet',
+        'commit1',
+        '{}',
+        '{"chain_id": "cosmoshub-3", "bech32_prefix": "cosmos", "slip44": 118}',
+        '2023-04-20 10:00:00+00');
+
+INSERT INTO chain (name, network, commit, asset_data, chain_data, created_at)
+VALUES ('cosmoshub',
+        'mainnet',
+        'commit2',
+        '{"assets": [{"base": "uatom", "denom_units": [{"denom": "uatom", "exponent": 0}, {"denom": "atom", "exponent": 6}]}]}',
+        '{"chain_id": "cosmoshub-4", "bech32_prefix": "cosmos", "slip44": 118}',
+        '2023-04-20 11:00:00+00');
+
+INSERT INTO chain (name, network, commit, asset_data, chain_data, created_at)
+VALUES ('osmosis',
+        'mainnet',
+        'commit2',
+        '{"assets": [{"base": "uosmo", "denom_units": [{"denom": "uosmo", "exponent": 0}, {"denom": "osmo", "exponent": 6}]}]}',
+        '{"chain_id": "osmosis-1", "bech32_prefix": "osmo", "slip44": 118}',
+        '2023-04-20 11:00:00+00');
+
+INSERT INTO chain (name, network, commit, asset_data, chain_data, created_at)
+VALUES ('cosmoshubtestnet',
+        'testnet',
+        'commit2',
+        '{}',
+        '{"chain_id": "theta-testnet-001", "bech32_prefix": "cosmos", "slip44": 118}',
+        '2023-04-20 11:00:00+00');
+
+INSERT INTO chain (name, network, commit, asset_data, chain_data, created_at)
+VALUES ('evmos',
+        'mainnet',
+        'commit2',
+        '{}',
+        '{"chain_id": "evmos_9001-2", "bech32_prefix": "evmos", "slip44": 60}',
+        '2023-04-20 11:00:00+00');
+
+INSERT INTO chain_commit (commit, chain_id_fk)
+SELECT commit, id
+FROM chain;
@@ -0,0 +1,52 @@
+-- old chain
+INSERT INTO chain (id, name, network, commit, asset_data, chain_data, created_at)
+VALUES (1,
+        'cosmoshub',
+        'mainnet',
+        'old_commit',
+        '{}',
+        '{}',
+        now() - interval '1 hour');
+
+INSERT INTO endpoint (chain_id_fk, kind, address)
+VALUES (1, 'rpc', 'https://old-rpc.cosmos.example.com');
+
+-- new chain
+INSERT INTO chain (id, name, network, commit, asset_data, chain_data)
+VALUES (2,
+        'cosmoshub',
+        'mainnet',
+        'new_commit',
+        '{}',
+        '{}');
+
+INSERT INTO endpoint (chain_id_fk, kind, address)
+VALUES (2, 'rpc', 'https://rpc.cosmos.example.com');
+
+INSERT INTO endpoint (chain_id_fk, kind, address, is_alive)
+VALUES (2, 'rest', 'https://rest.cosmos.example.com', false);
+
+INSERT INTO endpoint (chain_id_fk, kind, address, provider)
+VALUES (2, 'grpc', 'grpc.cosmos.example.com:443', 'Example');
+
+-- different chain
+INSERT INTO chain (id, name, network, commit, asset_data, chain_data)
+VALUES (3,
+        'juno',
+        'mainnet',
+        'new_commit',
+        '{}',
+        '{}');
+
+INSERT INTO endpoint (chain_id_fk, kind, address)
+VALUES (3, 'rpc', 'https://rpc.juno.example.com');
+
+-- Each chain row is only part of its own commit.
+INSERT INTO registry_commit (commit, created_at)
+SELECT commit, MAX(created_at)
+FROM chain
+GROUP BY commit;
+
+INSERT INTO chain_commit (commit, chain_id_fk)
+SELECT commit, id
+FROM chain;
@@ -43,3 +43,13 @@ VALUES (3, 'seed', 'abc123@seed2.example.com');
 INSERT INTO peer (chain_id_fk, type, address)
 VALUES (3, 'persistent', 'efg@peer.example.com');
 
+
+-- Each chain row is only part of its own commit.
+INSERT INTO registry_commit (commit, created_at)
+SELECT commit, MAX(created_at)
+FROM chain
+GROUP BY commit;
+
+INSERT INTO chain_commit (commit, chain_id_fk)
+SELECT commit, id
+FROM chain;
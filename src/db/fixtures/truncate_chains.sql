@@ -29,3 +29,11 @@ VALUES (NOW(),
         'commit4',
         '{}',
         '{}');
+
+INSERT INTO registry_commit (commit, created_at)
+SELECT commit, created_at
+FROM chain;
+
+INSERT INTO chain_commit (commit, chain_id_fk)
+SELECT commit, id
+FROM chain;
@@ -0,0 +1,276 @@
+use sqlx::{types::JsonValue, PgExecutor};
+use std::fs;
+use std::path::PathBuf;
+
+pub async fn insert_ibc_path(
+    executor: impl PgExecutor<'_>,
+    path: PathBuf,
+    network: String,
+    commit: &String,
+) -> anyhow::Result<i64> {
+    let file_name = path.file_name().unwrap().to_str().unwrap();
+    let ibc_json = match fs::read_to_string(&path) {
+        Ok(c) => c,
+        Err(err) => anyhow::bail!(
+            "failed to read ibc file {} {}: {:?}",
+            file_name,
+            network,
+            err,
+        ),
+    };
+    let ibc_json: serde_json::Value = serde_json::from_str(&ibc_json)?;
+
+    let chain_name = |field: &str| -> anyhow::Result<String> {
+        match ibc_json
+            .get(field)
+            .and_then(|c| c.get("chain_name"))
+            .and_then(|c| c.as_str())
+        {
+            Some(name) => Ok(name.to_string()),
+            None => anyhow::bail!("ibc file {} is missing {}.chain_name", file_name, field),
+        }
+    };
+    let chain_1 = chain_name("chain_1")?;
+    let chain_2 = chain_name("chain_2")?;
+
+    // Same as chains, DO NOTHING causes a RowNotFound error.
+    match sqlx::query!(
+        r#"
+        INSERT INTO ibc_path (network, chain_1, chain_2, ibc_data, commit)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (network, chain_1, chain_2, commit) DO UPDATE SET commit = $5
+        RETURNING id
+        "#,
+        network,
+        chain_1,
+        chain_2,
+        ibc_json,
+        commit,
+    )
+    .fetch_one(executor)
+    .await
+    {
+        Ok(row) => Ok(row.id),
+        Err(err) => anyhow::bail!(
+            "failed to insert ibc path {} {}: {:?}",
+            file_name,
+            network,
+            err,
+        ),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IbcPath {
+    pub commit: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub ibc_data: JsonValue,
+}
+
+/// Finds the IBC path between two chains from the most recent commit. Order of the chains does not
+/// matter.
+pub async fn find_ibc_path(
+    executor: impl PgExecutor<'_>,
+    network: &str,
+    chain_a: &str,
+    chain_b: &str,
+) -> sqlx::Result<IbcPath> {
+    sqlx::query_as!(
+        IbcPath,
+        r#"
+        WITH recent AS (
+            SELECT commit FROM ibc_path WHERE network = $1 ORDER BY created_at DESC LIMIT 1
+        )
+        SELECT commit, created_at, ibc_data FROM ibc_path
+        WHERE network = $1 AND
+        commit IN (SELECT commit FROM recent) AND
+        ((chain_1 = $2 AND chain_2 = $3) OR (chain_1 = $3 AND chain_2 = $2))
+        LIMIT 1
+        "#,
+        network,
+        chain_a,
+        chain_b,
+    )
+    .fetch_one(executor)
+    .await
+}
+
+/// Lists all IBC paths from the most recent commit where the chain is either side of the path.
+pub async fn list_ibc_paths(
+    executor: impl PgExecutor<'_>,
+    network: &str,
+    chain_name: &str,
+) -> sqlx::Result<Vec<IbcPath>> {
+    sqlx::query_as!(
+        IbcPath,
+        r#"
+        WITH recent AS (
+            SELECT commit FROM ibc_path WHERE network = $1 ORDER BY created_at DESC LIMIT 1
+        )
+        SELECT commit, created_at, ibc_data FROM ibc_path
+        WHERE network = $1 AND
+        commit IN (SELECT commit FROM recent) AND
+        (chain_1 = $2 OR chain_2 = $2)
+        ORDER BY chain_1, chain_2
+        "#,
+        network,
+        chain_name,
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Removes IBC paths whose commit was truncated. Call after chains are truncated.
+pub async fn truncate_old_ibc_paths(executor: impl PgExecutor<'_>) -> sqlx::Result<()> {
+    sqlx::query!(
+        r#"
+        DELETE FROM ibc_path WHERE commit NOT IN (SELECT commit FROM registry_commit)
+        "#,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::postgres::PgPool;
+    use tempfile::TempDir;
+
+    #[sqlx::test]
+    async fn test_insert_ibc_path(pool: PgPool) -> sqlx::Result<()> {
+        let test_path = TempDir::new()
+            .unwrap()
+            .into_path()
+            .join("cosmoshub-osmosis.json");
+        fs::write(
+            &test_path,
+            r#"{"chain_1":{"chain_name":"cosmoshub","client_id":"07-tendermint-259","connection_id":"connection-257"},"chain_2":{"chain_name":"osmosis","client_id":"07-tendermint-1","connection_id":"connection-1"},"channels":[]}"#,
+        )?;
+
+        let mut conn = pool.acquire().await?;
+
+        let id = insert_ibc_path(
+            &mut conn,
+            test_path.clone(),
+            "mainnet".to_string(),
+            &"stub commit".to_string(),
+        )
+        .await
+        .unwrap();
+
+        assert_ne!(id, 0);
+
+        let path = sqlx::query!("SELECT * FROM ibc_path")
+            .fetch_one(&mut conn)
+            .await?;
+        assert_eq!(path.network, "mainnet");
+        assert_eq!(path.chain_1, "cosmoshub");
+        assert_eq!(path.chain_2, "osmosis");
+        assert_eq!(path.commit, "stub commit");
+        assert!(path.ibc_data.get("channels").unwrap().is_array());
+
+        // Ensure we don't insert duplicate paths
+        insert_ibc_path(
+            &mut conn,
+            test_path.clone(),
+            "mainnet".to_string(),
+            &"stub commit".to_string(),
+        )
+        .await
+        .unwrap();
+
+        let count = sqlx::query!("SELECT count(*) FROM ibc_path")
+            .fetch_one(&mut conn)
+            .await?
+            .count
+            .unwrap();
+        assert_eq!(count, 1);
+
+        // Missing chain names are an error
+        fs::write(&test_path, r#"{"chain_1":{},"chain_2":{}}"#)?;
+        assert!(insert_ibc_path(
+            &mut conn,
+            test_path,
+            "mainnet".to_string(),
+            &"stub commit".to_string(),
+        )
+        .await
+        .is_err());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("ibc_paths"))]
+    async fn test_find_ibc_path(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+
+        let path = find_ibc_path(&mut conn, "mainnet", "cosmoshub", "osmosis").await?;
+        assert_eq!(path.commit, "new_commit");
+        assert_eq!(
+            path.ibc_data["chain_1"]["chain_name"].as_str(),
+            Some("cosmoshub")
+        );
+
+        let path = find_ibc_path(&mut conn, "mainnet", "osmosis", "cosmoshub").await?;
+        assert_eq!(path.commit, "new_commit");
+        assert_eq!(
+            path.ibc_data["chain_1"]["connection_id"].as_str(),
+            Some("connection-257")
+        );
+
+        let result = find_ibc_path(&mut conn, "testnet", "cosmoshub", "osmosis").await;
+        assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
+
+        // Removed from the registry since old_commit.
+        let result = find_ibc_path(&mut conn, "mainnet", "cosmoshub", "stargaze").await;
+        assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("ibc_paths"))]
+    async fn test_list_ibc_paths(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+
+        let paths = list_ibc_paths(&mut conn, "mainnet", "osmosis").await?;
+        assert_eq!(paths.len(), 2);
+        assert_eq!(
+            paths[0].ibc_data["chain_1"]["chain_name"].as_str(),
+            Some("cosmoshub")
+        );
+        assert_eq!(
+            paths[1].ibc_data["chain_1"]["chain_name"].as_str(),
+            Some("juno")
+        );
+        assert!(paths.iter().all(|p| p.commit == "new_commit"));
+
+        let paths = list_ibc_paths(&mut conn, "mainnet", "cosmoshub").await?;
+        assert_eq!(paths.len(), 1);
+
+        let paths = list_ibc_paths(&mut conn, "mainnet", "stargaze").await?;
+        assert!(paths.is_empty());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("ibc_paths"))]
+    async fn test_truncate_old_ibc_paths(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+
+        crate::db::commit::insert_commit(&mut conn, "new_commit").await?;
+
+        truncate_old_ibc_paths(&mut conn).await?;
+
+        let commits = sqlx::query!("SELECT DISTINCT commit FROM ibc_path")
+            .fetch_all(&mut conn)
+            .await?;
+
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].commit, "new_commit");
+
+        Ok(())
+    }
+}
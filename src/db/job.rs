@@ -0,0 +1,120 @@
+use sqlx::PgExecutor;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobRun {
+    pub job: String,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub finished_at: chrono::DateTime<chrono::Utc>,
+    pub succeeded: bool,
+    pub items: i64,
+}
+
+/// Records a finished run and prunes runs older than 7 days.
+pub async fn record_run(
+    executor: impl PgExecutor<'_>,
+    job: &str,
+    started_at: chrono::DateTime<chrono::Utc>,
+    succeeded: bool,
+    items: i64,
+) -> sqlx::Result<()> {
+    sqlx::query!(
+        r#"
+        WITH pruned AS (
+            DELETE FROM job_run WHERE finished_at < NOW() - INTERVAL '7 days'
+        )
+        INSERT INTO job_run (job, started_at, succeeded, items) VALUES ($1, $2, $3, $4)
+        "#,
+        job,
+        started_at,
+        succeeded,
+        items,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// The most recent run of each job.
+pub async fn latest_runs(executor: impl PgExecutor<'_>) -> sqlx::Result<Vec<JobRun>> {
+    sqlx::query_as!(
+        JobRun,
+        r#"
+        SELECT DISTINCT ON (job) job, started_at, finished_at, succeeded, items
+        FROM job_run
+        ORDER BY job, finished_at DESC
+        "#,
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// When the job last finished successfully, if within the 7 days of retained runs.
+pub async fn last_succeeded_at(
+    executor: impl PgExecutor<'_>,
+    job: &str,
+) -> sqlx::Result<Option<chrono::DateTime<chrono::Utc>>> {
+    let row = sqlx::query!(
+        r#"
+        SELECT MAX(finished_at) as finished_at FROM job_run WHERE job = $1 AND succeeded
+        "#,
+        job,
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(row.finished_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::PgPool;
+
+    #[sqlx::test]
+    async fn test_record_run(pool: PgPool) -> sqlx::Result<()> {
+        let started_at = chrono::Utc::now() - chrono::Duration::seconds(30);
+        record_run(&pool, "hydrate", started_at, false, 0).await?;
+        record_run(&pool, "hydrate", started_at, true, 12).await?;
+        record_run(&pool, "liveness", started_at, true, 40).await?;
+        sqlx::query!(
+            "INSERT INTO job_run (job, started_at, finished_at, succeeded) VALUES ('liveness', NOW() - INTERVAL '8 days', NOW() - INTERVAL '8 days', true)"
+        )
+        .execute(&pool)
+        .await?;
+        record_run(&pool, "liveness", started_at, true, 41).await?;
+
+        let runs = latest_runs(&pool).await?;
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].job, "hydrate");
+        assert!(runs[0].succeeded);
+        assert_eq!(runs[0].items, 12);
+        assert_eq!(runs[1].items, 41);
+        assert!(runs[1].finished_at > runs[1].started_at);
+
+        let count = sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM job_run"#)
+            .fetch_one(&pool)
+            .await?
+            .count;
+        assert_eq!(count, 4);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_last_succeeded_at(pool: PgPool) -> sqlx::Result<()> {
+        assert_eq!(last_succeeded_at(&pool, "hydrate").await?, None);
+
+        let started_at = chrono::Utc::now() - chrono::Duration::seconds(30);
+        record_run(&pool, "hydrate", started_at, true, 12).await?;
+        let succeeded = last_succeeded_at(&pool, "hydrate").await?.unwrap();
+        assert!(succeeded > started_at);
+
+        // Later failures and other jobs do not count.
+        record_run(&pool, "hydrate", started_at, false, 0).await?;
+        record_run(&pool, "liveness", started_at, true, 40).await?;
+        assert_eq!(last_succeeded_at(&pool, "hydrate").await?, Some(succeeded));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use sqlx::PgExecutor;
+
+// Keys for session level advisory locks. Postgres shares the key space with everything else using
+// the database, so keep keys unique per job.
+pub const HYDRATE_LOCK: i64 = 7_001;
+pub const LIVENESS_LOCK: i64 = 7_002;
+pub const CRAWL_LOCK: i64 = 7_003;
+
+/// Tries to take a session level advisory lock without waiting. Returns false if another session
+/// holds the lock. The lock belongs to the connection, so release it with the same connection.
+pub async fn try_lock(executor: impl PgExecutor<'_>, key: i64) -> sqlx::Result<bool> {
+    let row = sqlx::query!(
+        r#"
+        SELECT pg_try_advisory_lock($1) as "locked!"
+        "#,
+        key,
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(row.locked)
+}
+
+pub async fn unlock(executor: impl PgExecutor<'_>, key: i64) -> sqlx::Result<bool> {
+    let row = sqlx::query!(
+        r#"
+        SELECT pg_advisory_unlock($1) as "unlocked!"
+        "#,
+        key,
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(row.unlocked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::PgPool;
+
+    #[sqlx::test]
+    async fn test_lock(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn1 = pool.acquire().await?;
+        let mut conn2 = pool.acquire().await?;
+
+        assert!(try_lock(&mut conn1, HYDRATE_LOCK).await?);
+        assert!(!try_lock(&mut conn2, HYDRATE_LOCK).await?);
+        assert!(try_lock(&mut conn2, LIVENESS_LOCK).await?);
+
+        // Only the holder can unlock.
+        assert!(!unlock(&mut conn2, HYDRATE_LOCK).await?);
+        assert!(unlock(&mut conn1, HYDRATE_LOCK).await?);
+        assert!(try_lock(&mut conn2, HYDRATE_LOCK).await?);
+
+        Ok(())
+    }
+}
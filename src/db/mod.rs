@@ -1,2 +1,9 @@
+pub mod asset;
 pub mod chain;
+pub mod commit;
+pub mod endpoint;
+pub mod ibc;
+pub mod job;
+pub mod lock;
+pub mod notify;
 pub mod peer;
@@ -0,0 +1,52 @@
+use sqlx::postgres::{PgListener, PgPool};
+use sqlx::PgExecutor;
+
+// Channel for LISTEN/NOTIFY. The payload says what changed, e.g. hydrate or liveness.
+pub const UPDATE_CHANNEL: &str = "registry_update";
+
+/// Tells listeners that registry data changed. Inside a transaction, the notification is only sent
+/// on commit.
+pub async fn notify_update(executor: impl PgExecutor<'_>, source: &str) -> sqlx::Result<()> {
+    sqlx::query!(
+        r#"
+        SELECT pg_notify($1, $2)
+        "#,
+        UPDATE_CHANNEL,
+        source,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Listens on a dedicated connection that reconnects on its own.
+pub async fn listen_updates(pool: &PgPool) -> sqlx::Result<PgListener> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(UPDATE_CHANNEL).await?;
+    Ok(listener)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test]
+    async fn test_notify_update(pool: PgPool) -> sqlx::Result<()> {
+        let mut listener = listen_updates(&pool).await?;
+
+        notify_update(&pool, "hydrate").await?;
+        let notification = listener.recv().await?;
+        assert_eq!(notification.channel(), UPDATE_CHANNEL);
+        assert_eq!(notification.payload(), "hydrate");
+
+        // Nothing is sent for a rolled back transaction.
+        let mut tx = pool.begin().await?;
+        notify_update(&mut tx, "rolled back").await?;
+        tx.rollback().await?;
+        notify_update(&pool, "liveness").await?;
+        assert_eq!(listener.recv().await?.payload(), "liveness");
+
+        Ok(())
+    }
+}
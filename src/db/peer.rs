@@ -1,6 +1,10 @@
+use crate::liveness::{CheckError, PeerCheck};
 use serde::Deserialize;
 use sqlx::PgExecutor;
 
+/// How many days of liveness checks to keep. Uptime and latency stats cover this window.
+pub const CHECK_HISTORY_DAYS: i32 = 30;
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct RawPeer {
     node_id: Option<String>,
@@ -15,6 +19,17 @@ pub struct Peer {
     pub is_alive: bool,
     pub peer_type: String,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    pub advertised_network: Option<String>,
+    // True if the peer advertised a network other than the chain's chain_id.
+    pub network_mismatch: bool,
+    // Percentage of successful checks, 0 to 100.
+    pub uptime: Option<f64>,
+    pub last_seen_alive: Option<chrono::DateTime<chrono::Utc>>,
+    pub median_latency_ms: Option<f64>,
+    // registry or discovered
+    pub source: String,
+    // Consecutive failed checks. 0 if the last check succeeded.
+    pub failure_streak: i32,
 }
 
 pub type Peers = Vec<Peer>;
@@ -24,7 +39,7 @@ pub fn find_commit(peers: &Peers) -> Option<String> {
 }
 
 pub fn find_updated_at(peers: &Peers) -> Option<chrono::DateTime<chrono::Utc>> {
-    peers.iter().map(|p| p.updated_at.clone()).max()
+    peers.iter().map(|p| p.updated_at).max()
 }
 
 pub fn filter_by_type(peers: &Peers, peer_type: PeerType) -> Peers {
@@ -65,6 +80,24 @@ impl PeerType {
     }
 }
 
+/// Where a peer's address came from.
+#[derive(Debug, Clone, PartialEq, Copy)]
+pub enum PeerSource {
+    /// Listed in chain.json.
+    Registry,
+    /// Found by crawling RPC /net_info endpoints.
+    Discovered,
+}
+
+impl PeerSource {
+    pub fn as_str(&self) -> &str {
+        match self {
+            PeerSource::Registry => "registry",
+            PeerSource::Discovered => "discovered",
+        }
+    }
+}
+
 pub async fn find_peers(
     executor: impl PgExecutor<'_>,
     chain_id: i64,
@@ -97,11 +130,29 @@ pub async fn insert_peer(
     };
     let address = format!("{}@{}", node_id, address);
 
+    // A new chain row starts its peers where the same peer on the chain's previous row left off,
+    // so changed content does not reset liveness. A peer without a previous row is never checked.
     // The bogus DO UPDATE SET ensures we don't get a RowNotFound error.
     match sqlx::query!(
         r#"
-        INSERT INTO peer (chain_id_fk, address, type)
-        VALUES ($1, $2, $3)
+        WITH prior_chain AS (
+            SELECT prior.id FROM chain
+            INNER JOIN chain prior ON prior.name = chain.name AND prior.network = chain.network
+            WHERE chain.id = $1 AND prior.id <> chain.id
+            ORDER BY prior.created_at DESC, prior.id DESC
+            LIMIT 1
+        ), prior_peer AS (
+            SELECT peer.is_alive, peer.failure_streak, peer.success_streak, peer.advertised_network
+            FROM peer INNER JOIN prior_chain ON prior_chain.id = peer.chain_id_fk
+            WHERE peer.address = $2 AND peer.type = $3
+        )
+        INSERT INTO peer (chain_id_fk, address, type, is_alive, failure_streak, success_streak, advertised_network)
+        SELECT $1, $2, $3,
+        COALESCE(prior_peer.is_alive, true),
+        COALESCE(prior_peer.failure_streak, 0),
+        COALESCE(prior_peer.success_streak, 0),
+        prior_peer.advertised_network
+        FROM (SELECT 1) one LEFT JOIN prior_peer ON true
         ON CONFLICT (chain_id_fk, address, type) DO UPDATE SET is_alive = peer.is_alive
         "#,
         chain_id,
@@ -116,15 +167,142 @@ pub async fn insert_peer(
     }
 }
 
+/// Inserts a peer found by the crawler as an alive persistent peer. Does nothing if the chain
+/// already has the address as any type of peer. Returns true if the peer was inserted.
+pub async fn insert_discovered_peer(
+    executor: impl PgExecutor<'_>,
+    chain_id: i64,
+    address: &str,
+    advertised_network: &str,
+) -> sqlx::Result<bool> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO peer (chain_id_fk, address, type, source, is_alive, advertised_network)
+        SELECT $1, $2, $3, $4, true, $5
+        WHERE NOT EXISTS (SELECT 1 FROM peer WHERE chain_id_fk = $1 AND address = $2)
+        ON CONFLICT (chain_id_fk, address, type) DO NOTHING
+        "#,
+        chain_id,
+        address,
+        PeerType::Persistent.as_str(),
+        PeerSource::Discovered.as_str(),
+        advertised_network,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Copies the discovered peers of the chain's previous row to a newly inserted row, along with
+/// their liveness. Peers the new row already lists in chain.json are skipped. Returns the number of
+/// peers copied.
+pub async fn carry_discovered_peers(
+    executor: impl PgExecutor<'_>,
+    chain_id: i64,
+) -> sqlx::Result<u64> {
+    let result = sqlx::query!(
+        r#"
+        WITH prior_chain AS (
+            SELECT prior.id FROM chain
+            INNER JOIN chain prior ON prior.name = chain.name AND prior.network = chain.network
+            WHERE chain.id = $1 AND prior.id <> chain.id
+            ORDER BY prior.created_at DESC, prior.id DESC
+            LIMIT 1
+        )
+        INSERT INTO peer (chain_id_fk, address, type, source, is_alive, failure_streak,
+        success_streak, advertised_network)
+        SELECT $1, peer.address, peer.type, peer.source, peer.is_alive, peer.failure_streak,
+        peer.success_streak, peer.advertised_network
+        FROM peer INNER JOIN prior_chain ON prior_chain.id = peer.chain_id_fk
+        WHERE peer.source = $2 AND
+        NOT EXISTS (SELECT 1 FROM peer existing WHERE existing.chain_id_fk = $1 AND existing.address = peer.address)
+        ON CONFLICT (chain_id_fk, address, type) DO NOTHING
+        "#,
+        chain_id,
+        PeerSource::Discovered.as_str(),
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Deletes discovered peers that failed at least `failures` liveness checks in a row. Registry
+/// peers are kept, since the next hydrate would restore them anyway.
+pub async fn prune_discovered_peers(
+    executor: impl PgExecutor<'_>,
+    failures: i32,
+) -> sqlx::Result<u64> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM peer WHERE source = $1 AND failure_streak >= $2
+        "#,
+        PeerSource::Discovered.as_str(),
+        failures,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Where the crawler starts for a chain.
+#[derive(Debug, Clone)]
+pub struct CrawlChain {
+    pub id: i64,
+    pub network: String,
+    pub chain_name: String,
+    pub chain_id: Option<String>,
+    // Alive RPC endpoints.
+    pub rpcs: Vec<String>,
+    // Every peer the chain already has, registry or discovered.
+    pub peers: Vec<String>,
+}
+
+/// Chains at the latest commit with their alive RPC endpoints and known peers.
+pub async fn crawl_chains(executor: impl PgExecutor<'_>) -> sqlx::Result<Vec<CrawlChain>> {
+    sqlx::query_as!(
+        CrawlChain,
+        r#"
+        WITH recent_chain AS (
+            SELECT commit FROM registry_commit ORDER BY created_at DESC LIMIT 1
+        )
+        SELECT chain.id, chain.network, chain.name as chain_name,
+        chain.chain_data->>'chain_id' as chain_id,
+        ARRAY(
+            SELECT address FROM endpoint
+            WHERE endpoint.chain_id_fk = chain.id AND endpoint.kind = 'rpc' AND endpoint.is_alive
+            ORDER BY endpoint.id
+        ) as "rpcs!",
+        ARRAY(
+            SELECT address FROM peer WHERE peer.chain_id_fk = chain.id ORDER BY peer.id
+        ) as "peers!"
+        FROM chain INNER JOIN chain_commit ON chain_commit.chain_id_fk = chain.id
+        WHERE chain_commit.commit IN (SELECT commit FROM recent_chain)
+        ORDER BY chain.network, chain.name
+        "#,
+    )
+    .fetch_all(executor)
+    .await
+}
+
 pub async fn all_recent_peers(executor: impl PgExecutor<'_>) -> sqlx::Result<Peers> {
     sqlx::query_as!(
         Peer,
         r#"
         WITH recent_chain AS (
-            SELECT commit, created_at FROM chain ORDER BY created_at DESC LIMIT 1
+            SELECT commit FROM registry_commit ORDER BY created_at DESC LIMIT 1
         )
-        SELECT peer.id, address, peer.type as peer_type, chain.commit, peer.is_alive, peer.updated_at
-        FROM peer INNER JOIN chain ON chain.id = peer.chain_id_fk WHERE chain.commit IN (SELECT commit FROM recent_chain)
+        SELECT peer.id, address, peer.type as peer_type, chain_commit.commit, peer.is_alive, peer.updated_at,
+        peer.advertised_network,
+        COALESCE(peer.advertised_network <> chain.chain_data->>'chain_id', false) as "network_mismatch!",
+        -- Checking liveness does not need stats
+        NULL::float8 as uptime, NULL::timestamptz as last_seen_alive, NULL::float8 as median_latency_ms,
+        peer.source, peer.failure_streak
+        FROM peer INNER JOIN chain ON chain.id = peer.chain_id_fk
+        INNER JOIN chain_commit ON chain_commit.chain_id_fk = chain.id
+        WHERE chain_commit.commit IN (SELECT commit FROM recent_chain)
         "#,
     )
         .fetch_all(executor)
@@ -140,25 +318,52 @@ pub async fn recent_peers(
         Peer,
         r#"
         WITH recent_chain AS (
-            SELECT commit, created_at FROM chain ORDER BY created_at DESC LIMIT 1
+            SELECT commit FROM registry_commit ORDER BY created_at DESC LIMIT 1
         )
-        SELECT peer.id, peer.address, peer.type as peer_type, peer.is_alive, chain.commit, peer.updated_at
-        FROM peer INNER JOIN chain ON chain.id = peer.chain_id_fk 
-        WHERE chain.commit IN (SELECT commit FROM recent_chain) AND
+        SELECT peer.id, peer.address, peer.type as peer_type, peer.is_alive, chain_commit.commit, peer.updated_at,
+        peer.advertised_network,
+        COALESCE(peer.advertised_network <> chain.chain_data->>'chain_id', false) as "network_mismatch!",
+        stats.uptime, stats.last_seen_alive, stats.median_latency_ms, peer.source, peer.failure_streak
+        FROM peer INNER JOIN chain ON chain.id = peer.chain_id_fk
+        INNER JOIN chain_commit ON chain_commit.chain_id_fk = chain.id
+        LEFT JOIN LATERAL (
+            SELECT (100.0 * count(*) FILTER (WHERE success) / NULLIF(count(*), 0))::float8 as uptime,
+            max(checked_at) FILTER (WHERE success) as last_seen_alive,
+            (percentile_cont(0.5) WITHIN GROUP (ORDER BY latency_ms))::float8 as median_latency_ms
+            FROM peer_check
+            WHERE peer_check.network = chain.network AND peer_check.chain_name = chain.name AND
+            peer_check.peer_type = peer.type AND peer_check.address = peer.address AND
+            peer_check.checked_at > NOW() - make_interval(days => $3)
+        ) stats ON true
+        WHERE chain_commit.commit IN (SELECT commit FROM recent_chain) AND
         chain.name = $1 AND 
         chain.network = $2 
+        ORDER BY peer.id
         "#,
         chain_name,
         network,
+        CHECK_HISTORY_DAYS,
     )
     .fetch_all(executor)
     .await
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PeerSort {
+    // Lowest median latency first. Peers without latency data are last.
+    Latency,
+}
+
 pub struct PeerFilter {
     pub chain_name: String,
     pub network: String,
     pub include_all: bool,
+    // Minimum uptime percentage. Peers without any checks are excluded.
+    pub min_uptime: Option<f64>,
+    pub sort: Option<PeerSort>,
+    // Also return peers found by the crawler.
+    pub include_discovered: bool,
 }
 
 pub async fn filter_recent_peers(
@@ -169,11 +374,16 @@ pub async fn filter_recent_peers(
 
     let filtered: Vec<Peer> = peers
         .into_iter()
+        .filter(|p| filter.include_discovered || p.source == PeerSource::Registry.as_str())
         .filter(|p| {
             if filter.include_all {
                 return true;
             }
-            p.is_alive
+            p.is_alive && !p.network_mismatch
+        })
+        .filter(|p| match filter.min_uptime {
+            Some(min) => p.uptime.map(|uptime| uptime >= min).unwrap_or(false),
+            None => true,
         })
         .collect();
 
@@ -181,32 +391,177 @@ pub async fn filter_recent_peers(
         return Err(sqlx::Error::RowNotFound);
     }
 
+    let mut filtered = filtered;
+    if filter.sort == Some(PeerSort::Latency) {
+        filtered.sort_by(|a, b| match (a.median_latency_ms, b.median_latency_ms) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        });
+    }
+
     Ok(filtered)
 }
 
-pub async fn update_liveness<F: Fn(&str) -> anyhow::Result<()>>(
+/// The outcome of checking a peer.
+#[derive(Debug)]
+pub struct Liveness {
+    pub peer_id: i64,
+    pub check: Result<PeerCheck, CheckError>,
+}
+
+/// How many agreeing checks in a row it takes to flip a peer's is_alive, so one dropped
+/// connection does not remove a peer. A peer's first check sets is_alive directly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    pub failures: i32,
+    pub successes: i32,
+}
+
+impl Default for Thresholds {
+    /// Every check sets is_alive.
+    fn default() -> Self {
+        Thresholds {
+            failures: 1,
+            successes: 1,
+        }
+    }
+}
+
+/// Updates the peers' liveness and records the checks in their history in one round trip.
+/// A peer's advertised network (chain id) is kept until a later check reports another.
+pub async fn update_liveness(
     executor: impl PgExecutor<'_>,
-    peer: &Peer,
-    check: F,
-) -> sqlx::Result<()> {
-    let alive = check(&peer.address).is_ok();
-    sqlx::query!(
+    results: &[Liveness],
+    thresholds: Thresholds,
+) -> sqlx::Result<u64> {
+    let mut ids = Vec::with_capacity(results.len());
+    let mut alive = Vec::with_capacity(results.len());
+    let mut advertised_networks = Vec::with_capacity(results.len());
+    let mut error_kinds = Vec::with_capacity(results.len());
+    let mut latencies = Vec::with_capacity(results.len());
+    for result in results {
+        ids.push(result.peer_id);
+        alive.push(result.check.is_ok());
+        match &result.check {
+            Ok(check) => {
+                advertised_networks.push(check.advertised_network.clone());
+                error_kinds.push(None);
+                latencies.push(Some(check.latency.as_millis().min(i32::MAX as u128) as i32));
+            }
+            Err(err) => {
+                advertised_networks.push(None);
+                error_kinds.push(Some(err.kind().to_string()));
+                latencies.push(None);
+            }
+        }
+    }
+
+    let result = sqlx::query!(
+        r#"
+        WITH result AS (
+            SELECT * FROM UNNEST($1::bigint[], $2::bool[], $3::text[], $4::text[], $5::int[])
+            AS t(id, alive, advertised_network, error_kind, latency_ms)
+        ), updated AS (
+            -- On the right hand side, peer columns are the values before the update.
+            UPDATE peer SET
+            is_alive = CASE
+                -- A peer that has never been checked takes the first result as is.
+                WHEN peer.success_streak = 0 AND peer.failure_streak = 0 THEN result.alive
+                WHEN result.alive AND peer.success_streak + 1 >= $7 THEN true
+                WHEN NOT result.alive AND peer.failure_streak + 1 >= $6 THEN false
+                ELSE peer.is_alive
+            END,
+            failure_streak = CASE WHEN result.alive THEN 0 ELSE peer.failure_streak + 1 END,
+            success_streak = CASE WHEN result.alive THEN peer.success_streak + 1 ELSE 0 END,
+            advertised_network = COALESCE(result.advertised_network, peer.advertised_network)
+            FROM result, chain
+            WHERE peer.id = result.id AND chain.id = peer.chain_id_fk
+            RETURNING chain.network, chain.name, peer.type, peer.address, result.alive,
+            result.error_kind, result.latency_ms
+        )
+        INSERT INTO peer_check (network, chain_name, peer_type, address, success, error_kind, latency_ms)
+        SELECT network, name, type, address, alive, error_kind, latency_ms FROM updated
+        "#,
+        &ids,
+        &alive,
+        &advertised_networks as &[Option<String>],
+        &error_kinds as &[Option<String>],
+        &latencies as &[Option<i32>],
+        thresholds.failures,
+        thresholds.successes,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Deletes checks older than CHECK_HISTORY_DAYS.
+pub async fn prune_checks(executor: impl PgExecutor<'_>) -> sqlx::Result<u64> {
+    let result = sqlx::query!(
         r#"
-        UPDATE peer SET is_alive = $1 WHERE id = $2
+        DELETE FROM peer_check WHERE checked_at < NOW() - make_interval(days => $1)
         "#,
-        alive,
-        peer.id,
+        CHECK_HISTORY_DAYS,
     )
     .execute(executor)
     .await?;
 
-    Ok(())
+    Ok(result.rows_affected())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeerCount {
+    pub network: String,
+    pub chain_name: String,
+    pub alive: i64,
+    pub dead: i64,
+}
+
+/// Number of alive and dead peers per chain at the latest commit.
+pub async fn count_peers(executor: impl PgExecutor<'_>) -> sqlx::Result<Vec<PeerCount>> {
+    sqlx::query_as!(
+        PeerCount,
+        r#"
+        WITH recent_chain AS (
+            SELECT commit FROM registry_commit ORDER BY created_at DESC LIMIT 1
+        )
+        SELECT chain.network, chain.name as chain_name,
+        COUNT(*) FILTER (WHERE peer.is_alive) as "alive!",
+        COUNT(*) FILTER (WHERE NOT peer.is_alive) as "dead!"
+        FROM peer INNER JOIN chain ON chain.id = peer.chain_id_fk
+        INNER JOIN chain_commit ON chain_commit.chain_id_fk = chain.id
+        WHERE chain_commit.commit IN (SELECT commit FROM recent_chain)
+        GROUP BY chain.network, chain.name
+        ORDER BY chain.network, chain.name
+        "#,
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// When a peer was last updated by a liveness check or hydrate, if ever.
+pub async fn newest_updated_at(
+    executor: impl PgExecutor<'_>,
+) -> sqlx::Result<Option<chrono::DateTime<chrono::Utc>>> {
+    let row = sqlx::query!(
+        r#"
+        SELECT MAX(updated_at) as newest FROM peer
+        "#,
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(row.newest)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use sqlx::PgPool;
+    use std::time::Duration;
     use tokio_test::*;
 
     #[sqlx::test(fixtures("chains"))]
@@ -299,6 +654,9 @@ mod tests {
             chain_name: "cosmoshub".to_string(),
             network: "mainnet".to_string(),
             include_all: true,
+            min_uptime: None,
+            sort: None,
+            include_discovered: false,
         };
         let found = filter_recent_peers(&mut conn, &filter).await?;
         assert_eq!(found.len(), 2);
@@ -313,24 +671,142 @@ mod tests {
     }
 
     #[sqlx::test(fixtures("recent_peers"))]
-    async fn test_update_liveness(pool: PgPool) -> sqlx::Result<()> {
+    async fn test_filter_mismatched_peers(pool: PgPool) -> sqlx::Result<()> {
         let mut conn = pool.acquire().await?;
+        sqlx::query!(r#"UPDATE chain SET chain_data = '{"chain_id":"cosmoshub-4"}' WHERE id = 2"#)
+            .execute(&mut conn)
+            .await?;
+        sqlx::query!("UPDATE peer SET advertised_network = 'theta-testnet-001' WHERE chain_id_fk = 2 AND type = 'seed'")
+            .execute(&mut conn)
+            .await?;
+
+        let found = recent_peers(&mut conn, "cosmoshub", "mainnet").await?;
+        let seed = found.iter().find(|p| p.peer_type == "seed").unwrap();
+        assert_eq!(
+            seed.advertised_network.as_deref(),
+            Some("theta-testnet-001")
+        );
+        assert!(seed.network_mismatch);
+        let persistent = found.iter().find(|p| p.peer_type == "persistent").unwrap();
+        assert!(!persistent.network_mismatch);
 
-        let stub_liveness = |addr: &str| -> anyhow::Result<()> {
-            assert_eq!(addr, "stub@address");
-            anyhow::bail!("boom")
+        let mut filter = PeerFilter {
+            chain_name: "cosmoshub".to_string(),
+            network: "mainnet".to_string(),
+            include_all: false,
+            min_uptime: None,
+            sort: None,
+            include_discovered: false,
         };
+        // The only alive peer is the mismatched seed.
+        let found = filter_recent_peers(&mut conn, &filter).await;
+        assert!(matches!(found, Err(sqlx::Error::RowNotFound)));
+
+        filter.include_all = true;
+        let found = filter_recent_peers(&mut conn, &filter).await?;
+        assert_eq!(found.len(), 2);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("recent_peers"))]
+    async fn test_insert_discovered_peer(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+        let address = "def456@203.0.113.7:26656";
 
-        let peer = Peer {
-            id: 1,
-            address: "stub@address".to_string(),
-            commit: "stub".to_string(),
-            peer_type: "seed".to_string(),
-            is_alive: true,
-            updated_at: chrono::Utc::now(),
+        assert!(insert_discovered_peer(&mut conn, 2, address, "cosmoshub-4").await?);
+        assert!(!insert_discovered_peer(&mut conn, 2, address, "cosmoshub-4").await?);
+        // Already listed in the registry, also as another type of peer.
+        assert!(
+            !insert_discovered_peer(&mut conn, 2, "efg987@public-persistent.com:26656", "").await?
+        );
+        assert!(
+            !insert_discovered_peer(&mut conn, 2, "abc123@public-seed-node.com:26656", "").await?
+        );
+
+        let found = recent_peers(&mut conn, "cosmoshub", "mainnet").await?;
+        let discovered = found.iter().find(|p| p.address == address).unwrap();
+        assert_eq!(discovered.source, "discovered");
+        assert_eq!(discovered.peer_type, "persistent");
+        assert_eq!(
+            discovered.advertised_network.as_deref(),
+            Some("cosmoshub-4")
+        );
+        assert!(discovered.is_alive);
+
+        let mut filter = PeerFilter {
+            chain_name: "cosmoshub".to_string(),
+            network: "mainnet".to_string(),
+            include_all: false,
+            min_uptime: None,
+            sort: None,
+            include_discovered: false,
         };
+        let found = filter_recent_peers(&mut conn, &filter).await?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].source, "registry");
+
+        filter.include_discovered = true;
+        let found = filter_recent_peers(&mut conn, &filter).await?;
+        assert_eq!(found.len(), 2);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("recent_peers"))]
+    async fn test_crawl_chains(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+        sqlx::query!(r#"UPDATE chain SET chain_data = '{"chain_id":"cosmoshub-4"}' WHERE id = 2"#)
+            .execute(&mut conn)
+            .await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO endpoint (chain_id_fk, kind, address, is_alive) VALUES
+            (2, 'rpc', 'https://rpc.cosmos.example.com', true),
+            (2, 'rpc', 'https://dead-rpc.cosmos.example.com', false),
+            (2, 'rest', 'https://rest.cosmos.example.com', true)
+            "#
+        )
+        .execute(&mut conn)
+        .await?;
+
+        let found = crawl_chains(&mut conn).await?;
+        assert_eq!(found.len(), 2);
+
+        let cosmoshub = &found[0];
+        assert_eq!(cosmoshub.id, 2);
+        assert_eq!(cosmoshub.chain_name, "cosmoshub");
+        assert_eq!(cosmoshub.chain_id.as_deref(), Some("cosmoshub-4"));
+        assert_eq!(cosmoshub.rpcs, vec!["https://rpc.cosmos.example.com"]);
+        assert_eq!(
+            cosmoshub.peers,
+            vec![
+                "abc123@public-seed-node.com:26656",
+                "efg987@public-persistent.com:26656"
+            ]
+        );
+
+        let juno = &found[1];
+        assert_eq!(juno.chain_id, None);
+        assert!(juno.rpcs.is_empty());
+        assert_eq!(juno.peers.len(), 3);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("recent_peers"))]
+    async fn test_update_liveness(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+
+        let liveness = |check| [Liveness { peer_id: 1, check }];
 
-        update_liveness(&mut conn, &peer, stub_liveness).await?;
+        let refused = Err(CheckError::Connect(
+            std::io::ErrorKind::ConnectionRefused.into(),
+        ));
+        assert_eq!(
+            update_liveness(&mut conn, &liveness(refused), Thresholds::default()).await?,
+            1
+        );
 
         let updated = sqlx::query!(
             r#"
@@ -342,18 +818,449 @@ mod tests {
 
         assert!(!updated.is_alive);
 
-        let stub_liveness = |_: &str| -> anyhow::Result<()> { Ok(()) };
-        update_liveness(&mut conn, &peer, stub_liveness).await?;
+        update_liveness(
+            &mut conn,
+            &liveness(Ok(PeerCheck::default())),
+            Thresholds::default(),
+        )
+        .await?;
 
         let updated = sqlx::query!(
             r#"
-            SELECT is_alive FROM peer WHERE id = 1
+            SELECT is_alive, advertised_network FROM peer WHERE id = 1
             "#,
         )
         .fetch_one(&mut conn)
         .await?;
 
         assert!(updated.is_alive);
+        assert_eq!(updated.advertised_network, None);
+
+        let handshake = Ok(PeerCheck {
+            latency: Duration::from_millis(120),
+            advertised_network: Some("cosmoshub-4".to_string()),
+        });
+        update_liveness(&mut conn, &liveness(handshake), Thresholds::default()).await?;
+
+        // A failed or tcp only check keeps the last advertised network.
+        update_liveness(
+            &mut conn,
+            &liveness(Ok(PeerCheck::default())),
+            Thresholds::default(),
+        )
+        .await?;
+
+        let updated = sqlx::query!(
+            r#"
+            SELECT is_alive, advertised_network FROM peer WHERE id = 1
+            "#,
+        )
+        .fetch_one(&mut conn)
+        .await?;
+
+        assert!(updated.is_alive);
+        assert_eq!(updated.advertised_network.as_deref(), Some("cosmoshub-4"));
+
+        let checks = sqlx::query!(
+            r#"
+            SELECT address, success, error_kind, latency_ms FROM peer_check ORDER BY id
+            "#,
+        )
+        .fetch_all(&mut conn)
+        .await?;
+
+        assert_eq!(checks.len(), 4);
+        assert_eq!(checks[0].address, "abc123@public-seed-node.com:26656");
+        assert!(!checks[0].success);
+        assert_eq!(checks[0].error_kind.as_deref(), Some("refused"));
+        assert_eq!(checks[0].latency_ms, None);
+        assert!(checks[2].success);
+        assert_eq!(checks[2].error_kind, None);
+        assert_eq!(checks[2].latency_ms, Some(120));
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("recent_peers"))]
+    async fn test_update_liveness_batch(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+        let results = [
+            Liveness {
+                peer_id: 1,
+                check: Err(CheckError::Dns(std::io::ErrorKind::NotFound.into())),
+            },
+            Liveness {
+                peer_id: 2,
+                check: Ok(PeerCheck::default()),
+            },
+            // Deleted peers are skipped.
+            Liveness {
+                peer_id: 999,
+                check: Ok(PeerCheck::default()),
+            },
+        ];
+        assert_eq!(
+            update_liveness(&mut conn, &results, Thresholds::default()).await?,
+            2
+        );
+
+        let updated = sqlx::query!(
+            r#"
+            SELECT id, is_alive FROM peer WHERE id IN (1, 2) ORDER BY id
+            "#,
+        )
+        .fetch_all(&mut conn)
+        .await?;
+        assert!(!updated[0].is_alive);
+        assert!(updated[1].is_alive);
+
+        let checks = sqlx::query!(
+            r#"
+            SELECT address, success, error_kind FROM peer_check ORDER BY address
+            "#,
+        )
+        .fetch_all(&mut conn)
+        .await?;
+        assert_eq!(checks.len(), 2);
+        assert_eq!(checks[0].address, "abc123@public-seed-node.com:26656");
+        assert_eq!(checks[0].error_kind.as_deref(), Some("dns"));
+        assert!(checks[1].success);
+
+        assert_eq!(
+            update_liveness(&mut conn, &[], Thresholds::default()).await?,
+            0
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("recent_peers"))]
+    async fn test_update_liveness_thresholds(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+        let thresholds = Thresholds {
+            failures: 2,
+            successes: 2,
+        };
+        let failed = || {
+            [Liveness {
+                peer_id: 1,
+                check: Err(CheckError::Connect(std::io::ErrorKind::TimedOut.into())),
+            }]
+        };
+        let succeeded = || {
+            [Liveness {
+                peer_id: 1,
+                check: Ok(PeerCheck::default()),
+            }]
+        };
+
+        // Start from a peer that has passed a check before.
+        sqlx::query!("UPDATE peer SET success_streak = 1 WHERE id = 1")
+            .execute(&mut conn)
+            .await?;
+
+        // (is_alive, failure_streak) after each check
+        let mut states = vec![];
+        for results in [
+            failed(),
+            failed(),
+            failed(),
+            succeeded(),
+            failed(),
+            succeeded(),
+            succeeded(),
+        ] {
+            update_liveness(&mut conn, &results, thresholds).await?;
+            let found = recent_peers(&mut conn, "cosmoshub", "mainnet").await?;
+            let peer = found.iter().find(|p| p.id == 1).unwrap();
+            states.push((peer.is_alive, peer.failure_streak));
+        }
+        assert_eq!(
+            states,
+            vec![
+                (true, 1),
+                (false, 2),
+                (false, 3),
+                (false, 0),
+                (false, 1),
+                (false, 0),
+                (true, 0),
+            ]
+        );
+
+        // Every check is still recorded for uptime.
+        let checks = sqlx::query!("SELECT COUNT(*) as count FROM peer_check")
+            .fetch_one(&mut conn)
+            .await?;
+        assert_eq!(checks.count, Some(7));
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("recent_peers"))]
+    async fn test_update_liveness_first_check(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+        let thresholds = Thresholds {
+            failures: 3,
+            successes: 3,
+        };
+        // Neither peer has been checked; peer 1 starts alive and peer 2 dead.
+        let results = [
+            Liveness {
+                peer_id: 1,
+                check: Err(CheckError::Connect(std::io::ErrorKind::TimedOut.into())),
+            },
+            Liveness {
+                peer_id: 2,
+                check: Ok(PeerCheck::default()),
+            },
+        ];
+        update_liveness(&mut conn, &results, thresholds).await?;
+
+        let found = recent_peers(&mut conn, "cosmoshub", "mainnet").await?;
+        let peer = found.iter().find(|p| p.id == 1).unwrap();
+        assert!(!peer.is_alive);
+        assert_eq!(peer.failure_streak, 1);
+        let peer = found.iter().find(|p| p.id == 2).unwrap();
+        assert!(peer.is_alive);
+        assert_eq!(peer.failure_streak, 0);
+
+        Ok(())
+    }
+
+    // Inserts a check of a cosmoshub mainnet peer of the type the fixture lists it as.
+    async fn insert_check(
+        conn: &mut sqlx::PgConnection,
+        address: &str,
+        success: bool,
+        latency_ms: Option<i32>,
+        days_ago: i32,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO peer_check (network, chain_name, peer_type, address, success, latency_ms, checked_at)
+            SELECT 'mainnet', 'cosmoshub', peer.type, $1, $2, $3, NOW() - make_interval(days => $4)
+            FROM peer WHERE peer.address = $1 AND peer.chain_id_fk = 2
+            "#,
+            address,
+            success,
+            latency_ms,
+            days_ago,
+        )
+        .execute(conn)
+        .await?;
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("recent_peers"))]
+    async fn test_peer_stats(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+        let seed = "abc123@public-seed-node.com:26656";
+        let persistent = "efg987@public-persistent.com:26656";
+        insert_check(&mut conn, seed, true, Some(100), 0).await?;
+        insert_check(&mut conn, seed, true, Some(300), 1).await?;
+        insert_check(&mut conn, seed, false, None, 2).await?;
+        insert_check(&mut conn, seed, true, Some(200), 3).await?;
+        // Outside the history window.
+        insert_check(&mut conn, seed, false, None, CHECK_HISTORY_DAYS + 1).await?;
+        insert_check(&mut conn, persistent, true, Some(50), 0).await?;
+
+        let found = recent_peers(&mut conn, "cosmoshub", "mainnet").await?;
+        let stats = found.iter().find(|p| p.address == seed).unwrap();
+        assert_eq!(stats.uptime, Some(75.0));
+        assert_eq!(stats.median_latency_ms, Some(200.0));
+        assert!(stats.last_seen_alive.is_some());
+
+        let mut filter = PeerFilter {
+            chain_name: "cosmoshub".to_string(),
+            network: "mainnet".to_string(),
+            include_all: true,
+            min_uptime: Some(80.0),
+            sort: None,
+            include_discovered: false,
+        };
+        let found = filter_recent_peers(&mut conn, &filter).await?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].address, persistent);
+
+        filter.min_uptime = None;
+        filter.sort = Some(PeerSort::Latency);
+        let found = filter_recent_peers(&mut conn, &filter).await?;
+        assert_eq!(found[0].address, persistent);
+        assert_eq!(found[1].address, seed);
+
+        let found = recent_peers(&mut conn, "juno", "mainnet").await?;
+        assert_eq!(found[0].uptime, None);
+        assert_eq!(found[0].median_latency_ms, None);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("recent_peers"))]
+    async fn test_peer_stats_per_chain_and_type(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+        let seed = "abc123@public-seed-node.com:26656";
+        insert_check(&mut conn, seed, true, Some(100), 0).await?;
+        // The same address on another chain, or checked as another type, is another peer.
+        sqlx::query!(
+            r#"
+            INSERT INTO peer_check (network, chain_name, peer_type, address, success)
+            VALUES ('mainnet', 'juno', 'seed', $1, false),
+                   ('mainnet', 'cosmoshub', 'persistent', $1, false)
+            "#,
+            seed,
+        )
+        .execute(&mut conn)
+        .await?;
+
+        let found = recent_peers(&mut conn, "cosmoshub", "mainnet").await?;
+        let stats = found.iter().find(|p| p.address == seed).unwrap();
+        assert_eq!(stats.uptime, Some(100.0));
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("recent_peers"))]
+    async fn test_carry_discovered_peers(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+        // Discovered on the old cosmoshub row. The seed is listed in the new row's chain.json.
+        let discovered = "def456@203.0.113.7:26656";
+        let seed = "abc123@public-seed-node.com:26656";
+        assert!(insert_discovered_peer(&mut conn, 1, discovered, "cosmoshub-4").await?);
+        assert!(insert_discovered_peer(&mut conn, 1, seed, "cosmoshub-4").await?);
+        sqlx::query!(
+            "UPDATE peer SET is_alive = false, failure_streak = 3 WHERE chain_id_fk = 1 AND address = $1",
+            discovered
+        )
+        .execute(&mut conn)
+        .await?;
+
+        assert_eq!(carry_discovered_peers(&mut conn, 2).await?, 1);
+        assert_eq!(carry_discovered_peers(&mut conn, 2).await?, 0);
+
+        let found = recent_peers(&mut conn, "cosmoshub", "mainnet").await?;
+        assert_eq!(found.len(), 3);
+        let carried = found.iter().find(|p| p.address == discovered).unwrap();
+        assert_eq!(carried.source, "discovered");
+        assert!(!carried.is_alive);
+        assert_eq!(carried.failure_streak, 3);
+        assert_eq!(carried.advertised_network.as_deref(), Some("cosmoshub-4"));
+        let seed = found.iter().find(|p| p.address == seed).unwrap();
+        assert_eq!(seed.source, "registry");
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("recent_peers"))]
+    async fn test_prune_discovered_peers(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+        let dead = "dead@discovered.com:26656";
+        let flaky = "flaky@discovered.com:26656";
+        assert!(insert_discovered_peer(&mut conn, 2, dead, "cosmoshub-4").await?);
+        assert!(insert_discovered_peer(&mut conn, 2, flaky, "cosmoshub-4").await?);
+        sqlx::query!(
+            "UPDATE peer SET failure_streak = 5 WHERE address IN ($1, 'abc123@public-seed-node.com:26656')",
+            dead
+        )
+        .execute(&mut conn)
+        .await?;
+        sqlx::query!(
+            "UPDATE peer SET failure_streak = 4 WHERE address = $1",
+            flaky
+        )
+        .execute(&mut conn)
+        .await?;
+
+        assert_eq!(prune_discovered_peers(&mut conn, 5).await?, 1);
+        assert_eq!(prune_discovered_peers(&mut conn, 5).await?, 0);
+
+        let found = recent_peers(&mut conn, "cosmoshub", "mainnet").await?;
+        let addresses: Vec<_> = found.iter().map(|p| p.address.as_str()).collect();
+        assert!(!addresses.contains(&dead));
+        assert!(addresses.contains(&flaky));
+        // Registry peers are never pruned.
+        assert!(addresses.contains(&"abc123@public-seed-node.com:26656"));
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("recent_peers"))]
+    async fn test_prune_checks(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+        let seed = "abc123@public-seed-node.com:26656";
+        insert_check(&mut conn, seed, true, Some(100), 0).await?;
+        insert_check(&mut conn, seed, false, None, CHECK_HISTORY_DAYS + 1).await?;
+
+        assert_eq!(prune_checks(&mut conn).await?, 1);
+        assert_eq!(prune_checks(&mut conn).await?, 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("recent_peers"))]
+    async fn test_count_peers(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+        let counts = count_peers(&mut conn).await?;
+
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts[0].chain_name, "cosmoshub");
+        assert_eq!((counts[0].alive, counts[0].dead), (1, 1));
+        assert_eq!(counts[1].chain_name, "juno");
+        assert_eq!((counts[1].alive, counts[1].dead), (3, 0));
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("recent_peers"))]
+    async fn test_count_peers_latest_commit(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+        // A newer row that is not part of the latest commit, e.g. from a rolled back hydrate.
+        sqlx::query!(
+            r#"
+            INSERT INTO chain (id, name, network, commit, asset_data, chain_data, created_at)
+            VALUES (4, 'stargaze', 'mainnet', 'stray_commit', '{}', '{}', NOW() + interval '1 hour')
+            "#
+        )
+        .execute(&mut conn)
+        .await?;
+        sqlx::query!(
+            "INSERT INTO peer (chain_id_fk, type, address) VALUES (4, 'seed', 'abc@stray.com')"
+        )
+        .execute(&mut conn)
+        .await?;
+
+        let counts = count_peers(&mut conn).await?;
+        let names: Vec<&str> = counts.iter().map(|c| c.chain_name.as_str()).collect();
+        assert_eq!(names, vec!["cosmoshub", "juno"]);
+
+        // Peers are counted for the same chains as count_chains.
+        let chains = crate::db::chain::count_chains(&mut conn).await?;
+        assert_eq!(chains.len(), 1);
+        assert_eq!(chains[0].chains, 2);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("recent_peers"))]
+    async fn test_newest_updated_at(pool: PgPool) -> sqlx::Result<()> {
+        let mut conn = pool.acquire().await?;
+        // The trigger would set updated_at to now.
+        sqlx::query!("ALTER TABLE peer DISABLE TRIGGER peer_set_updated_at")
+            .execute(&mut conn)
+            .await?;
+        sqlx::query!("UPDATE peer SET updated_at = '2023-04-20 10:00:00+00'")
+            .execute(&mut conn)
+            .await?;
+        sqlx::query!(
+            "UPDATE peer SET updated_at = '2023-04-20 11:00:00+00' WHERE address = 'efg@peer.example.com'"
+        )
+        .execute(&mut conn)
+        .await?;
+
+        assert_eq!(
+            newest_updated_at(&mut conn).await?,
+            Some("2023-04-20T11:00:00Z".parse().unwrap())
+        );
 
         Ok(())
     }
@@ -0,0 +1,217 @@
+//! Resolves ibc/ denoms back to the asset they were transferred from.
+//!
+//! An ICS-20 denom is "ibc/" followed by the uppercase hex SHA-256 of its trace path, e.g.
+//! transfer/channel-0/uatom. The hash cannot be reversed, so every path the registry knows about is
+//! hashed until one matches.
+
+use crate::registry::Asset;
+use sha2::{Digest, Sha256};
+
+/// A transfer channel from the chain resolving denoms to a counterparty chain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferChannel {
+    pub counterparty: String,
+    /// Channel id on the chain resolving denoms.
+    pub channel_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DenomTrace {
+    /// ibc/ followed by the hash
+    pub denom: String,
+    pub path: String,
+    pub base_denom: String,
+    pub origin_chain_name: String,
+    /// Channel the asset arrived on.
+    pub channel_id: String,
+}
+
+/// Returns the ibc/ denom for a trace path.
+pub fn ibc_denom(path: &str) -> String {
+    format!("ibc/{}", hex::encode_upper(Sha256::digest(path.as_bytes())))
+}
+
+/// Finds the trace whose ibc/ denom has the given hash. Candidates are the chain's own assets that
+/// list an IBC trace, then every asset of a counterparty sent over one of the channels.
+/// counterparty_assets pairs each asset with the chain whose assetlist it is in.
+pub fn resolve(
+    hash: &str,
+    own_assets: &[Asset],
+    channels: &[TransferChannel],
+    counterparty_assets: &[(String, Asset)],
+) -> Option<DenomTrace> {
+    let denom = format!("ibc/{}", hash.to_uppercase());
+
+    let own = own_assets.iter().filter_map(ibc_trace);
+
+    let received = channels.iter().flat_map(|channel| {
+        counterparty_assets
+            .iter()
+            .filter(|(chain_name, _)| *chain_name == channel.counterparty)
+            .map(|(chain_name, asset)| {
+                // An asset the counterparty received itself keeps its trace path, so the denom here
+                // is a multi-hop trace back to where it came from.
+                let (base_path, base_denom, origin) = match ibc_trace(asset) {
+                    Some(trace) => (trace.path, trace.base_denom, trace.origin_chain_name),
+                    None => (asset.base.clone(), asset.base.clone(), chain_name.clone()),
+                };
+                DenomTrace {
+                    denom: String::new(),
+                    path: format!("transfer/{}/{}", channel.channel_id, base_path),
+                    base_denom,
+                    origin_chain_name: origin,
+                    channel_id: channel.channel_id.clone(),
+                }
+            })
+    });
+
+    own.chain(received)
+        .map(|trace| DenomTrace {
+            denom: ibc_denom(&trace.path),
+            ..trace
+        })
+        .find(|trace| trace.denom == denom)
+}
+
+/// Composes an asset's IBC hops, which are listed oldest first. The last hop arrived on this chain,
+/// so its path is the full trace path here. The first hop is where the asset entered IBC.
+fn ibc_trace(asset: &Asset) -> Option<DenomTrace> {
+    let hops: Vec<_> = asset
+        .traces
+        .iter()
+        .filter(|trace| matches!(trace.trace_type.as_str(), "ibc" | "ibc-cw20"))
+        .collect();
+    let origin = hops.first()?.counterparty.as_ref()?;
+    let chain = hops.last()?.chain.as_ref()?;
+    Some(DenomTrace {
+        denom: String::new(),
+        path: chain.path.clone()?,
+        base_denom: origin.base_denom.clone(),
+        origin_chain_name: origin.chain_name.clone(),
+        channel_id: chain.channel_id.clone()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(value: serde_json::Value) -> Asset {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_ibc_denom() {
+        assert_eq!(
+            ibc_denom("transfer/channel-0/uatom"),
+            "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2"
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        let channels = vec![TransferChannel {
+            counterparty: "cosmoshub".to_string(),
+            channel_id: "channel-0".to_string(),
+        }];
+        let cosmoshub = vec![
+            (
+                "cosmoshub".to_string(),
+                asset(serde_json::json!({"base": "uatom"})),
+            ),
+            // Osmosis received on cosmoshub's channel-141 and sent back over channel-0.
+            (
+                "cosmoshub".to_string(),
+                asset(serde_json::json!({
+                    "base": "ibc/14F9BC3E44B8A9C1BE1FB08980FAB87034C9905EF17CF2F5008FC085218811CC",
+                    "traces": [{
+                        "type": "ibc",
+                        "counterparty": {"chain_name": "osmosis", "base_denom": "uosmo"},
+                        "chain": {"channel_id": "channel-141", "path": "transfer/channel-141/uosmo"},
+                    }],
+                })),
+            ),
+        ];
+
+        let atom = resolve(
+            "27394fb092d2eccd56123c74f36e4c1f926001ceada9ca97ea622b25f41e5eb2",
+            &[],
+            &channels,
+            &cosmoshub,
+        )
+        .unwrap();
+        assert_eq!(
+            atom,
+            DenomTrace {
+                denom: "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2"
+                    .to_string(),
+                path: "transfer/channel-0/uatom".to_string(),
+                base_denom: "uatom".to_string(),
+                origin_chain_name: "cosmoshub".to_string(),
+                channel_id: "channel-0".to_string(),
+            }
+        );
+
+        let hash = &ibc_denom("transfer/channel-0/transfer/channel-141/uosmo")[4..];
+        let multi_hop = resolve(hash, &[], &channels, &cosmoshub).unwrap();
+        assert_eq!(multi_hop.base_denom, "uosmo");
+        assert_eq!(multi_hop.origin_chain_name, "osmosis");
+
+        // Own assets are resolved without an IBC path.
+        let own = vec![asset(serde_json::json!({
+            "base": "ibc/D189335C6E4A68B513C10AB227BF1C1D38C746766278BA3EEB4FB14124F1D858",
+            "traces": [{
+                "type": "ibc",
+                "counterparty": {"chain_name": "axelar", "base_denom": "uusdc"},
+                "chain": {"channel_id": "channel-208", "path": "transfer/channel-208/uusdc"},
+            }],
+        }))];
+        let hash = &ibc_denom("transfer/channel-208/uusdc")[4..];
+        let usdc = resolve(hash, &own, &[], &[]).unwrap();
+        assert_eq!(usdc.origin_chain_name, "axelar");
+        assert_eq!(usdc.channel_id, "channel-208");
+
+        assert_eq!(resolve("ABC", &own, &channels, &cosmoshub), None);
+    }
+
+    #[test]
+    fn test_resolve_two_hops() {
+        // ATOM that reached juno through osmosis. Only the last hop's path is a denom on juno.
+        let own = vec![asset(serde_json::json!({
+            "base": "ibc/C4CFF46F",
+            "traces": [
+                {
+                    "type": "ibc",
+                    "counterparty": {"chain_name": "cosmoshub", "base_denom": "uatom", "channel_id": "channel-141"},
+                    "chain": {"channel_id": "channel-0", "path": "transfer/channel-0/uatom"},
+                },
+                {
+                    "type": "ibc",
+                    "counterparty": {"chain_name": "osmosis", "base_denom": "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2", "channel_id": "channel-42"},
+                    "chain": {"channel_id": "channel-0", "path": "transfer/channel-0/transfer/channel-0/uatom"},
+                },
+            ],
+        }))];
+        let hash = &ibc_denom("transfer/channel-0/transfer/channel-0/uatom")[4..];
+        let atom = resolve(hash, &own, &[], &[]).unwrap();
+        assert_eq!(atom.path, "transfer/channel-0/transfer/channel-0/uatom");
+        assert_eq!(atom.base_denom, "uatom");
+        assert_eq!(atom.origin_chain_name, "cosmoshub");
+        // The first hop's path is osmosis's denom, not juno's.
+        let hash = &ibc_denom("transfer/channel-0/uatom")[4..];
+        assert_eq!(resolve(hash, &own, &[], &[]), None);
+
+        // Sent on from juno, the asset is three hops from cosmoshub.
+        let channels = vec![TransferChannel {
+            counterparty: "juno".to_string(),
+            channel_id: "channel-7".to_string(),
+        }];
+        let juno: Vec<_> = own.into_iter().map(|a| ("juno".to_string(), a)).collect();
+        let hash =
+            &ibc_denom("transfer/channel-7/transfer/channel-0/transfer/channel-0/uatom")[4..];
+        let atom = resolve(hash, &[], &channels, &juno).unwrap();
+        assert_eq!(atom.base_denom, "uatom");
+        assert_eq!(atom.origin_chain_name, "cosmoshub");
+        assert_eq!(atom.channel_id, "channel-7");
+    }
+}
@@ -0,0 +1,227 @@
+//! Encodes exported chains one at a time so a full registry export can be streamed.
+
+use crate::db::chain::ExportedChain;
+use crate::db::commit::RegistryCommit;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Deserialize;
+use serde_json::json;
+use std::io;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub enum Format {
+    /// {"meta": ..., "result": [chain, ...]}
+    #[default]
+    #[serde(rename = "json")]
+    Json,
+    /// One chain per line.
+    #[serde(rename = "ndjson")]
+    Ndjson,
+    /// {network}/{chain}/chain.json, assetlist.json, and peers.json, like the registry's layout.
+    #[serde(rename = "tar.gz")]
+    TarGz,
+}
+
+impl Format {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Ndjson => "application/x-ndjson",
+            Format::TarGz => "application/gzip",
+        }
+    }
+}
+
+pub struct Encoder {
+    format: Format,
+    commit: RegistryCommit,
+    count: usize,
+    tar: Option<tar::Builder<GzEncoder<Vec<u8>>>>,
+}
+
+impl Encoder {
+    pub fn new(format: Format, commit: RegistryCommit) -> Encoder {
+        let tar = match format {
+            Format::TarGz => Some(tar::Builder::new(GzEncoder::new(
+                Vec::new(),
+                Compression::default(),
+            ))),
+            _ => None,
+        };
+        Encoder {
+            format,
+            commit,
+            count: 0,
+            tar,
+        }
+    }
+
+    /// Returns the bytes to send for the chain. Compressed output may lag behind by a few chains.
+    pub fn chain(&mut self, chain: &ExportedChain) -> io::Result<Vec<u8>> {
+        self.count += 1;
+        let mut out = Vec::new();
+        match self.format {
+            Format::Json => {
+                if self.count == 1 {
+                    out.extend(self.json_prefix()?);
+                } else {
+                    out.push(b',');
+                }
+                serde_json::to_writer(&mut out, &chain_json(chain))?;
+            }
+            Format::Ndjson => {
+                serde_json::to_writer(&mut out, &chain_json(chain))?;
+                out.push(b'\n');
+            }
+            Format::TarGz => {
+                let mtime = self.commit.created_at.timestamp() as u64;
+                let tar = self.tar.as_mut().expect("tar builder");
+                let dir = format!("{}/{}", chain.network, chain.name);
+                for (file, value) in [
+                    ("chain.json", &chain.chain_data),
+                    ("assetlist.json", &chain.asset_data),
+                    ("peers.json", &chain.peers),
+                ] {
+                    let data = serde_json::to_vec_pretty(value)?;
+                    let mut header = tar::Header::new_gnu();
+                    header.set_size(data.len() as u64);
+                    header.set_mode(0o644);
+                    header.set_mtime(mtime);
+                    tar.append_data(&mut header, format!("{}/{}", dir, file), data.as_slice())?;
+                }
+                out = std::mem::take(tar.get_mut().get_mut());
+            }
+        }
+        Ok(out)
+    }
+
+    /// Returns the remaining bytes.
+    pub fn finish(self) -> io::Result<Vec<u8>> {
+        match self.format {
+            Format::Json => {
+                let mut out = Vec::new();
+                if self.count == 0 {
+                    out.extend(self.json_prefix()?);
+                }
+                out.extend(b"]}");
+                Ok(out)
+            }
+            Format::Ndjson => Ok(Vec::new()),
+            Format::TarGz => {
+                let tar = self.tar.expect("tar builder");
+                tar.into_inner()?.finish()
+            }
+        }
+    }
+
+    fn json_prefix(&self) -> io::Result<Vec<u8>> {
+        let meta = json!({
+            "commit": self.commit.commit,
+            "updated_at": self.commit.created_at,
+        });
+        let mut out = br#"{"meta":"#.to_vec();
+        serde_json::to_writer(&mut out, &meta)?;
+        out.extend(br#","result":["#);
+        Ok(out)
+    }
+}
+
+fn chain_json(chain: &ExportedChain) -> serde_json::Value {
+    json!({
+        "network": chain.network,
+        "chain_name": chain.name,
+        "chain_data": chain.chain_data,
+        "asset_data": chain.asset_data,
+        "peers": chain.peers,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    fn commit() -> RegistryCommit {
+        RegistryCommit {
+            commit: "abc123".to_string(),
+            created_at: "2023-04-20T12:00:00Z".parse().unwrap(),
+        }
+    }
+
+    fn chains() -> Vec<ExportedChain> {
+        ["cosmoshub", "osmosis"]
+            .iter()
+            .map(|name| ExportedChain {
+                network: "mainnet".to_string(),
+                name: name.to_string(),
+                chain_data: json!({ "chain_name": name }),
+                asset_data: json!({}),
+                peers: json!([]),
+            })
+            .collect()
+    }
+
+    fn encode(format: Format, chains: &[ExportedChain]) -> Vec<u8> {
+        let mut encoder = Encoder::new(format, commit());
+        let mut out = Vec::new();
+        for chain in chains {
+            out.extend(encoder.chain(chain).unwrap());
+        }
+        out.extend(encoder.finish().unwrap());
+        out
+    }
+
+    #[test]
+    fn test_encode_json() {
+        let out: serde_json::Value =
+            serde_json::from_slice(&encode(Format::Json, &chains())).unwrap();
+        assert_eq!(out["meta"]["commit"], "abc123");
+        assert_eq!(out["result"][1]["chain_name"], "osmosis");
+        assert_eq!(out["result"][1]["chain_data"]["chain_name"], "osmosis");
+
+        let out: serde_json::Value = serde_json::from_slice(&encode(Format::Json, &[])).unwrap();
+        assert_eq!(out["result"], json!([]));
+    }
+
+    #[test]
+    fn test_encode_ndjson() {
+        let out = String::from_utf8(encode(Format::Ndjson, &chains())).unwrap();
+        let lines: Vec<serde_json::Value> = out
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["chain_name"], "cosmoshub");
+    }
+
+    #[test]
+    fn test_encode_tar_gz() {
+        let out = encode(Format::TarGz, &chains());
+        let mut archive = tar::Archive::new(GzDecoder::new(out.as_slice()));
+        let mut files = vec![];
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let path = entry.path().unwrap().display().to_string();
+            if path == "mainnet/osmosis/chain.json" {
+                let mut contents = String::new();
+                entry.read_to_string(&mut contents).unwrap();
+                let chain: serde_json::Value = serde_json::from_str(&contents).unwrap();
+                assert_eq!(chain["chain_name"], "osmosis");
+                assert_eq!(entry.header().mtime().unwrap(), 1681992000);
+            }
+            files.push(path);
+        }
+        assert_eq!(
+            files,
+            vec![
+                "mainnet/cosmoshub/chain.json",
+                "mainnet/cosmoshub/assetlist.json",
+                "mainnet/cosmoshub/peers.json",
+                "mainnet/osmosis/chain.json",
+                "mainnet/osmosis/assetlist.json",
+                "mainnet/osmosis/peers.json",
+            ]
+        );
+    }
+}
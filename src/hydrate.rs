@@ -1,10 +1,65 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 pub struct ChainRegRepo {
+    // Top level of the registry, where the JSON schemas live.
+    pub root: PathBuf,
     pub commit: String,
     pub mainnets: Vec<PathBuf>,
     pub testnets: Vec<PathBuf>,
+    pub mainnet_ibc: Vec<PathBuf>,
+    pub testnet_ibc: Vec<PathBuf>,
+}
+
+/// Where to read the Chain Registry from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Git {
+        remote: String,
+        git_ref: String,
+    },
+    /// An existing checkout.
+    Dir(PathBuf),
+    /// A gzipped tarball such as a GitHub source archive.
+    Tar(PathBuf),
+}
+
+impl FromStr for Source {
+    type Err = String;
+
+    // Parses dir:/path or tar:/path.tar.gz. Git sources come from other flags.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("dir", path)) if !path.is_empty() => Ok(Source::Dir(path.into())),
+            Some(("tar", path)) if !path.is_empty() => Ok(Source::Tar(path.into())),
+            _ => Err(format!(
+                "invalid source {:?}, expected dir:/path or tar:/path.tar.gz",
+                s
+            )),
+        }
+    }
+}
+
+/// Builds a ChainRegRepo from the source. Git sources are cloned and tarballs are unpacked into
+/// work_dir. If commit is None, it is read from the git metadata or, for tarballs, the archive's
+/// commit comment.
+pub fn load(
+    source: Source,
+    work_dir: &Path,
+    commit: Option<String>,
+) -> anyhow::Result<ChainRegRepo> {
+    match source {
+        Source::Git { remote, git_ref } => {
+            let mut repo = shallow_clone(remote, git_ref, &work_dir.to_path_buf())?;
+            if let Some(commit) = commit {
+                repo.commit = commit;
+            }
+            Ok(repo)
+        }
+        Source::Dir(dir) => open_dir(&dir, commit),
+        Source::Tar(archive) => unpack_tar(&archive, work_dir, commit),
+    }
 }
 
 pub fn shallow_clone(
@@ -30,9 +85,6 @@ pub fn shallow_clone(
         );
     }
 
-    let mainnets = collect_chains(clone_dir.clone())?;
-    let testnets = collect_chains(clone_dir.join("testnets"))?;
-
     // Get commit hash
     let mut cmd = std::process::Command::new("git");
     let output = cmd
@@ -51,13 +103,136 @@ pub fn shallow_clone(
     let commit = std::str::from_utf8(output.stdout.as_ref())?;
     let commit = commit.trim().to_string();
 
+    collect_repo(clone_dir, commit)
+}
+
+/// Asks the remote for the commit git_ref points to without cloning.
+pub fn remote_commit(remote: &str, git_ref: &str) -> anyhow::Result<String> {
+    let output = std::process::Command::new("git")
+        .arg("ls-remote")
+        .arg(remote)
+        .arg(git_ref)
+        .output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git ls-remote failed with status: {:?} stderr: {:?}",
+            output.status,
+            std::str::from_utf8(output.stderr.as_ref()).unwrap_or("cannot read stderr"),
+        );
+    }
+    let output = std::str::from_utf8(output.stdout.as_ref())?;
+    match parse_ls_remote(output) {
+        Some(commit) => Ok(commit),
+        None => anyhow::bail!("{} not found in {}", git_ref, remote),
+    }
+}
+
+// Annotated tags are listed twice. The peeled line, ending in ^{}, has the commit the tag points to.
+fn parse_ls_remote(output: &str) -> Option<String> {
+    let refs: Vec<(&str, &str)> = output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .collect();
+    refs.iter()
+        .find(|(_, name)| name.ends_with("^{}"))
+        .or_else(|| refs.first())
+        .map(|(commit, _)| commit.to_string())
+}
+
+/// Reads an existing checkout without invoking git.
+pub fn open_dir(dir: &Path, commit: Option<String>) -> anyhow::Result<ChainRegRepo> {
+    if !dir.is_dir() {
+        anyhow::bail!("{} is not a directory", dir.display());
+    }
+    let commit = match commit {
+        Some(commit) => commit,
+        None => read_git_head(dir)?,
+    };
+    collect_repo(dir, commit)
+}
+
+/// Unpacks a .tar.gz archive into dest. Archives with a single top level directory, like GitHub's
+/// source archives, use that directory as the registry root.
+pub fn unpack_tar(
+    archive: &Path,
+    dest: &Path,
+    commit: Option<String>,
+) -> anyhow::Result<ChainRegRepo> {
+    let file = fs::File::open(archive)?;
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+    fs::create_dir_all(dest)?;
+
+    let mut archive_commit = None;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        // git archive stores the commit as a comment in the pax global header.
+        if entry.header().entry_type() == tar::EntryType::XGlobalHeader {
+            if let Some(extensions) = entry.pax_extensions()? {
+                for ext in extensions {
+                    let ext = ext?;
+                    if ext.key() == Ok("comment") {
+                        archive_commit = ext.value().ok().map(|v| v.trim().to_string());
+                    }
+                }
+            }
+            continue;
+        }
+        entry.unpack_in(dest)?;
+    }
+
+    let entries = fs::read_dir(dest)?.collect::<Result<Vec<_>, _>>()?;
+    let root = match entries.as_slice() {
+        [only] if only.path().is_dir() => only.path(),
+        _ => dest.to_path_buf(),
+    };
+
+    let commit = match commit.or(archive_commit) {
+        Some(commit) => commit,
+        None => read_git_head(&root).map_err(|err| {
+            anyhow::anyhow!("cannot determine commit for archive, supply one: {}", err)
+        })?,
+    };
+    collect_repo(&root, commit)
+}
+
+fn collect_repo(root: &Path, commit: String) -> anyhow::Result<ChainRegRepo> {
     Ok(ChainRegRepo {
+        root: root.to_path_buf(),
         commit,
-        mainnets,
-        testnets,
+        mainnets: collect_chains(root.to_path_buf())?,
+        testnets: collect_chains(root.join("testnets"))?,
+        mainnet_ibc: collect_ibc_paths(root.join("_IBC"))?,
+        testnet_ibc: collect_ibc_paths(root.join("testnets").join("_IBC"))?,
     })
 }
 
+// Resolves .git/HEAD to a commit hash, following a branch ref to a loose or packed ref.
+fn read_git_head(root: &Path) -> anyhow::Result<String> {
+    let git_dir = root.join(".git");
+    let head = fs::read_to_string(git_dir.join("HEAD"))
+        .map_err(|err| anyhow::anyhow!("failed to read .git/HEAD: {}", err))?;
+    let head = head.trim();
+
+    let ref_name = match head.strip_prefix("ref:") {
+        Some(ref_name) => ref_name.trim(),
+        None => return Ok(head.to_string()),
+    };
+
+    if let Ok(commit) = fs::read_to_string(git_dir.join(ref_name)) {
+        return Ok(commit.trim().to_string());
+    }
+
+    let packed = fs::read_to_string(git_dir.join("packed-refs")).unwrap_or_default();
+    packed
+        .lines()
+        .filter(|line| !line.starts_with('#') && !line.starts_with('^'))
+        .find_map(|line| match line.split_once(' ') {
+            Some((commit, name)) if name == ref_name => Some(commit.to_string()),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow::anyhow!("cannot resolve {} in .git", ref_name))
+}
+
 fn collect_chains(dir: PathBuf) -> anyhow::Result<Vec<PathBuf>> {
     let found = fs::read_dir(dir)?
         .filter_map(|f| {
@@ -65,7 +240,7 @@ fn collect_chains(dir: PathBuf) -> anyhow::Result<Vec<PathBuf>> {
             if !f.is_dir() {
                 return None;
             }
-            return Some(f);
+            Some(f)
         })
         .filter(|f| {
             let fname = f.file_name().unwrap().to_str().unwrap();
@@ -75,11 +250,187 @@ fn collect_chains(dir: PathBuf) -> anyhow::Result<Vec<PathBuf>> {
     Ok(found)
 }
 
+// IBC data lives in json files such as _IBC/cosmoshub-osmosis.json. Not every network has them.
+fn collect_ibc_paths(dir: PathBuf) -> anyhow::Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let found = fs::read_dir(dir)?
+        .filter_map(|f| {
+            let f = f.unwrap().path();
+            if !f.is_file() || f.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                return None;
+            }
+            Some(f)
+        })
+        .collect();
+    Ok(found)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tempfile::TempDir;
 
+    const COMMIT: &str = "3c1d8a2f0e4b5a6978c0d1e2f3a4b5c6d7e8f901";
+
+    fn fixture_registry() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("src/hydrate/fixtures/registry")
+    }
+
+    fn names(paths: &[PathBuf]) -> Vec<String> {
+        let mut names: Vec<String> = paths
+            .iter()
+            .map(|p| p.file_name().unwrap().to_str().unwrap().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn assert_fixture_repo(repo: &ChainRegRepo) {
+        assert_eq!(repo.commit, COMMIT);
+        assert_eq!(names(&repo.mainnets), vec!["cosmoshub", "osmosis"]);
+        assert_eq!(names(&repo.testnets), vec!["cosmoshubtestnet"]);
+        assert_eq!(names(&repo.mainnet_ibc), vec!["cosmoshub-osmosis.json"]);
+        assert!(repo.testnet_ibc.is_empty());
+        assert!(repo.mainnets.iter().all(|p| p.join("chain.json").exists()));
+    }
+
+    #[test]
+    fn test_source_from_str() {
+        assert_eq!(
+            "dir:/tmp/registry".parse::<Source>(),
+            Ok(Source::Dir("/tmp/registry".into()))
+        );
+        assert_eq!(
+            "tar:registry.tar.gz".parse::<Source>(),
+            Ok(Source::Tar("registry.tar.gz".into()))
+        );
+        assert!("dir:".parse::<Source>().is_err());
+        assert!("/tmp/registry".parse::<Source>().is_err());
+        assert!("zip:registry.zip".parse::<Source>().is_err());
+    }
+
+    #[test]
+    fn test_open_dir() {
+        let repo = open_dir(&fixture_registry(), Some(COMMIT.to_string())).unwrap();
+        assert_fixture_repo(&repo);
+
+        // The fixture has no .git dir.
+        assert!(open_dir(&fixture_registry(), None).is_err());
+        assert!(open_dir(Path::new("/does/not/exist"), Some(COMMIT.to_string())).is_err());
+    }
+
+    #[test]
+    fn test_read_git_head() {
+        let temp_dir = TempDir::new().unwrap();
+        let git_dir = temp_dir.path().join(".git");
+        fs::create_dir_all(git_dir.join("refs/heads")).unwrap();
+
+        // Detached
+        fs::write(git_dir.join("HEAD"), format!("{}\n", COMMIT)).unwrap();
+        assert_eq!(read_git_head(temp_dir.path()).unwrap(), COMMIT);
+
+        // Packed ref
+        fs::write(git_dir.join("HEAD"), "ref: refs/heads/master\n").unwrap();
+        assert!(read_git_head(temp_dir.path()).is_err());
+        fs::write(
+            git_dir.join("packed-refs"),
+            format!(
+                "# pack-refs with: peeled fully-peeled sorted\nabc refs/heads/other\n{} refs/heads/master\n",
+                COMMIT
+            ),
+        )
+        .unwrap();
+        assert_eq!(read_git_head(temp_dir.path()).unwrap(), COMMIT);
+
+        // Loose ref wins over packed
+        fs::write(git_dir.join("refs/heads/master"), "def456\n").unwrap();
+        assert_eq!(read_git_head(temp_dir.path()).unwrap(), "def456");
+    }
+
+    #[test]
+    fn test_parse_ls_remote() {
+        assert_eq!(parse_ls_remote(""), None);
+        assert_eq!(
+            parse_ls_remote(&format!("{}\trefs/heads/master\n", COMMIT)).as_deref(),
+            Some(COMMIT)
+        );
+        assert_eq!(
+            parse_ls_remote(&format!(
+                "abc123\trefs/tags/v1.0.0\n{}\trefs/tags/v1.0.0^{{}}\n",
+                COMMIT
+            ))
+            .as_deref(),
+            Some(COMMIT)
+        );
+    }
+
+    fn write_archive(dest: &Path, comment: Option<&str>) {
+        let file = fs::File::create(dest).unwrap();
+        let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+
+        if let Some(comment) = comment {
+            // Mimic git archive, which writes the commit into a pax global header.
+            // Each record starts with its own length, including the length digits.
+            let body = format!(" comment={}\n", comment);
+            let digits = (1..)
+                .find(|d| (body.len() + d).to_string().len() == *d)
+                .unwrap();
+            let record = format!("{}{}", body.len() + digits, body);
+            let mut header = tar::Header::new_ustar();
+            header.set_entry_type(tar::EntryType::XGlobalHeader);
+            header.set_path("pax_global_header").unwrap();
+            header.set_size(record.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append(&header, record.as_bytes()).unwrap();
+        }
+
+        builder
+            .append_dir_all("chain-registry-master", fixture_registry())
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn test_unpack_tar() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive = temp_dir.path().join("registry.tar.gz");
+
+        write_archive(&archive, Some(COMMIT));
+        let dest = temp_dir.path().join("with_comment");
+        let repo = unpack_tar(&archive, &dest, None).unwrap();
+        assert_fixture_repo(&repo);
+        assert!(repo.mainnets[0].starts_with(dest.join("chain-registry-master")));
+
+        write_archive(&archive, None);
+        let dest = temp_dir.path().join("without_comment");
+        assert!(unpack_tar(&archive, &dest, None).is_err());
+
+        let dest = temp_dir.path().join("with_commit");
+        let repo = unpack_tar(&archive, &dest, Some(COMMIT.to_string())).unwrap();
+        assert_fixture_repo(&repo);
+    }
+
+    #[test]
+    fn test_collect_ibc_paths() {
+        let temp_dir = TempDir::new().unwrap();
+        let ibc_dir = temp_dir.path().join("_IBC");
+
+        let found = collect_ibc_paths(ibc_dir.clone()).unwrap();
+        assert!(found.is_empty());
+
+        fs::create_dir(&ibc_dir).unwrap();
+        fs::write(ibc_dir.join("cosmoshub-osmosis.json"), "{}").unwrap();
+        fs::write(ibc_dir.join("README.md"), "ignored").unwrap();
+        fs::create_dir(ibc_dir.join("nested")).unwrap();
+
+        let found = collect_ibc_paths(ibc_dir.clone()).unwrap();
+        assert_eq!(found, vec![ibc_dir.join("cosmoshub-osmosis.json")]);
+    }
+
     #[test]
     #[ignore] // Longer integration test
     fn test_shallow_clone() {
@@ -137,5 +488,11 @@ mod tests {
             testnets
         );
         assert!(!testnets.contains(&".".to_string()));
+
+        assert!(!repo.mainnet_ibc.is_empty());
+        assert!(repo
+            .mainnet_ibc
+            .iter()
+            .any(|p| p.ends_with("_IBC/cosmoshub-osmosis.json")));
     }
 }
@@ -1,55 +1,601 @@
-use std::net::{TcpStream, ToSocketAddrs};
-use std::time::Duration;
-
-pub fn tcp_check_liveness(addr: &str, timeout: Duration) -> anyhow::Result<()> {
-    let parts = addr.split('@').collect::<Vec<&str>>();
-    let addr = match parts.last() {
-        Some(a) => a,
-        None => addr,
-    };
+use crate::db::endpoint::EndpointKind;
+use crate::db::peer::Thresholds;
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+mod handshake;
+
+// Wait before a retry, multiplied by the number of retries so far.
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// How thoroughly to check peers.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum CheckMode {
+    /// Only check that the peer accepts TCP connections.
+    Tcp,
+    /// Perform the p2p SecretConnection handshake and verify the peer's node id.
+    Handshake,
+}
+
+/// A successful peer check.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PeerCheck {
+    /// Time to establish the TCP connection.
+    pub latency: Duration,
+    /// Network (chain id) the peer advertised. Only set by the handshake check.
+    pub advertised_network: Option<String>,
+}
+
+/// Why a peer check failed.
+#[derive(Debug)]
+pub enum CheckError {
+    Dns(std::io::Error),
+    Connect(std::io::Error),
+    Handshake(anyhow::Error),
+    NodeIdMismatch { expected: String, actual: String },
+}
+
+impl CheckError {
+    /// A short, stable name for the error suitable for storing and grouping.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CheckError::Dns(_) => "dns",
+            CheckError::Connect(err) => match err.kind() {
+                std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock => "timeout",
+                std::io::ErrorKind::ConnectionRefused => "refused",
+                _ => "connect",
+            },
+            CheckError::Handshake(_) => "handshake",
+            CheckError::NodeIdMismatch { .. } => "node_id_mismatch",
+        }
+    }
+}
+
+impl std::fmt::Display for CheckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckError::Dns(err) => write!(f, "dns lookup failed: {}", err),
+            CheckError::Connect(err) => write!(f, "connect failed: {}", err),
+            CheckError::Handshake(err) => write!(f, "handshake failed: {}", err),
+            CheckError::NodeIdMismatch { expected, actual } => write!(
+                f,
+                "expected node id {} but peer authenticated as {}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CheckError {}
+
+/// How to run liveness checks.
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub check_mode: CheckMode,
+    /// Max checks in flight.
+    pub concurrency: usize,
+    /// For each attempt.
+    pub timeout: Duration,
+    /// Attempts after the first failed one.
+    pub retries: u32,
+    /// Max checks in flight against one host. Providers often run many peers and endpoints on the
+    /// same host.
+    pub per_host_limit: usize,
+    /// Min time between the starts of two checks against one host.
+    pub per_host_interval: Duration,
+    /// Agreeing checks in a row needed to mark a peer dead or alive.
+    pub thresholds: Thresholds,
+}
+
+/// Checks peers and endpoints according to Options. Share one checker across a run so the per
+/// host limits apply to all of its checks.
+pub struct Checker {
+    opts: Options,
+    client: reqwest::Client,
+    hosts: HostLimiter,
+}
+
+impl Checker {
+    pub fn new(opts: Options) -> anyhow::Result<Checker> {
+        let client = reqwest::Client::builder().timeout(opts.timeout).build()?;
+        let hosts = HostLimiter::new(opts.per_host_limit, opts.per_host_interval);
+        Ok(Checker {
+            opts,
+            client,
+            hosts,
+        })
+    }
+
+    /// Checks a peer with the check mode. A node id mismatch is not retried because it will not
+    /// change.
+    pub async fn check_peer(&self, addr: &str) -> Result<PeerCheck, CheckError> {
+        let host = peer_host(addr);
+        let retryable = |err: &CheckError| !matches!(err, CheckError::NodeIdMismatch { .. });
+        retry(self.opts.retries, retryable, || async {
+            let _permit = self.hosts.acquire(host).await;
+            match self.opts.check_mode {
+                CheckMode::Tcp => tcp_check_liveness(addr, self.opts.timeout).await,
+                CheckMode::Handshake => handshake_check_liveness(addr, self.opts.timeout).await,
+            }
+        })
+        .await
+    }
+
+    pub async fn check_endpoint(&self, kind: EndpointKind, address: &str) -> anyhow::Result<()> {
+        let host = endpoint_host(address);
+        retry(
+            self.opts.retries,
+            |_| true,
+            || async {
+                let _permit = self.hosts.acquire(&host).await;
+                endpoint_check_liveness(&self.client, kind, address, self.opts.timeout).await
+            },
+        )
+        .await
+    }
+}
+
+async fn retry<T, E, F, Fut>(
+    retries: u32,
+    retryable: impl Fn(&E) -> bool,
+    mut attempt: F,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut tries = 0;
+    loop {
+        match attempt().await {
+            Err(err) if tries < retries && retryable(&err) => {
+                tries += 1;
+                tokio::time::sleep(RETRY_BACKOFF * tries).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Limits checks in flight per host and spaces out their starts, so a host with many peers does
+/// not get a burst of connections as fast as checks complete.
+struct HostLimiter {
+    limit: usize,
+    interval: Duration,
+    hosts: Mutex<HashMap<String, HostState>>,
+}
+
+struct HostState {
+    in_flight: Arc<Semaphore>,
+    // When the next check against the host may start.
+    next_start: tokio::time::Instant,
+}
+
+impl HostLimiter {
+    fn new(limit: usize, interval: Duration) -> HostLimiter {
+        HostLimiter {
+            limit: limit.max(1),
+            interval,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn acquire(&self, host: &str) -> OwnedSemaphorePermit {
+        let host = host.to_ascii_lowercase();
+        let sem = self
+            .hosts
+            .lock()
+            .unwrap()
+            .entry(host.clone())
+            .or_insert_with(|| HostState {
+                in_flight: Arc::new(Semaphore::new(self.limit)),
+                next_start: tokio::time::Instant::now(),
+            })
+            .in_flight
+            .clone();
+        let permit = sem
+            .acquire_owned()
+            .await
+            .expect("host semaphores are never closed");
+
+        // Reserve the next start slot, then wait for it.
+        let start = {
+            let mut hosts = self.hosts.lock().unwrap();
+            let state = hosts.get_mut(&host).expect("hosts are never removed");
+            let start = state.next_start.max(tokio::time::Instant::now());
+            state.next_start = start + self.interval;
+            start
+        };
+        tokio::time::sleep_until(start).await;
+        permit
+    }
+}
+
+// The host of id@host:port.
+fn peer_host(addr: &str) -> &str {
+    let (_, addr) = split_node_id(addr);
+    let host = addr.rsplit_once(':').map_or(addr, |(host, _)| host);
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+fn endpoint_host(address: &str) -> String {
+    if let Some(host) = reqwest::Url::parse(address)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+    {
+        return host;
+    }
+    // gRPC endpoints without a scheme.
+    peer_host(&grpc_socket_addr(address)).to_string()
+}
+
+pub async fn tcp_check_liveness(addr: &str, timeout: Duration) -> Result<PeerCheck, CheckError> {
+    let (_, addr) = split_node_id(addr);
+    // Dropping the stream closes the connection.
+    let (_stream, latency) = connect(addr, timeout).await?;
+    Ok(PeerCheck {
+        latency,
+        advertised_network: None,
+    })
+}
+
+/// Connects to a peer and performs the p2p handshake. Fails if the peer authenticates with a node id
+/// other than the one in the address, e.g. the "abc123" in abc123@seed.example.com:26656.
+pub async fn handshake_check_liveness(
+    addr: &str,
+    timeout: Duration,
+) -> Result<PeerCheck, CheckError> {
+    let (node_id, addr) = split_node_id(addr);
+    let (stream, latency) = connect(addr, timeout).await?;
+
+    // The handshake is synchronous, so it runs on a blocking thread with socket timeouts.
+    let stream = stream
+        .into_std()
+        .and_then(|stream| {
+            stream.set_nonblocking(false)?;
+            stream.set_read_timeout(Some(timeout))?;
+            stream.set_write_timeout(Some(timeout))?;
+            Ok(stream)
+        })
+        .map_err(CheckError::Connect)?;
+    let info = tokio::task::spawn_blocking(move || handshake::handshake(stream))
+        .await
+        .map_err(|err| CheckError::Handshake(err.into()))?
+        .map_err(CheckError::Handshake)?;
+
+    if let Some(node_id) = node_id {
+        if !node_id.eq_ignore_ascii_case(&info.node_id) {
+            return Err(CheckError::NodeIdMismatch {
+                expected: node_id.to_string(),
+                actual: info.node_id,
+            });
+        }
+    }
+    Ok(PeerCheck {
+        latency,
+        advertised_network: Some(info.network),
+    })
+}
 
-    let socket_addrs = addr.to_socket_addrs()?;
+fn split_node_id(addr: &str) -> (Option<&str>, &str) {
+    match addr.rsplit_once('@') {
+        Some((node_id, addr)) => (Some(node_id), addr),
+        None => (None, addr),
+    }
+}
+
+async fn connect(addr: &str, timeout: Duration) -> Result<(TcpStream, Duration), CheckError> {
+    let socket_addrs: Vec<SocketAddr> =
+        match tokio::time::timeout(timeout, tokio::net::lookup_host(addr)).await {
+            Ok(found) => found.map_err(CheckError::Dns)?.collect(),
+            Err(_) => return Err(CheckError::Dns(std::io::ErrorKind::TimedOut.into())),
+        };
     let mut last_error = None;
     for socket_addr in socket_addrs {
-        match TcpStream::connect_timeout(&socket_addr, timeout) {
-            Ok(stream) => {
-                stream.shutdown(std::net::Shutdown::Both)?;
-                return Ok(());
+        let start = Instant::now();
+        match tokio::time::timeout(timeout, TcpStream::connect(socket_addr)).await {
+            Ok(Ok(stream)) => {
+                return Ok((stream, start.elapsed()));
             }
-            Err(e) => {
+            Ok(Err(e)) => {
                 last_error = Some(e);
             }
+            Err(_) => {
+                last_error = Some(std::io::ErrorKind::TimedOut.into());
+            }
         }
     }
 
-    Err(last_error.unwrap_or(std::io::Error::new(
-        std::io::ErrorKind::Other,
-        "No good addresses",
-    )))?
+    Err(CheckError::Connect(
+        last_error.unwrap_or(std::io::Error::other("No good addresses")),
+    ))
+}
+
+/// Checks an endpoint using a request appropriate for its kind. RPC and REST endpoints must answer
+/// with node info. gRPC endpoints only get a TCP check because they require HTTP/2.
+pub async fn endpoint_check_liveness(
+    client: &reqwest::Client,
+    kind: EndpointKind,
+    address: &str,
+    timeout: Duration,
+) -> anyhow::Result<()> {
+    let base = address.trim_end_matches('/');
+    match kind {
+        EndpointKind::Rpc => {
+            http_check_liveness(client, &format!("{}/status", base), "/result/node_info").await
+        }
+        EndpointKind::Rest => {
+            http_check_liveness(
+                client,
+                &format!("{}/cosmos/base/tendermint/v1beta1/node_info", base),
+                "/default_node_info",
+            )
+            .await
+        }
+        EndpointKind::Grpc => {
+            tcp_check_liveness(&grpc_socket_addr(base), timeout).await?;
+            Ok(())
+        }
+    }
+}
+
+async fn http_check_liveness(
+    client: &reqwest::Client,
+    url: &str,
+    json_pointer: &str,
+) -> anyhow::Result<()> {
+    let resp = client.get(url).send().await?.error_for_status()?;
+    let body: serde_json::Value = resp.json().await?;
+    if body.pointer(json_pointer).is_none() {
+        anyhow::bail!("response from {} is missing {}", url, json_pointer);
+    }
+    Ok(())
+}
+
+// The registry lists gRPC endpoints with and without a scheme. Without a port, assume TLS.
+fn grpc_socket_addr(address: &str) -> String {
+    let (addr, default_port) = match address.split_once("://") {
+        Some(("http", addr)) => (addr, 80),
+        Some((_, addr)) => (addr, 443),
+        None => (address, 443),
+    };
+    let addr = addr.trim_end_matches('/');
+    match addr.rsplit_once(':') {
+        Some((_, port)) if port.parse::<u16>().is_ok() => addr.to_string(),
+        _ => format!("{}:{}", addr, default_port),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use axum::{routing::get, Json, Router};
+    use serde_json::json;
     use tokio_test::*;
 
+    async fn serve_stub(router: Router) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(router.into_make_service())
+                .await
+                .unwrap();
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_endpoint_check_liveness() {
+        let timeout = Duration::from_secs(3);
+        let client = reqwest::Client::builder().timeout(timeout).build().unwrap();
+
+        let router = Router::new()
+            .route(
+                "/status",
+                get(|| async {
+                    Json(json!({"result": {"node_info": {"network": "cosmoshub-4"}}}))
+                }),
+            )
+            .route(
+                "/cosmos/base/tendermint/v1beta1/node_info",
+                get(|| async { Json(json!({"default_node_info": {}})) }),
+            )
+            .route("/bad/status", get(|| async { Json(json!({"result": {}})) }));
+        let base = serve_stub(router).await;
+
+        assert_ok!(endpoint_check_liveness(&client, EndpointKind::Rpc, &base, timeout).await);
+        let with_slash = format!("{}/", base);
+        assert_ok!(endpoint_check_liveness(&client, EndpointKind::Rpc, &with_slash, timeout).await);
+        assert_ok!(endpoint_check_liveness(&client, EndpointKind::Rest, &base, timeout).await);
+        assert_ok!(endpoint_check_liveness(&client, EndpointKind::Grpc, &base, timeout).await);
+
+        let bad = format!("{}/bad", base);
+        assert_err!(endpoint_check_liveness(&client, EndpointKind::Rpc, &bad, timeout).await);
+        assert_err!(endpoint_check_liveness(&client, EndpointKind::Rest, &bad, timeout).await);
+        assert_err!(
+            endpoint_check_liveness(&client, EndpointKind::Rpc, "http://127.0.0.1:433", timeout)
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handshake_check_liveness() {
+        let timeout = Duration::from_secs(3);
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let remote_key = ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng);
+        let node_id = handshake::node_id(&remote_key.verifying_key());
+
+        let server = std::thread::spawn(move || {
+            for _ in 0..3 {
+                let (stream, _) = listener.accept().unwrap();
+                let _ = handshake::handshake_as(stream, &remote_key, "cosmoshub-4");
+            }
+        });
+
+        let addr = format!("{}@127.0.0.1:{}", node_id, port);
+        let check = handshake_check_liveness(&addr, timeout).await.unwrap();
+        assert_eq!(check.advertised_network.as_deref(), Some("cosmoshub-4"));
+
+        let addr = format!("{}@127.0.0.1:{}", node_id.to_uppercase(), port);
+        assert_ok!(handshake_check_liveness(&addr, timeout).await);
+
+        let addr = format!("abc123@127.0.0.1:{}", port);
+        let err = handshake_check_liveness(&addr, timeout).await.unwrap_err();
+        assert_eq!(err.kind(), "node_id_mismatch");
+
+        server.join().unwrap();
+
+        let err = handshake_check_liveness("abc123@127.0.0.1:433", timeout)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), "refused");
+    }
+
     #[test]
-    fn test_tcp_check_liveness() {
+    fn test_grpc_socket_addr() {
+        assert_eq!(
+            grpc_socket_addr("grpc.example.com:9090"),
+            "grpc.example.com:9090"
+        );
+        assert_eq!(grpc_socket_addr("grpc.example.com"), "grpc.example.com:443");
+        assert_eq!(
+            grpc_socket_addr("https://grpc.example.com/"),
+            "grpc.example.com:443"
+        );
+        assert_eq!(
+            grpc_socket_addr("http://grpc.example.com"),
+            "grpc.example.com:80"
+        );
+        assert_eq!(grpc_socket_addr("http://127.0.0.1:9090"), "127.0.0.1:9090");
+    }
+
+    #[tokio::test]
+    async fn test_tcp_check_liveness() {
         let timeout = Duration::from_secs(3);
 
-        assert_err!(tcp_check_liveness("127.0.0.1:433", timeout));
+        assert_err!(tcp_check_liveness("127.0.0.1:433", timeout).await);
 
         let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
         let local_addr = listener.local_addr().unwrap();
         let addr = format!("127.0.0.1:{}", local_addr.port());
 
-        assert_ok!(tcp_check_liveness(addr.as_ref(), timeout));
+        assert_ok!(tcp_check_liveness(addr.as_ref(), timeout).await);
 
         let addr = format!("abcignored@127.0.0.1:{}", local_addr.port());
 
-        assert_ok!(tcp_check_liveness(addr.as_ref(), timeout));
+        assert_ok!(tcp_check_liveness(addr.as_ref(), timeout).await);
 
         // Testing domain names
-        assert_ok!(tcp_check_liveness("google.com:80", timeout));
+        assert_ok!(tcp_check_liveness("google.com:80", timeout).await);
+    }
+
+    #[tokio::test]
+    async fn test_check_error_kind() {
+        let timeout = Duration::from_secs(3);
+
+        let err = tcp_check_liveness("abc@127.0.0.1:433", timeout)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), "refused");
+
+        let err = tcp_check_liveness("abc@no port", timeout)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), "dns");
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = format!("127.0.0.1:{}", listener.local_addr().unwrap().port());
+        let check = tcp_check_liveness(&addr, timeout).await.unwrap();
+        assert!(check.latency < timeout);
+        assert_eq!(check.advertised_network, None);
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let attempts = std::sync::atomic::AtomicU32::new(0);
+        let result: Result<u32, &str> = retry(
+            2,
+            |_| true,
+            || async {
+                let n = attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                if n < 1 {
+                    Err("flaky")
+                } else {
+                    Ok(n)
+                }
+            },
+        )
+        .await;
+        assert_eq!(result, Ok(1));
+
+        attempts.store(0, std::sync::atomic::Ordering::SeqCst);
+        let result: Result<(), &str> = retry(
+            2,
+            |err| *err != "permanent",
+            || async {
+                attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Err("permanent")
+            },
+        )
+        .await;
+        assert_eq!(result, Err("permanent"));
+        assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        let result: Result<(), &str> = retry(0, |_| true, || async { Err("down") }).await;
+        assert_eq!(result, Err("down"));
+    }
+
+    #[tokio::test]
+    async fn test_host_limiter() {
+        let limiter = HostLimiter::new(1, Duration::ZERO);
+        let permit = limiter.acquire("seed.example.com").await;
+        // Other hosts are not limited.
+        let _other = limiter.acquire("peer.example.com").await;
+        let blocked = tokio::time::timeout(
+            Duration::from_millis(50),
+            limiter.acquire("SEED.example.com"),
+        )
+        .await;
+        assert!(blocked.is_err());
+        drop(permit);
+        let unblocked =
+            tokio::time::timeout(Duration::from_secs(1), limiter.acquire("seed.example.com")).await;
+        assert!(unblocked.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_host_limiter_interval() {
+        let interval = Duration::from_millis(100);
+        let limiter = HostLimiter::new(10, interval);
+        let started = Instant::now();
+        let _first = limiter.acquire("seed.example.com").await;
+        let _second = limiter.acquire("seed.example.com").await;
+        let _third = limiter.acquire("seed.example.com").await;
+        assert!(started.elapsed() >= interval * 2);
+
+        // Other hosts have their own schedule.
+        let started = Instant::now();
+        let _other = limiter.acquire("peer.example.com").await;
+        assert!(started.elapsed() < interval);
+    }
+
+    #[test]
+    fn test_hosts() {
+        assert_eq!(peer_host("abc@seed.example.com:26656"), "seed.example.com");
+        assert_eq!(peer_host("abc@[2001:db8::1]:26656"), "2001:db8::1");
+        assert_eq!(peer_host("seed.example.com"), "seed.example.com");
+        assert_eq!(
+            endpoint_host("https://rpc.example.com:443/path"),
+            "rpc.example.com"
+        );
+        assert_eq!(endpoint_host("grpc.example.com:9090"), "grpc.example.com");
+        assert_eq!(endpoint_host("grpc.example.com"), "grpc.example.com");
     }
 }
@@ -0,0 +1,435 @@
+// Implements just enough of the Tendermint/CometBFT p2p protocol to authenticate a peer and read
+// its NodeInfo. See https://github.com/cometbft/cometbft/blob/main/spec/p2p/legacy-docs/peer.md
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Tag};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use hkdf::Hkdf;
+use merlin::Transcript;
+use prost::Message;
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use x25519_dalek::{EphemeralSecret, PublicKey as EphemeralPublic};
+
+const DATA_LEN_SIZE: usize = 4;
+const DATA_MAX_SIZE: usize = 1024;
+const TOTAL_FRAME_SIZE: usize = DATA_MAX_SIZE + DATA_LEN_SIZE;
+const TAG_SIZE: usize = 16;
+const SEALED_FRAME_SIZE: usize = TOTAL_FRAME_SIZE + TAG_SIZE;
+
+const MAX_HANDSHAKE_MSG_SIZE: usize = 1024;
+const MAX_NODE_INFO_SIZE: usize = 10240;
+
+#[derive(Clone, PartialEq, Message)]
+struct BytesValue {
+    #[prost(bytes = "vec", tag = "1")]
+    value: Vec<u8>,
+}
+
+// The proto is a oneof, but node keys are always ed25519, so a plain field encodes identically.
+#[derive(Clone, PartialEq, Message)]
+struct PublicKey {
+    #[prost(bytes = "vec", tag = "1")]
+    ed25519: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct AuthSigMessage {
+    #[prost(message, optional, tag = "1")]
+    pub_key: Option<PublicKey>,
+    #[prost(bytes = "vec", tag = "2")]
+    sig: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ProtocolVersion {
+    #[prost(uint64, tag = "1")]
+    p2p: u64,
+    #[prost(uint64, tag = "2")]
+    block: u64,
+    #[prost(uint64, tag = "3")]
+    app: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct DefaultNodeInfo {
+    #[prost(message, optional, tag = "1")]
+    protocol_version: Option<ProtocolVersion>,
+    #[prost(string, tag = "2")]
+    default_node_id: String,
+    #[prost(string, tag = "3")]
+    listen_addr: String,
+    #[prost(string, tag = "4")]
+    network: String,
+    #[prost(string, tag = "5")]
+    version: String,
+    #[prost(bytes = "vec", tag = "6")]
+    channels: Vec<u8>,
+    #[prost(string, tag = "7")]
+    moniker: String,
+}
+
+/// What a peer advertises about itself after a successful handshake.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeInfo {
+    /// Derived from the authenticated public key, not from what the peer claims.
+    pub node_id: String,
+    /// The chain id, e.g. cosmoshub-4.
+    pub network: String,
+    pub moniker: String,
+    pub version: String,
+}
+
+/// Derives a node id: the hex encoded first 20 bytes of the SHA-256 hash of the ed25519 public key.
+pub fn node_id(pubkey: &VerifyingKey) -> String {
+    hex::encode(&Sha256::digest(pubkey.as_bytes())[..20])
+}
+
+/// Performs the SecretConnection handshake with a throwaway node key, then exchanges NodeInfo.
+pub fn handshake<S: Read + Write>(io: S) -> anyhow::Result<NodeInfo> {
+    handshake_as(io, &SigningKey::generate(&mut OsRng), "")
+}
+
+/// Performs the handshake as the given node on the given network. Useful to stub a remote peer.
+pub fn handshake_as<S: Read + Write>(
+    io: S,
+    local_key: &SigningKey,
+    network: &str,
+) -> anyhow::Result<NodeInfo> {
+    let local_info = DefaultNodeInfo {
+        protocol_version: Some(ProtocolVersion {
+            p2p: 8,
+            block: 11,
+            app: 0,
+        }),
+        default_node_id: node_id(&local_key.verifying_key()),
+        listen_addr: "tcp://0.0.0.0:26656".to_string(),
+        network: network.to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        channels: vec![],
+        moniker: env!("CARGO_PKG_NAME").to_string(),
+    };
+
+    let mut conn = SecretConnection::new(io, local_key)?;
+    exchange_node_info(&mut conn, &local_info)
+}
+
+fn exchange_node_info<S: Read + Write>(
+    conn: &mut SecretConnection<S>,
+    local_info: &DefaultNodeInfo,
+) -> anyhow::Result<NodeInfo> {
+    write_delimited(conn, local_info)?;
+    let remote_info: DefaultNodeInfo = read_delimited(conn, MAX_NODE_INFO_SIZE)?;
+
+    let node_id = conn.remote_node_id();
+    if !remote_info.default_node_id.eq_ignore_ascii_case(&node_id) {
+        anyhow::bail!(
+            "peer authenticated as {} but advertised node id {}",
+            node_id,
+            remote_info.default_node_id
+        );
+    }
+
+    Ok(NodeInfo {
+        node_id,
+        network: remote_info.network,
+        moniker: remote_info.moniker,
+        version: remote_info.version,
+    })
+}
+
+struct Cipher {
+    aead: ChaCha20Poly1305,
+    nonce: [u8; 12],
+}
+
+impl Cipher {
+    fn new(key: &[u8]) -> Cipher {
+        Cipher {
+            aead: ChaCha20Poly1305::new_from_slice(key).expect("key must be 32 bytes"),
+            nonce: [0; 12],
+        }
+    }
+
+    // The nonce is a little endian counter in the last 8 bytes, incremented after every frame.
+    fn next_nonce(&mut self) -> [u8; 12] {
+        let nonce = self.nonce;
+        let counter = u64::from_le_bytes(self.nonce[4..].try_into().unwrap());
+        self.nonce[4..].copy_from_slice(&counter.wrapping_add(1).to_le_bytes());
+        nonce
+    }
+}
+
+/// An authenticated, encrypted stream. Data is sent in fixed size sealed frames.
+pub struct SecretConnection<S> {
+    io: S,
+    remote_pubkey: Option<VerifyingKey>,
+    send: Cipher,
+    recv: Cipher,
+    recv_buf: Vec<u8>,
+}
+
+impl<S: Read + Write> SecretConnection<S> {
+    pub fn new(mut io: S, local_key: &SigningKey) -> anyhow::Result<SecretConnection<S>> {
+        let local_eph_secret = EphemeralSecret::random_from_rng(OsRng);
+        let local_eph_pub = EphemeralPublic::from(&local_eph_secret).to_bytes();
+
+        write_delimited(
+            &mut io,
+            &BytesValue {
+                value: local_eph_pub.to_vec(),
+            },
+        )?;
+        let remote: BytesValue = read_delimited(&mut io, MAX_HANDSHAKE_MSG_SIZE)?;
+        let remote_eph_pub: [u8; 32] = match remote.value.as_slice().try_into() {
+            Ok(key) => key,
+            Err(_) => anyhow::bail!("remote ephemeral key must be 32 bytes"),
+        };
+
+        let shared_secret = local_eph_secret.diffie_hellman(&EphemeralPublic::from(remote_eph_pub));
+        if !shared_secret.was_contributory() {
+            anyhow::bail!("remote ephemeral key is a low order point");
+        }
+
+        let (lo, hi) = if local_eph_pub < remote_eph_pub {
+            (local_eph_pub, remote_eph_pub)
+        } else {
+            (remote_eph_pub, local_eph_pub)
+        };
+
+        let mut transcript = Transcript::new(b"TENDERMINT_SECRET_CONNECTION_TRANSCRIPT_HASH");
+        transcript.append_message(b"EPHEMERAL_LOWER_PUBLIC_KEY", &lo);
+        transcript.append_message(b"EPHEMERAL_UPPER_PUBLIC_KEY", &hi);
+        transcript.append_message(b"DH_SECRET", shared_secret.as_bytes());
+
+        let mut key_material = [0u8; 96];
+        Hkdf::<Sha256>::new(None, shared_secret.as_bytes())
+            .expand(
+                b"TENDERMINT_SECRET_CONNECTION_KEY_AND_CHALLENGE_GEN",
+                &mut key_material,
+            )
+            .map_err(|err| anyhow::anyhow!("failed to derive secrets: {}", err))?;
+        let (recv_secret, send_secret) = if local_eph_pub == lo {
+            (&key_material[0..32], &key_material[32..64])
+        } else {
+            (&key_material[32..64], &key_material[0..32])
+        };
+
+        let mut challenge = [0u8; 32];
+        transcript.challenge_bytes(b"SECRET_CONNECTION_MAC", &mut challenge);
+
+        let mut conn = SecretConnection {
+            io,
+            remote_pubkey: None,
+            send: Cipher::new(send_secret),
+            recv: Cipher::new(recv_secret),
+            recv_buf: vec![],
+        };
+
+        let local_sig = AuthSigMessage {
+            pub_key: Some(PublicKey {
+                ed25519: local_key.verifying_key().to_bytes().to_vec(),
+            }),
+            sig: local_key.sign(&challenge).to_bytes().to_vec(),
+        };
+        write_delimited(&mut conn, &local_sig)?;
+        let remote_sig: AuthSigMessage = read_delimited(&mut conn, MAX_HANDSHAKE_MSG_SIZE)?;
+
+        let remote_pubkey = match remote_sig.pub_key {
+            Some(key) => match key.ed25519.as_slice().try_into() {
+                Ok(bytes) => VerifyingKey::from_bytes(&bytes)?,
+                Err(_) => anyhow::bail!("remote public key must be a 32 byte ed25519 key"),
+            },
+            None => anyhow::bail!("remote did not send a public key"),
+        };
+        let sig = Signature::from_slice(&remote_sig.sig)?;
+        remote_pubkey.verify_strict(&challenge, &sig)?;
+
+        conn.remote_pubkey = Some(remote_pubkey);
+        Ok(conn)
+    }
+
+    pub fn remote_node_id(&self) -> String {
+        self.remote_pubkey.as_ref().map(node_id).unwrap_or_default()
+    }
+}
+
+impl<S: Read + Write> Write for SecretConnection<S> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let chunk = &buf[..buf.len().min(DATA_MAX_SIZE)];
+
+        let mut frame = [0u8; SEALED_FRAME_SIZE];
+        frame[..DATA_LEN_SIZE].copy_from_slice(&(chunk.len() as u32).to_le_bytes());
+        frame[DATA_LEN_SIZE..DATA_LEN_SIZE + chunk.len()].copy_from_slice(chunk);
+
+        let nonce = self.send.next_nonce();
+        let tag = self
+            .send
+            .aead
+            .encrypt_in_place_detached(&nonce.into(), b"", &mut frame[..TOTAL_FRAME_SIZE])
+            .map_err(|_| std::io::Error::other("failed to encrypt frame"))?;
+        frame[TOTAL_FRAME_SIZE..].copy_from_slice(&tag);
+
+        self.io.write_all(&frame)?;
+        Ok(chunk.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.io.flush()
+    }
+}
+
+impl<S: Read + Write> Read for SecretConnection<S> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.recv_buf.is_empty() {
+            let mut frame = [0u8; SEALED_FRAME_SIZE];
+            self.io.read_exact(&mut frame)?;
+
+            let nonce = self.recv.next_nonce();
+            let (data, tag) = frame.split_at_mut(TOTAL_FRAME_SIZE);
+            self.recv
+                .aead
+                .decrypt_in_place_detached(&nonce.into(), b"", data, Tag::from_slice(tag))
+                .map_err(|_| std::io::Error::other("failed to decrypt frame"))?;
+
+            let len = u32::from_le_bytes(data[..DATA_LEN_SIZE].try_into().unwrap()) as usize;
+            if len > DATA_MAX_SIZE {
+                return Err(std::io::Error::other("frame length exceeds max size"));
+            }
+            self.recv_buf
+                .extend_from_slice(&data[DATA_LEN_SIZE..DATA_LEN_SIZE + len]);
+        }
+
+        let n = buf.len().min(self.recv_buf.len());
+        buf[..n].copy_from_slice(&self.recv_buf[..n]);
+        self.recv_buf.drain(..n);
+        Ok(n)
+    }
+}
+
+fn write_delimited<W: Write, M: Message>(w: &mut W, msg: &M) -> anyhow::Result<()> {
+    w.write_all(&msg.encode_length_delimited_to_vec())?;
+    w.flush()?;
+    Ok(())
+}
+
+fn read_delimited<R: Read, M: Message + Default>(r: &mut R, max_len: usize) -> anyhow::Result<M> {
+    // Messages are prefixed with their length as a uvarint.
+    let mut len: u64 = 0;
+    for i in 0..10 {
+        let mut byte = [0u8; 1];
+        r.read_exact(&mut byte)?;
+        len |= ((byte[0] & 0x7f) as u64) << (7 * i);
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+        if i == 9 {
+            anyhow::bail!("message length overflows a uvarint");
+        }
+    }
+
+    let len = len as usize;
+    if len > max_len {
+        anyhow::bail!("message length {} exceeds max {}", len, max_len);
+    }
+    let mut buf = vec![0u8; len];
+    r.read_exact(&mut buf)?;
+    Ok(M::decode(buf.as_slice())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixStream;
+
+    fn stub_node_info(key: &SigningKey, network: &str) -> DefaultNodeInfo {
+        DefaultNodeInfo {
+            protocol_version: Some(ProtocolVersion {
+                p2p: 8,
+                block: 11,
+                app: 0,
+            }),
+            default_node_id: node_id(&key.verifying_key()),
+            listen_addr: "tcp://0.0.0.0:26656".to_string(),
+            network: network.to_string(),
+            version: "0.34.27".to_string(),
+            channels: vec![0x40, 0x20],
+            moniker: "stub".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_node_id() {
+        let key = VerifyingKey::from_bytes(&[
+            215, 90, 152, 1, 130, 177, 10, 183, 213, 75, 254, 211, 201, 100, 7, 58, 14, 225, 114,
+            243, 218, 166, 35, 37, 175, 2, 26, 104, 247, 7, 81, 26,
+        ])
+        .unwrap();
+        let id = node_id(&key);
+        assert_eq!(id.len(), 40);
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(id, hex::encode(&Sha256::digest(key.as_bytes())[..20]));
+    }
+
+    #[test]
+    fn test_nonce_increments_little_endian() {
+        let mut cipher = Cipher::new(&[0u8; 32]);
+        assert_eq!(cipher.next_nonce(), [0; 12]);
+        assert_eq!(cipher.next_nonce(), [0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+        cipher.nonce[4] = 0xff;
+        cipher.next_nonce();
+        assert_eq!(cipher.nonce, [0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_handshake() {
+        let (local, remote) = UnixStream::pair().unwrap();
+        let remote_key = SigningKey::generate(&mut OsRng);
+        let expected_id = node_id(&remote_key.verifying_key());
+
+        let server = std::thread::spawn(move || {
+            let mut conn = SecretConnection::new(remote, &remote_key).unwrap();
+            let info =
+                exchange_node_info(&mut conn, &stub_node_info(&remote_key, "cosmoshub-4")).unwrap();
+            assert_eq!(info.network, "");
+
+            // Messages larger than a frame are split across frames.
+            let big = vec![7u8; DATA_MAX_SIZE * 2 + 10];
+            conn.write_all(&big).unwrap();
+        });
+
+        let local_key = SigningKey::generate(&mut OsRng);
+        let mut conn = SecretConnection::new(local, &local_key).unwrap();
+        assert_eq!(conn.remote_node_id(), expected_id);
+
+        let info = exchange_node_info(&mut conn, &stub_node_info(&local_key, "")).unwrap();
+        assert_eq!(info.node_id, expected_id);
+        assert_eq!(info.network, "cosmoshub-4");
+        assert_eq!(info.moniker, "stub");
+        assert_eq!(info.version, "0.34.27");
+
+        let mut big = vec![0u8; DATA_MAX_SIZE * 2 + 10];
+        conn.read_exact(&mut big).unwrap();
+        assert!(big.iter().all(|b| *b == 7));
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_handshake_rejects_mismatched_node_info() {
+        let (local, remote) = UnixStream::pair().unwrap();
+        let remote_key = SigningKey::generate(&mut OsRng);
+        let imposter = SigningKey::generate(&mut OsRng);
+
+        let server = std::thread::spawn(move || {
+            let mut conn = SecretConnection::new(remote, &remote_key).unwrap();
+            write_delimited(&mut conn, &stub_node_info(&imposter, "cosmoshub-4")).unwrap();
+            let _: DefaultNodeInfo = read_delimited(&mut conn, MAX_NODE_INFO_SIZE).unwrap();
+        });
+
+        let err = handshake(local).unwrap_err();
+        assert!(err.to_string().contains("advertised node id"), "{}", err);
+
+        server.join().unwrap();
+    }
+}
@@ -1,20 +1,37 @@
+use crate::api::cache::CachePolicy;
+use crate::api::response_cache::ResponseCache;
+use crate::db::endpoint::EndpointKind;
 use crate::db::peer::PeerType;
 use axum::Router;
 use clap::{Parser, Subcommand};
+use futures::future;
+use futures::stream::{self, StreamExt};
 use sqlx::postgres::{PgPool, PgPoolOptions};
 use sqlx::Acquire;
 use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
 use tempfile::TempDir;
-use tokio::sync::Semaphore;
+use tokio::sync::watch;
 use tracing_subscriber::filter::{EnvFilter, LevelFilter};
 
+// Peer or endpoint results written per round trip.
+const LIVENESS_BATCH_SIZE: usize = 200;
+
+mod addrbook;
 mod api;
+mod crawl;
 mod db;
+mod denom;
+mod export;
 mod hydrate;
 mod liveness;
+mod metrics;
+mod node_config;
+mod registry;
+mod validation;
 mod web;
+mod worker;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -49,6 +66,47 @@ enum Sub {
             default_value = "30"
         )]
         pg_timeout_sec: u64,
+
+        #[arg(
+            long,
+            help = "Cache-Control header for API responses",
+            default_value = "public, max-age=60"
+        )]
+        cache_control: String,
+
+        #[arg(
+            long,
+            help = "Cache-Control header for one route, as ROUTE=POLICY, e.g. /v1/:network/:chain_name/peers=public, max-age=30. Repeatable"
+        )]
+        route_cache_control: Vec<String>,
+
+        #[arg(
+            long,
+            help = "Max number of responses to cache in memory. 0 disables the cache",
+            default_value = "1000"
+        )]
+        response_cache_size: usize,
+
+        #[arg(
+            long,
+            help = "Seconds a cached response is kept if no update clears it first",
+            default_value = "600"
+        )]
+        response_cache_ttl_sec: u64,
+
+        #[arg(
+            long,
+            help = "/readyz fails if the last successful hydrate finished more than this many seconds ago",
+            default_value = "86400"
+        )]
+        ready_max_chain_age_sec: u64,
+
+        #[arg(
+            long,
+            help = "/readyz fails if no peer liveness was checked within this many seconds",
+            default_value = "1800"
+        )]
+        ready_max_liveness_age_sec: u64,
     },
 
     #[command(about = "Download data from Chain Registry and store in database")]
@@ -66,16 +124,297 @@ enum Sub {
         #[arg(long, help = "Path to dir for git clone", required = false)]
         path: Option<String>,
 
+        #[arg(
+            long,
+            help = "Read the Chain Registry from dir:/path or tar:/path.tar.gz instead of cloning with git"
+        )]
+        source: Option<hydrate::Source>,
+
+        #[arg(
+            long,
+            help = "Commit hash of the source. Defaults to the commit in .git/HEAD or the archive"
+        )]
+        commit: Option<String>,
+
         #[arg(
             long,
             default_value = "false",
             help = "Keep the git clone after hydrating"
         )]
         keep_clone: bool,
+
+        #[arg(
+            long,
+            default_value = "false",
+            help = "Hydrate even if the commit has not changed since the last run"
+        )]
+        force: bool,
+
+        #[arg(
+            long,
+            default_value = "5",
+            value_parser = clap::value_parser!(u32).range(1..),
+            help = "Number of most recent commits to keep for historical queries"
+        )]
+        keep_commits: u32,
+
+        #[arg(
+            long,
+            help = "Do not publish a commit with more schema validation errors than this, or that cannot be validated"
+        )]
+        max_validation_errors: Option<usize>,
     },
 
     #[command(about = "Check liveness of peers and rpc/api endpoints")]
     Liveness {
+        #[arg(
+            long,
+            value_enum,
+            default_value = "tcp",
+            help = "How to check peers. Handshake also verifies node ids and records the advertised network"
+        )]
+        check_mode: liveness::CheckMode,
+
+        #[arg(
+            long,
+            help = "Max number of liveness checks in flight",
+            default_value = "100",
+            value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+        )]
+        concurrency: usize,
+
+        #[arg(
+            long,
+            help = "Timeout in seconds for each liveness check attempt",
+            default_value = "5",
+            value_parser = clap::value_parser!(u64).range(1..)
+        )]
+        timeout_sec: u64,
+
+        #[arg(
+            long,
+            help = "Times to retry a failed liveness check",
+            default_value = "1",
+            value_parser = clap::value_parser!(u32).range(..=10)
+        )]
+        retries: u32,
+
+        #[arg(
+            long,
+            help = "Max number of liveness checks in flight against one host",
+            default_value = "4",
+            value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+        )]
+        per_host_limit: usize,
+
+        #[arg(
+            long,
+            help = "Min milliseconds between the starts of two liveness checks against one host",
+            default_value = "250",
+            value_parser = clap::value_parser!(u64).range(..=60_000)
+        )]
+        per_host_interval_ms: u64,
+
+        #[arg(
+            long,
+            help = "Consecutive failed checks before a peer is marked dead",
+            default_value = "3",
+            value_parser = clap::value_parser!(i32).range(1..)
+        )]
+        dead_after: i32,
+
+        #[arg(
+            long,
+            help = "Consecutive successful checks before a dead peer is marked alive",
+            default_value = "2",
+            value_parser = clap::value_parser!(i32).range(1..)
+        )]
+        alive_after: i32,
+
+        #[arg(
+            long,
+            help = "Max number of postgres connections",
+            default_value = "25"
+        )]
+        pg_conns: u32,
+
+        #[arg(
+            long,
+            help = "Postgres connection timeout in seconds",
+            default_value = "30"
+        )]
+        pg_timeout_sec: u64,
+    },
+
+    #[command(
+        about = "Discover peers by crawling alive RPC /net_info endpoints, starting from the registry"
+    )]
+    Crawl {
+        #[arg(
+            long,
+            default_value = "20",
+            help = "Max number of /net_info requests per chain"
+        )]
+        max_rpcs: usize,
+
+        #[arg(
+            long,
+            default_value = "50",
+            help = "Max number of new peers to store per chain"
+        )]
+        max_peers: usize,
+
+        #[arg(
+            long,
+            default_value = "5",
+            help = "Timeout in seconds for each request and handshake",
+            value_parser = clap::value_parser!(u64).range(1..)
+        )]
+        timeout_sec: u64,
+
+        #[arg(
+            long,
+            default_value = "25",
+            help = "Max number of requests and handshakes in flight",
+            value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+        )]
+        concurrency: usize,
+
+        #[arg(
+            long,
+            default_value = "4",
+            help = "Max number of handshakes in flight against one host",
+            value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+        )]
+        per_host_limit: usize,
+
+        #[arg(
+            long,
+            default_value = "250",
+            help = "Min milliseconds between the starts of two handshakes against one host",
+            value_parser = clap::value_parser!(u64).range(..=60_000)
+        )]
+        per_host_interval_ms: u64,
+
+        #[arg(
+            long,
+            default_value = "10",
+            help = "Delete discovered peers after this many consecutive failed liveness checks",
+            value_parser = clap::value_parser!(i32).range(1..)
+        )]
+        prune_after: i32,
+
+        #[arg(
+            long,
+            help = "Postgres connection timeout in seconds",
+            default_value = "30"
+        )]
+        pg_timeout_sec: u64,
+    },
+
+    #[command(about = "Run hydrate and liveness checks periodically in one long running process")]
+    Worker {
+        #[arg(
+            long,
+            default_value = "https://github.com/cosmos/chain-registry",
+            help = "Chain Registry git URL"
+        )]
+        git_remote: String,
+
+        #[arg(long, default_value = "master", help = "Git branch or tag")]
+        git_ref: String,
+
+        #[arg(long, default_value = "300", help = "Seconds between hydrate runs")]
+        hydrate_interval_sec: u64,
+
+        #[arg(long, default_value = "300", help = "Seconds between liveness checks")]
+        liveness_interval_sec: u64,
+
+        #[arg(
+            long,
+            default_value = "30",
+            help = "Max random seconds added to each interval so replicas spread out"
+        )]
+        jitter_sec: u64,
+
+        #[arg(
+            long,
+            default_value = "5",
+            value_parser = clap::value_parser!(u32).range(1..),
+            help = "Number of most recent commits to keep for historical queries"
+        )]
+        keep_commits: u32,
+
+        #[arg(
+            long,
+            help = "Do not publish a commit with more schema validation errors than this, or that cannot be validated"
+        )]
+        max_validation_errors: Option<usize>,
+
+        #[arg(
+            long,
+            value_enum,
+            default_value = "tcp",
+            help = "How to check peers. Handshake also verifies node ids and records the advertised network"
+        )]
+        check_mode: liveness::CheckMode,
+
+        #[arg(
+            long,
+            help = "Max number of liveness checks in flight",
+            default_value = "100",
+            value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+        )]
+        concurrency: usize,
+
+        #[arg(
+            long,
+            help = "Timeout in seconds for each liveness check attempt",
+            default_value = "5",
+            value_parser = clap::value_parser!(u64).range(1..)
+        )]
+        timeout_sec: u64,
+
+        #[arg(
+            long,
+            help = "Times to retry a failed liveness check",
+            default_value = "1",
+            value_parser = clap::value_parser!(u32).range(..=10)
+        )]
+        retries: u32,
+
+        #[arg(
+            long,
+            help = "Max number of liveness checks in flight against one host",
+            default_value = "4",
+            value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+        )]
+        per_host_limit: usize,
+
+        #[arg(
+            long,
+            help = "Min milliseconds between the starts of two liveness checks against one host",
+            default_value = "250",
+            value_parser = clap::value_parser!(u64).range(..=60_000)
+        )]
+        per_host_interval_ms: u64,
+
+        #[arg(
+            long,
+            help = "Consecutive failed checks before a peer is marked dead",
+            default_value = "3",
+            value_parser = clap::value_parser!(i32).range(1..)
+        )]
+        dead_after: i32,
+
+        #[arg(
+            long,
+            help = "Consecutive successful checks before a dead peer is marked alive",
+            default_value = "2",
+            value_parser = clap::value_parser!(i32).range(1..)
+        )]
+        alive_after: i32,
+
         #[arg(
             long,
             help = "Max number of postgres connections",
@@ -111,18 +450,172 @@ async fn main() {
             port,
             pg_conns,
             pg_timeout_sec,
-        } => run_server(port, pg_conns, Duration::from_secs(pg_timeout_sec)).await,
+            cache_control,
+            route_cache_control,
+            response_cache_size,
+            response_cache_ttl_sec,
+            ready_max_chain_age_sec,
+            ready_max_liveness_age_sec,
+        } => {
+            let cache_policy = CachePolicy::new(&cache_control, &route_cache_control)
+                .expect("Invalid cache control");
+            let cache = ResponseCache::new(
+                response_cache_size,
+                Duration::from_secs(response_cache_ttl_sec),
+            );
+            run_server(
+                port,
+                pg_conns,
+                Duration::from_secs(pg_timeout_sec),
+                cache_policy,
+                cache,
+                Duration::from_secs(ready_max_chain_age_sec),
+                Duration::from_secs(ready_max_liveness_age_sec),
+            )
+            .await
+        }
         Sub::Hydrate {
             git_remote,
             git_ref,
             path,
+            source,
+            commit,
             keep_clone,
-        } => hydrate_chain_registry(git_remote, git_ref, path, keep_clone).await,
+            force,
+            keep_commits,
+            max_validation_errors,
+        } => {
+            let source = source.unwrap_or(hydrate::Source::Git {
+                remote: git_remote,
+                git_ref,
+            });
+            let opts = HydrateOpts {
+                path,
+                keep_clone,
+                force,
+                keep_commits,
+                max_validation_errors,
+            };
+            let pool = connect_pool(2, Duration::from_secs(30)).await;
+            let run_pool = pool.clone();
+            let run =
+                || async move { hydrate_chain_registry(&run_pool, source, commit, &opts).await };
+            if let Some(result) = worker::run_locked(&pool, "hydrate", db::lock::HYDRATE_LOCK, run)
+                .await
+                .expect("Hydrate failed")
+            {
+                result.expect("Hydrate failed");
+            }
+        }
         Sub::Liveness {
+            check_mode,
+            concurrency,
+            timeout_sec,
+            retries,
+            per_host_limit,
+            per_host_interval_ms,
+            dead_after,
+            alive_after,
+            pg_conns,
+            pg_timeout_sec,
+        } => {
+            let opts = liveness::Options {
+                check_mode,
+                concurrency,
+                timeout: Duration::from_secs(timeout_sec),
+                retries,
+                per_host_limit,
+                per_host_interval: Duration::from_millis(per_host_interval_ms),
+                thresholds: db::peer::Thresholds {
+                    failures: dead_after,
+                    successes: alive_after,
+                },
+            };
+            let pool = connect_pool(pg_conns, Duration::from_secs(pg_timeout_sec)).await;
+            let run_pool = pool.clone();
+            let run = || async move { check_liveness(&run_pool, &opts).await };
+            worker::run_locked(&pool, "liveness", db::lock::LIVENESS_LOCK, run)
+                .await
+                .expect("Liveness check failed");
+        }
+        Sub::Crawl {
+            max_rpcs,
+            max_peers,
+            timeout_sec,
+            concurrency,
+            per_host_limit,
+            per_host_interval_ms,
+            prune_after,
+            pg_timeout_sec,
+        } => {
+            let opts = crawl::Options {
+                max_rpcs,
+                max_peers,
+                timeout: Duration::from_secs(timeout_sec),
+                concurrency,
+                per_host_limit,
+                per_host_interval: Duration::from_millis(per_host_interval_ms),
+            };
+            let pool = connect_pool(2, Duration::from_secs(pg_timeout_sec)).await;
+            let run_pool = pool.clone();
+            let run = || async move { crawl_peers(&run_pool, &opts, prune_after).await };
+            if let Some(result) = worker::run_locked(&pool, "crawl", db::lock::CRAWL_LOCK, run)
+                .await
+                .expect("Crawl failed")
+            {
+                result.expect("Crawl failed");
+            }
+        }
+        Sub::Worker {
+            git_remote,
+            git_ref,
+            hydrate_interval_sec,
+            liveness_interval_sec,
+            jitter_sec,
+            keep_commits,
+            max_validation_errors,
+            check_mode,
+            concurrency,
+            timeout_sec,
+            retries,
+            per_host_limit,
+            per_host_interval_ms,
+            dead_after,
+            alive_after,
             pg_conns,
             pg_timeout_sec,
         } => {
-            check_liveness(pg_conns, Duration::from_secs(pg_timeout_sec)).await;
+            let liveness_opts = liveness::Options {
+                check_mode,
+                concurrency,
+                timeout: Duration::from_secs(timeout_sec),
+                retries,
+                per_host_limit,
+                per_host_interval: Duration::from_millis(per_host_interval_ms),
+                thresholds: db::peer::Thresholds {
+                    failures: dead_after,
+                    successes: alive_after,
+                },
+            };
+            let opts = HydrateOpts {
+                path: None,
+                keep_clone: false,
+                force: false,
+                keep_commits,
+                max_validation_errors,
+            };
+            let pool = connect_pool(pg_conns, Duration::from_secs(pg_timeout_sec)).await;
+            run_worker(
+                pool,
+                git_remote,
+                git_ref,
+                Duration::from_secs(hydrate_interval_sec),
+                Duration::from_secs(liveness_interval_sec),
+                Duration::from_secs(jitter_sec),
+                opts,
+                liveness_opts,
+            )
+            .await;
         }
     }
 }
@@ -147,13 +640,40 @@ async fn connect_pool(max_conns: u32, timeout: Duration) -> PgPool {
     pool
 }
 
-async fn run_server(port: u16, conns: u32, timeout: Duration) {
+#[allow(clippy::too_many_arguments)]
+async fn run_server(
+    port: u16,
+    conns: u32,
+    timeout: Duration,
+    cache_policy: CachePolicy,
+    cache: ResponseCache,
+    max_chain_age: Duration,
+    max_liveness_age: Duration,
+) {
     let pool = connect_pool(conns, timeout).await;
 
-    let api_routes = api::router::new();
+    tokio::spawn(api::response_cache::invalidate_on_update(
+        pool.clone(),
+        cache.clone(),
+    ));
+    let http_metrics = Arc::new(metrics::HttpMetrics::default());
+    let metrics_routes = api::metrics::router(api::metrics::MetricsState {
+        pool: pool.clone(),
+        max_connections: conns,
+        http: http_metrics.clone(),
+        cache: cache.clone(),
+    });
+    let health_routes = api::health::router(api::health::HealthState {
+        pool: pool.clone(),
+        max_chain_age,
+        max_liveness_age,
+    });
+    let api_routes = api::router::new(cache_policy, cache, http_metrics);
     let app = Router::new()
         .merge(api_routes)
         .with_state(pool)
+        .merge(metrics_routes)
+        .merge(health_routes)
         .merge(web::static_web());
 
     let addr = format!("0.0.0.0:{}", port);
@@ -164,90 +684,335 @@ async fn run_server(port: u16, conns: u32, timeout: Duration) {
         .unwrap();
 }
 
-async fn hydrate_chain_registry(
-    remote: String,
+#[allow(clippy::too_many_arguments)]
+async fn run_worker(
+    pool: PgPool,
+    git_remote: String,
     git_ref: String,
+    hydrate_interval: Duration,
+    liveness_interval: Duration,
+    jitter: Duration,
+    hydrate_opts: HydrateOpts,
+    liveness_opts: liveness::Options,
+) {
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+    let hydrate_job = worker::Job {
+        name: "hydrate",
+        lock_key: db::lock::HYDRATE_LOCK,
+        interval: hydrate_interval,
+        jitter,
+    };
+    let hydrate_pool = pool.clone();
+    let hydrate = tokio::spawn(worker::run_job(
+        pool.clone(),
+        hydrate_job,
+        shutdown_rx.clone(),
+        move || {
+            let pool = hydrate_pool.clone();
+            let source = hydrate::Source::Git {
+                remote: git_remote.clone(),
+                git_ref: git_ref.clone(),
+            };
+            let opts = hydrate_opts.clone();
+            async move {
+                if let Err(err) = hydrate_chain_registry(&pool, source, None, &opts).await {
+                    tracing::error!("Hydrate failed: {:?}", err);
+                }
+            }
+        },
+    ));
+
+    let liveness_job = worker::Job {
+        name: "liveness",
+        lock_key: db::lock::LIVENESS_LOCK,
+        interval: liveness_interval,
+        jitter,
+    };
+    let liveness_pool = pool.clone();
+    let liveness = tokio::spawn(worker::run_job(
+        pool.clone(),
+        liveness_job,
+        shutdown_rx,
+        move || {
+            let pool = liveness_pool.clone();
+            let opts = liveness_opts.clone();
+            async move { check_liveness(&pool, &opts).await }
+        },
+    ));
+
+    worker::shutdown_signal().await;
+    tracing::info!("Shutting down, waiting for running jobs to finish...");
+    let _ = shutdown_tx.send(true);
+
+    for handle in [hydrate, liveness] {
+        if let Err(err) = handle.await {
+            tracing::error!("Worker task failed: {:?}", err);
+        }
+    }
+    pool.close().await;
+    tracing::info!("Worker stopped.");
+}
+
+#[derive(Debug, Clone)]
+struct HydrateOpts {
+    // Where to clone or unpack. Defaults to a temp dir.
     path: Option<String>,
     keep_clone: bool,
+    force: bool,
+    keep_commits: u32,
+    max_validation_errors: Option<usize>,
+}
+
+async fn hydrate_chain_registry(
+    pool: &PgPool,
+    source: hydrate::Source,
+    commit: Option<String>,
+    opts: &HydrateOpts,
+) -> anyhow::Result<()> {
+    let started_at = chrono::Utc::now();
+    let result = hydrate_repo(pool, source, commit, opts).await;
+    let inserted = *result.as_ref().unwrap_or(&0);
+    record_job_run(pool, "hydrate", started_at, result.is_ok(), inserted).await;
+    result.map(|_| ())
+}
+
+async fn record_job_run(
+    pool: &PgPool,
+    job: &str,
+    started_at: chrono::DateTime<chrono::Utc>,
+    succeeded: bool,
+    items: usize,
 ) {
-    let clone_dir =
-        path.unwrap_or_else(|| TempDir::new().unwrap().path().to_str().unwrap().to_string());
-    tracing::info!("Cloning {} {} into {}...", remote, git_ref, clone_dir);
-    let repo = hydrate::shallow_clone(remote, git_ref, &clone_dir.clone().into())
-        .expect("shallow clone failed");
+    if let Err(err) = db::job::record_run(pool, job, started_at, succeeded, items as i64).await {
+        tracing::error!("Failed to record {} run: {:?}", job, err);
+    }
+}
+
+// Returns the number of chains inserted.
+async fn hydrate_repo(
+    pool: &PgPool,
+    source: hydrate::Source,
+    commit: Option<String>,
+    opts: &HydrateOpts,
+) -> anyhow::Result<usize> {
+    let force = opts.force;
+    let latest_commit = db::commit::latest_commit(pool).await?;
 
-    let pool = connect_pool(2, Duration::from_secs(30)).await;
-    let mut conn = pool.acquire().await.unwrap();
-    let mut tx = conn.begin().await.unwrap();
+    // Avoid cloning at all if the remote has not moved.
+    if let (hydrate::Source::Git { remote, git_ref }, false) = (&source, force) {
+        let (remote, git_ref) = (remote.clone(), git_ref.clone());
+        match tokio::task::spawn_blocking(move || hydrate::remote_commit(&remote, &git_ref)).await?
+        {
+            Ok(remote_commit) if Some(&remote_commit) == latest_commit.as_ref() => {
+                tracing::info!("Commit {} already hydrated, skipping", remote_commit);
+                return Ok(0);
+            }
+            Ok(_) => {}
+            Err(err) => tracing::warn!("Failed to get remote commit, cloning anyway: {:?}", err),
+        }
+    }
+
+    let clone_dir = opts
+        .path
+        .clone()
+        .unwrap_or_else(|| TempDir::new().unwrap().path().to_str().unwrap().to_string());
+    match &source {
+        hydrate::Source::Git { remote, git_ref } => {
+            tracing::info!("Cloning {} {} into {}...", remote, git_ref, clone_dir)
+        }
+        hydrate::Source::Dir(dir) => tracing::info!("Reading {}...", dir.display()),
+        hydrate::Source::Tar(archive) => {
+            tracing::info!("Unpacking {} into {}...", archive.display(), clone_dir)
+        }
+    }
+    // Only a directory this hydrate creates is removed afterwards. Directory sources are read in
+    // place, and an existing --path may hold the user's own files.
+    let created_dir =
+        !matches!(source, hydrate::Source::Dir(_)) && !Path::new(clone_dir.as_str()).exists();
+    let dest: std::path::PathBuf = clone_dir.clone().into();
+    let repo = tokio::task::spawn_blocking(move || hydrate::load(source, &dest, commit)).await??;
 
-    // chain ids mutable array of i64
+    let result = if !force && Some(&repo.commit) == latest_commit.as_ref() {
+        tracing::info!("Commit {} already hydrated, skipping", repo.commit);
+        Ok(0)
+    } else {
+        save_repo(pool, repo, opts.keep_commits, opts.max_validation_errors).await
+    };
+
+    let path = Path::new(clone_dir.as_str());
+    if created_dir && !opts.keep_clone && path.exists() {
+        match std::fs::remove_dir_all(path) {
+            Ok(_) => {
+                tracing::info!("Removed clone dir {}", clone_dir)
+            }
+            Err(err) => tracing::error!("Failed to remove clone dir: {:?}", err),
+        }
+    }
+
+    result
+}
+
+async fn save_repo(
+    pool: &PgPool,
+    repo: hydrate::ChainRegRepo,
+    keep_commits: u32,
+    max_validation_errors: Option<usize>,
+) -> anyhow::Result<usize> {
+    let mut conn = pool.acquire().await?;
+    let mut tx = conn.begin().await?;
+
+    // Ids of newly inserted chains. Unchanged chains keep their peers and endpoints.
     let mut chain_ids: Vec<i64> = Vec::new();
+    let mut unchanged = 0;
 
     tracing::info!("Inserting chains...");
 
-    // Insert mainnet chains
-    for chain in repo.mainnets {
-        match db::chain::insert_chain(
-            &mut tx,
-            chain.to_path_buf(),
-            "mainnet".to_string(),
-            &repo.commit,
-        )
-        .await
-        {
-            Ok(id) => {
-                chain_ids.push(id);
+    // With a max, a commit that cannot be validated must not be published as if it had no errors.
+    let validator = match validation::Validator::load(&repo.root) {
+        Ok(validator) => Some(validator),
+        Err(err) if max_validation_errors.is_some() => {
+            anyhow::bail!(
+                "failed to load schemas for commit {}: {:?}",
+                repo.commit,
+                err
+            )
+        }
+        Err(err) => {
+            tracing::error!("Failed to load schemas, skipping validation: {:?}", err);
+            None
+        }
+    };
+    let mut validation_errors = 0;
+
+    // Chains are mapped to the commit, so it must exist first.
+    db::commit::insert_commit(&mut tx, &repo.commit).await?;
+
+    for (network, chains) in [("mainnet", repo.mainnets), ("testnet", repo.testnets)] {
+        for chain in chains {
+            let id = match db::chain::insert_chain(
+                &mut tx,
+                chain.to_path_buf(),
+                network.to_string(),
+                &repo.commit,
+            )
+            .await
+            {
+                Ok(db::chain::SavedChain::Inserted(id)) => {
+                    chain_ids.push(id);
+                    id
+                }
+                Ok(db::chain::SavedChain::Unchanged(id)) => {
+                    unchanged += 1;
+                    id
+                }
+                Err(err) => {
+                    tracing::error!("Failed to save {} chain {:?}: {:?}", network, chain, err);
+                    continue;
+                }
+            };
+            if let Some(validator) = &validator {
+                match save_validation_errors(&mut tx, validator, id, &repo.commit, &chain).await {
+                    Ok(count) => validation_errors += count,
+                    Err(err) if max_validation_errors.is_some() => anyhow::bail!(
+                        "failed to validate {} chain {:?}: {:?}",
+                        network,
+                        chain,
+                        err
+                    ),
+                    Err(err) => {
+                        tracing::error!("Failed to validate chain {:?}: {:?}", chain, err)
+                    }
+                }
             }
-            Err(err) => tracing::error!("Failed to save mainnet chain {:?}: {:?}", chain, err),
         }
     }
 
-    // Insert testnet chains
-    for chain in repo.testnets {
-        match db::chain::insert_chain(
-            &mut tx,
-            chain.to_path_buf(),
-            "testnet".to_string(),
-            &repo.commit,
-        )
-        .await
-        {
-            Ok(id) => {
-                chain_ids.push(id);
+    tracing::info!(
+        "Inserted {} chains, {} unchanged, {} validation errors",
+        chain_ids.len(),
+        unchanged,
+        validation_errors
+    );
+    if let Some(max) = max_validation_errors {
+        if validation_errors > max {
+            // Dropping the transaction rolls back, so the previous commit stays published.
+            anyhow::bail!(
+                "commit {} has {} validation errors, more than the max of {}",
+                repo.commit,
+                validation_errors,
+                max
+            );
+        }
+    }
+
+    tracing::info!("Inserting IBC paths...");
+    for (network, paths) in [("mainnet", repo.mainnet_ibc), ("testnet", repo.testnet_ibc)] {
+        for path in paths {
+            if let Err(err) =
+                db::ibc::insert_ibc_path(&mut tx, path.clone(), network.to_string(), &repo.commit)
+                    .await
+            {
+                tracing::error!("Failed to save {} IBC path {:?}: {:?}", network, path, err);
             }
-            Err(err) => tracing::error!("Failed to save testnet chain {:?}: {:?}", chain, err),
         }
     }
 
-    tracing::info!("Inserting peers...");
+    tracing::info!("Inserting peers, endpoints, and assets...");
+    let inserted = chain_ids.len();
     for chain_id in chain_ids {
         insert_peers(&mut tx, chain_id, PeerType::Seed).await;
         insert_peers(&mut tx, chain_id, PeerType::Persistent).await;
+        // After the registry peers, so an address chain.json now lists stays a registry peer.
+        if let Err(err) = db::peer::carry_discovered_peers(&mut tx, chain_id).await {
+            tracing::error!(
+                "Failed to carry over discovered peers for chain {}: {:?}",
+                chain_id,
+                err
+            );
+        }
+        for kind in [EndpointKind::Rpc, EndpointKind::Rest, EndpointKind::Grpc] {
+            insert_endpoints(&mut tx, chain_id, kind).await;
+        }
+        if let Err(err) = db::asset::insert_assets(&mut tx, chain_id).await {
+            tracing::error!("Failed to insert assets for chain {}: {:?}", chain_id, err);
+        }
     }
 
-    let keep = 5;
+    let keep = keep_commits;
     match db::chain::truncate_old_chains(&mut tx, keep).await {
         Ok(_) => tracing::info!("Pruned old chains, kept {} most recent", keep),
         Err(err) => tracing::error!("Failed to prune chains: {:?}", err),
     }
-
-    tx.commit().await.unwrap_or_else(|err| {
-        tracing::error!("Failed to commit transaction: {:?}", err);
-    });
-
-    if keep_clone {
-        return;
+    if let Err(err) = db::ibc::truncate_old_ibc_paths(&mut tx).await {
+        tracing::error!("Failed to prune IBC paths: {:?}", err);
     }
 
-    let path = Path::new(clone_dir.as_str());
-    match std::fs::remove_dir_all(path) {
-        Ok(_) => {
-            tracing::info!("Removed clone dir {}", clone_dir)
-        }
-        Err(err) => tracing::error!("Failed to remove clone dir: {:?}", err),
+    // Sent once the transaction commits.
+    if let Err(err) = db::notify::notify_update(&mut tx, "hydrate").await {
+        tracing::error!("Failed to notify update: {:?}", err);
     }
 
+    tx.commit().await?;
+
     tracing::info!("Hydrate complete!");
+    Ok(inserted)
+}
+
+// Returns the number of errors, or an error if the chain could not be validated.
+async fn save_validation_errors(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    validator: &validation::Validator,
+    chain_id: i64,
+    commit: &str,
+    dir: &Path,
+) -> anyhow::Result<usize> {
+    let errors = validator.validate_dir(dir)?;
+    if let Err(err) = db::chain::update_validation_errors(&mut *tx, chain_id, commit, &errors).await
+    {
+        tracing::error!("Failed to save validation errors for {:?}: {:?}", dir, err);
+    }
+    Ok(errors.len())
 }
 
 async fn insert_peers(
@@ -255,7 +1020,7 @@ async fn insert_peers(
     chain_id: i64,
     peer_type: PeerType,
 ) {
-    let peers = match db::peer::find_peers(&mut *tx, chain_id, peer_type.clone()).await {
+    let peers = match db::peer::find_peers(&mut *tx, chain_id, peer_type).await {
         Ok(peers) => peers,
         Err(err) => {
             tracing::error!("Failed to find peers for chain {}: {:?}", chain_id, err);
@@ -264,63 +1029,531 @@ async fn insert_peers(
     };
 
     for peer in peers {
-        match db::peer::insert_peer(&mut *tx, chain_id, peer_type.clone(), peer.clone()).await {
+        match db::peer::insert_peer(&mut *tx, chain_id, peer_type, peer.clone()).await {
             Ok(_) => {}
             Err(err) => tracing::error!("Failed to insert peer {:?}: {:?}", peer, err),
         }
     }
 }
 
-async fn check_liveness(max_conns: u32, timeout: Duration) {
-    let pool = connect_pool(max_conns, timeout).await;
+async fn insert_endpoints(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    chain_id: i64,
+    kind: EndpointKind,
+) {
+    let endpoints = match db::endpoint::find_endpoints(&mut *tx, chain_id, kind).await {
+        Ok(endpoints) => endpoints,
+        Err(err) => {
+            tracing::error!("Failed to find endpoints for chain {}: {:?}", chain_id, err);
+            return;
+        }
+    };
+
+    for endpoint in endpoints {
+        match db::endpoint::insert_endpoint(&mut *tx, chain_id, kind, endpoint.clone()).await {
+            Ok(_) => {}
+            Err(err) => tracing::error!("Failed to insert endpoint {:?}: {:?}", endpoint, err),
+        }
+    }
+}
+
+async fn check_liveness(pool: &PgPool, opts: &liveness::Options) {
+    let started_at = chrono::Utc::now();
+    let result = check_all_liveness(pool, opts).await;
+    let checked = *result.as_ref().unwrap_or(&0);
+    if let Err(err) = &result {
+        tracing::error!("Liveness check failed: {:?}", err);
+    }
+    record_job_run(pool, "liveness", started_at, result.is_ok(), checked).await;
+}
 
-    let mut conn = pool
-        .acquire()
-        .await
-        .expect("Failed to acquire connection from pool");
+// Returns the number of peers and endpoints checked. Results are written in batches as checks
+// finish, so a slow host does not hold back the others' updates.
+async fn check_all_liveness(pool: &PgPool, opts: &liveness::Options) -> anyhow::Result<usize> {
+    let mut conn = pool.acquire().await?;
+    let peers = db::peer::all_recent_peers(&mut conn).await?;
+    let endpoints = db::endpoint::all_recent_endpoints(&mut conn).await?;
+    drop(conn);
 
-    let peers = db::peer::all_recent_peers(&mut conn)
-        .await
-        .expect("Failed to get recent peers");
+    let checker = liveness::Checker::new(opts.clone())?;
+    let checked = peers.len() + endpoints.len();
 
     tracing::info!("Checking liveness for {} peers...", peers.len());
+    let checker = &checker;
+    let mut batches = stream::iter(peers)
+        .map(|peer| async move {
+            tracing::info!("Checking peer liveness for {}", peer.address);
+            let check = checker.check_peer(&peer.address).await;
+            if let Err(err) = &check {
+                tracing::info!("Peer {} is not alive: {}", peer.address, err);
+            }
+            db::peer::Liveness {
+                peer_id: peer.id,
+                check,
+            }
+        })
+        .buffer_unordered(opts.concurrency)
+        .chunks(LIVENESS_BATCH_SIZE);
+    while let Some(batch) = batches.next().await {
+        if let Err(err) = db::peer::update_liveness(pool, &batch, opts.thresholds).await {
+            tracing::error!(
+                "Failed to update liveness for {} peers: {:?}",
+                batch.len(),
+                err
+            );
+        }
+    }
 
-    let pool = Arc::new(pool);
-    let sem = Arc::new(Semaphore::new(max_conns as usize));
-    let mut handles = vec![];
+    tracing::info!("Checking liveness for {} endpoints...", endpoints.len());
+    let mut batches = stream::iter(endpoints)
+        .map(|endpoint| async move {
+            let Some(kind) = EndpointKind::from_str(endpoint.kind.as_str()) else {
+                tracing::error!("Unknown endpoint kind {:?}", endpoint);
+                return None;
+            };
+            tracing::info!(
+                "Checking {} liveness for {}",
+                endpoint.kind,
+                endpoint.address
+            );
+            let alive = checker
+                .check_endpoint(kind, endpoint.address.as_str())
+                .await
+                .is_ok();
+            Some((endpoint.id, alive))
+        })
+        .buffer_unordered(opts.concurrency)
+        .filter_map(future::ready)
+        .chunks(LIVENESS_BATCH_SIZE);
+    while let Some(batch) = batches.next().await {
+        if let Err(err) = db::endpoint::update_liveness(pool, &batch).await {
+            tracing::error!(
+                "Failed to update liveness for {} endpoints: {:?}",
+                batch.len(),
+                err
+            );
+        }
+    }
 
-    for peer in peers {
-        let pool = std::sync::Arc::clone(&pool);
-        let permit = sem.clone().acquire_owned().await.unwrap();
-        handles.push(tokio::spawn(async move {
-            let mut conn = match pool.acquire().await {
-                Ok(conn) => conn,
-                Err(err) => {
-                    tracing::error!("Failed to acquire connection from pool: {:?}", err);
-                    drop(permit);
-                    return;
+    match db::peer::prune_checks(pool).await {
+        Ok(count) => tracing::info!("Pruned {} old peer checks", count),
+        Err(err) => tracing::error!("Failed to prune peer checks: {:?}", err),
+    }
+
+    if let Err(err) = db::notify::notify_update(pool, "liveness").await {
+        tracing::error!("Failed to notify update: {:?}", err);
+    }
+
+    tracing::info!("Liveness check complete.");
+    Ok(checked)
+}
+
+async fn crawl_peers(pool: &PgPool, opts: &crawl::Options, prune_after: i32) -> anyhow::Result<()> {
+    let started_at = chrono::Utc::now();
+    let result = crawl_all_peers(pool, opts, prune_after).await;
+    let inserted = *result.as_ref().unwrap_or(&0);
+    record_job_run(pool, "crawl", started_at, result.is_ok(), inserted).await;
+    result.map(|_| ())
+}
+
+// Returns the number of peers discovered. Hydrate carries discovered peers over to a chain's new
+// row when its content changes.
+async fn crawl_all_peers(
+    pool: &PgPool,
+    opts: &crawl::Options,
+    prune_after: i32,
+) -> anyhow::Result<usize> {
+    // Prune before crawling, so a dead peer that is still advertised gets a fresh start only if
+    // it passes the handshake again.
+    match db::peer::prune_discovered_peers(pool, prune_after).await {
+        Ok(count) => tracing::info!("Pruned {} dead discovered peers", count),
+        Err(err) => tracing::error!("Failed to prune discovered peers: {:?}", err),
+    }
+
+    let chains = db::peer::crawl_chains(pool).await?;
+    let client = reqwest::Client::builder().timeout(opts.timeout).build()?;
+    // Shared by every chain, so a host serving several chains is still limited.
+    let checker = opts.checker()?;
+
+    let mut inserted = 0;
+    for chain in chains {
+        let Some(chain_id) = chain.chain_id.as_deref() else {
+            tracing::warn!(
+                "Skipping {} {}, chain.json has no chain_id",
+                chain.network,
+                chain.chain_name
+            );
+            continue;
+        };
+        tracing::info!("Crawling {} {}...", chain.network, chain.chain_name);
+        for address in crawl::crawl(&client, &checker, &chain, opts).await {
+            match db::peer::insert_discovered_peer(pool, chain.id, &address, chain_id).await {
+                Ok(true) => inserted += 1,
+                Ok(false) => {}
+                Err(err) => tracing::error!("Failed to insert peer {}: {:?}", address, err),
+            }
+        }
+    }
+
+    if let Err(err) = db::notify::notify_update(pool, "crawl").await {
+        tracing::error!("Failed to notify update: {:?}", err);
+    }
+
+    tracing::info!("Crawl complete, discovered {} peers.", inserted);
+    Ok(inserted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args_ranges() {
+        let parse = |args: &[&str]| Args::try_parse_from([&["chain-registry"], args].concat());
+
+        for sub in ["hydrate", "worker"] {
+            assert!(parse(&[sub, "--keep-commits", "1"]).is_ok());
+            assert!(parse(&[sub, "--keep-commits", "0"]).is_err());
+            assert!(parse(&[sub, "--keep-commits", "-1"]).is_err());
+        }
+
+        // buffer_unordered(0) never polls anything, and a zero timeout fails every check.
+        for sub in ["liveness", "worker", "crawl"] {
+            for flag in ["--concurrency", "--timeout-sec"] {
+                assert!(parse(&[sub, flag, "1"]).is_ok(), "{} {}", sub, flag);
+                assert!(parse(&[sub, flag, "0"]).is_err(), "{} {}", sub, flag);
+            }
+        }
+        for sub in ["liveness", "worker", "crawl"] {
+            assert!(parse(&[sub, "--per-host-limit", "0"]).is_err());
+            assert!(parse(&[sub, "--per-host-interval-ms", "0"]).is_ok());
+            assert!(parse(&[sub, "--per-host-interval-ms", "60001"]).is_err());
+        }
+        for sub in ["liveness", "worker"] {
+            assert!(parse(&[sub, "--retries", "10"]).is_ok());
+            assert!(parse(&[sub, "--retries", "11"]).is_err());
+        }
+        assert!(parse(&["crawl", "--prune-after", "0"]).is_err());
+    }
+
+    fn test_opts() -> HydrateOpts {
+        HydrateOpts {
+            path: None,
+            keep_clone: false,
+            force: false,
+            keep_commits: 5,
+            max_validation_errors: None,
+        }
+    }
+
+    // Copies the fixture registry so tests can modify it.
+    fn copy_fixture(dest: &Path) -> std::io::Result<std::path::PathBuf> {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/hydrate/fixtures/registry");
+        let registry = dest.join("registry");
+        for path in [
+            "",
+            "cosmoshub",
+            "osmosis",
+            "testnets/cosmoshubtestnet",
+            "_IBC",
+        ] {
+            std::fs::create_dir_all(registry.join(path))?;
+            for file in std::fs::read_dir(fixture.join(path))? {
+                let file = file?;
+                if file.path().is_file() {
+                    std::fs::copy(file.path(), registry.join(path).join(file.file_name()))?;
                 }
-            };
+            }
+        }
+        Ok(registry)
+    }
 
-            let check_liveness = |addr: &str| -> anyhow::Result<()> {
-                tracing::info!("Checking peer liveness for {}", addr);
-                liveness::tcp_check_liveness(addr, Duration::from_secs(5))
-            };
+    #[sqlx::test]
+    async fn test_hydrate_from_dir(pool: PgPool) -> anyhow::Result<()> {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/hydrate/fixtures/registry");
+        let source = format!("dir:{}", fixture.display()).parse().unwrap();
+
+        hydrate_chain_registry(&pool, source, Some("abc123".to_string()), &test_opts()).await?;
+
+        let mut conn = pool.acquire().await?;
+        let chain = db::chain::find_chain(&mut conn, "mainnet", "cosmoshub").await?;
+        assert_eq!(chain.commit, "abc123");
+        assert_eq!(chain.chain_data["chain_id"], "cosmoshub-4");
+        assert_eq!(chain.asset_data["assets"][0]["base"], "uatom");
+        assert!(
+            db::chain::find_chain(&mut conn, "testnet", "cosmoshubtestnet")
+                .await
+                .is_ok()
+        );
+        assert!(db::chain::find_chain(&mut conn, "mainnet", "_non-cosmos")
+            .await
+            .is_err());
+
+        assert_eq!(db::peer::all_recent_peers(&mut conn).await?.len(), 4);
+        assert_eq!(
+            db::endpoint::all_recent_endpoints(&mut conn).await?.len(),
+            4
+        );
+        let path = db::ibc::find_ibc_path(&mut conn, "mainnet", "osmosis", "cosmoshub").await?;
+        assert_eq!(path.commit, "abc123");
 
-            match db::peer::update_liveness(&mut conn, &peer, check_liveness).await {
-                Ok(_) => {}
-                Err(err) => tracing::error!("Failed to update liveness for {:?}: {:?}", peer, err),
+        let filter = db::asset::AssetFilter {
+            network: "mainnet".to_string(),
+            trace_chain_name: Some("cosmoshub".to_string()),
+            ..Default::default()
+        };
+        let assets = db::asset::list_assets(&mut conn, &filter, None, 10).await?;
+        assert_eq!(assets.len(), 1);
+        assert_eq!(assets[0].chain_name, "osmosis");
+        assert_eq!(assets[0].data["symbol"], "ATOM");
+
+        // The fixture is read in place and must not be removed.
+        assert!(fixture.join("cosmoshub/chain.json").exists());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_hydrate_keeps_source_dir(pool: PgPool) -> anyhow::Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let registry = copy_fixture(temp_dir.path())?;
+        let source = hydrate::Source::Dir(registry.clone());
+
+        // --path pointing at the source directory must not remove it either.
+        let opts = HydrateOpts {
+            path: Some(registry.display().to_string()),
+            ..test_opts()
+        };
+        hydrate_chain_registry(&pool, source, Some("abc123".to_string()), &opts).await?;
+        assert!(registry.join("cosmoshub/chain.json").exists());
+
+        // Nor an existing --path that the hydrate did not create.
+        let existing = temp_dir.path().join("existing");
+        std::fs::create_dir(&existing)?;
+        std::fs::write(existing.join("notes.txt"), "mine")?;
+        let opts = HydrateOpts {
+            path: Some(existing.display().to_string()),
+            force: true,
+            ..test_opts()
+        };
+        let source = hydrate::Source::Dir(registry);
+        hydrate_chain_registry(&pool, source, Some("abc123".to_string()), &opts).await?;
+        assert!(existing.join("notes.txt").exists());
+
+        Ok(())
+    }
+
+    async fn chain_rows(pool: &PgPool) -> sqlx::Result<Vec<(i64, String, String)>> {
+        let rows = sqlx::query!("SELECT id, name, commit FROM chain ORDER BY id")
+            .fetch_all(pool)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.id, row.name, row.commit))
+            .collect())
+    }
+
+    #[sqlx::test]
+    async fn test_hydrate_incremental(pool: PgPool) -> anyhow::Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let registry = copy_fixture(temp_dir.path())?;
+        let source = hydrate::Source::Dir(registry.clone());
+        let hydrate = |commit: &str, force: bool| {
+            let (pool, source, commit) = (&pool, source.clone(), commit.to_string());
+            let opts = HydrateOpts {
+                force,
+                ..test_opts()
             };
-            drop(permit);
-        }));
+            async move { hydrate_chain_registry(pool, source, Some(commit), &opts).await }
+        };
+
+        hydrate("commit1", false).await?;
+        let first = chain_rows(&pool).await?;
+        assert_eq!(first.len(), 3);
+        let cosmoshub_v1 = db::chain::find_chain(&pool, "mainnet", "cosmoshub").await?;
+
+        // Same commit is skipped
+        std::fs::write(
+            registry.join("osmosis/chain.json"),
+            r#"{"chain_name":"osmosis","chain_id":"osmosis-2"}"#,
+        )?;
+        hydrate("commit1", false).await?;
+        assert_eq!(chain_rows(&pool).await?, first);
+
+        // Only the changed chain gets a new row. The others are reused as they are.
+        hydrate("commit2", false).await?;
+        let second = chain_rows(&pool).await?;
+        assert_eq!(second.len(), 4);
+        assert_eq!(second[..3], first[..]);
+        assert_eq!(second[3].1, "osmosis");
+        assert_eq!(second[3].2, "commit2");
+
+        let mut conn = pool.acquire().await?;
+        let osmosis = db::chain::find_chain(&mut conn, "mainnet", "osmosis").await?;
+        assert_eq!(osmosis.chain_data["chain_id"], "osmosis-2");
+        let cosmoshub = db::chain::find_chain(&mut conn, "mainnet", "cosmoshub").await?;
+        assert_eq!(cosmoshub.commit, "commit2");
+        // The version only changes with the content.
+        assert_eq!(cosmoshub.version(), cosmoshub_v1.version());
+        assert_ne!(cosmoshub.version(), cosmoshub.commit);
+        assert_ne!(osmosis.version(), cosmoshub.version());
+        // Osmosis no longer lists a seed.
+        assert_eq!(db::peer::all_recent_peers(&mut conn).await?.len(), 3);
+
+        let commits = db::commit::list_commits(&mut conn).await?;
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[1].commit, "commit1");
+        let osmosis =
+            db::chain::find_chain_at(&mut conn, "mainnet", "osmosis", &commits[1]).await?;
+        assert_eq!(osmosis.chain_data["chain_id"], "osmosis-1");
+        let cosmoshub =
+            db::chain::find_chain_at(&mut conn, "mainnet", "cosmoshub", &commits[1]).await?;
+        assert_eq!(cosmoshub.chain_data["chain_id"], "cosmoshub-4");
+        drop(conn);
+
+        // Force rewrites an unchanged commit, but unchanged chains are still reused.
+        hydrate("commit2", true).await?;
+        assert_eq!(chain_rows(&pool).await?, second);
+
+        Ok(())
     }
 
-    for handle in handles {
-        match handle.await {
-            Ok(_) => {}
-            Err(err) => tracing::error!("Task failed: {:?}", err),
-        }
+    // Checks every cosmoshub peer and returns each peer's (is_alive, failure_streak).
+    async fn check_cosmoshub_peers(
+        conn: &mut sqlx::PgConnection,
+        alive: bool,
+    ) -> anyhow::Result<Vec<(bool, i32)>> {
+        let thresholds = db::peer::Thresholds {
+            failures: 3,
+            successes: 2,
+        };
+        let peers = db::peer::recent_peers(&mut *conn, "cosmoshub", "mainnet").await?;
+        let results: Vec<_> = peers
+            .iter()
+            .map(|peer| db::peer::Liveness {
+                peer_id: peer.id,
+                check: if alive {
+                    Ok(liveness::PeerCheck::default())
+                } else {
+                    Err(liveness::CheckError::Connect(
+                        std::io::ErrorKind::TimedOut.into(),
+                    ))
+                },
+            })
+            .collect();
+        db::peer::update_liveness(&mut *conn, &results, thresholds).await?;
+        let peers = db::peer::recent_peers(&mut *conn, "cosmoshub", "mainnet").await?;
+        Ok(peers
+            .iter()
+            .map(|peer| (peer.is_alive, peer.failure_streak))
+            .collect())
     }
 
-    tracing::info!("Liveness check complete.");
+    #[sqlx::test]
+    async fn test_hydrate_keeps_peer_liveness(pool: PgPool) -> anyhow::Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let registry = copy_fixture(temp_dir.path())?;
+        let source = hydrate::Source::Dir(registry.clone());
+        hydrate_chain_registry(
+            &pool,
+            source.clone(),
+            Some("commit1".to_string()),
+            &test_opts(),
+        )
+        .await?;
+        let mut conn = pool.acquire().await?;
+        assert_eq!(
+            check_cosmoshub_peers(&mut conn, true).await?,
+            vec![(true, 0), (true, 0)]
+        );
+        assert_eq!(
+            check_cosmoshub_peers(&mut conn, false).await?,
+            vec![(true, 1), (true, 1)]
+        );
+        drop(conn);
+
+        // Changed content gets a new chain row with new peer rows.
+        std::fs::write(
+            registry.join("cosmoshub/assetlist.json"),
+            r#"{"chain_name":"cosmoshub","assets":[]}"#,
+        )?;
+        hydrate_chain_registry(&pool, source, Some("commit2".to_string()), &test_opts()).await?;
+        let mut conn = pool.acquire().await?;
+        let chain = db::chain::find_chain(&mut conn, "mainnet", "cosmoshub").await?;
+        assert_eq!(chain.commit, "commit2");
+
+        // The streaks carried over, so one more failure does not mark the peers dead.
+        assert_eq!(
+            check_cosmoshub_peers(&mut conn, false).await?,
+            vec![(true, 2), (true, 2)]
+        );
+        assert_eq!(
+            check_cosmoshub_peers(&mut conn, false).await?,
+            vec![(false, 3), (false, 3)]
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_hydrate_validation(pool: PgPool) -> anyhow::Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let registry = copy_fixture(temp_dir.path())?;
+        std::fs::write(
+            registry.join("osmosis/chain.json"),
+            r#"{"chain_name": "osmosis", "chain_id": 1}"#,
+        )?;
+        let source = hydrate::Source::Dir(registry);
+
+        let strict = HydrateOpts {
+            max_validation_errors: Some(2),
+            ..test_opts()
+        };
+        let result =
+            hydrate_chain_registry(&pool, source.clone(), Some("abc".to_string()), &strict).await;
+        assert!(result.is_err());
+        assert_eq!(db::commit::latest_commit(&pool).await?, None);
+
+        hydrate_chain_registry(&pool, source, Some("abc".to_string()), &test_opts()).await?;
+        let mut conn = pool.acquire().await?;
+        let osmosis = db::chain::find_chain(&mut conn, "mainnet", "osmosis").await?;
+        // Stored as is even though it does not match the model.
+        assert_eq!(osmosis.chain_data["chain_id"], 1);
+        let errors: Vec<validation::ValidationError> =
+            serde_json::from_value(osmosis.validation_errors)?;
+        // chain_id type plus missing status, network_type, and bech32_prefix
+        assert_eq!(errors.len(), 4, "{:?}", errors);
+        assert!(errors.iter().all(|e| e.file == "chain.json"));
+        let cosmoshub = db::chain::find_chain(&mut conn, "mainnet", "cosmoshub").await?;
+        assert_eq!(cosmoshub.validation_errors, serde_json::json!([]));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_hydrate_validation_unavailable(pool: PgPool) -> anyhow::Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let registry = copy_fixture(temp_dir.path())?;
+        std::fs::write(registry.join("chain.schema.json"), "{")?;
+        let source = hydrate::Source::Dir(registry);
+
+        // Schemas that fail to load are not counted as zero errors.
+        let strict = HydrateOpts {
+            max_validation_errors: Some(2),
+            ..test_opts()
+        };
+        let result =
+            hydrate_chain_registry(&pool, source.clone(), Some("abc".to_string()), &strict).await;
+        assert!(result.is_err());
+        assert_eq!(db::commit::latest_commit(&pool).await?, None);
+
+        // Without a max, validation is skipped.
+        hydrate_chain_registry(&pool, source, Some("abc".to_string()), &test_opts()).await?;
+        assert_eq!(
+            db::commit::latest_commit(&pool).await?,
+            Some("abc".to_string())
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,191 @@
+//! Metrics in the Prometheus text exposition format.
+//!
+//! See https://prometheus.io/docs/instrumenting/exposition_formats/.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Upper bounds in seconds of the request latency histogram.
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Writes metric families one sample at a time.
+#[derive(Debug, Default)]
+pub struct Encoder {
+    out: String,
+}
+
+impl Encoder {
+    /// Starts a family. kind is counter, gauge, or histogram.
+    pub fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.out, "# HELP {} {}", name, help);
+        let _ = writeln!(self.out, "# TYPE {} {}", name, kind);
+    }
+
+    pub fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.out.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(key, value)| format!("{}=\"{}\"", key, escape(value)))
+                .collect();
+            let _ = write!(self.out, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.out, " {}", value);
+    }
+
+    pub fn finish(self) -> String {
+        self.out
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+}
+
+#[derive(Debug, Default, Clone)]
+struct Histogram {
+    // Not cumulative; summed when encoded.
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+#[derive(Debug, Default)]
+struct Requests {
+    // (route, method, status)
+    counts: BTreeMap<(String, String, u16), u64>,
+    latency: BTreeMap<String, Histogram>,
+}
+
+/// Request counts and latency per route.
+#[derive(Debug, Default)]
+pub struct HttpMetrics {
+    requests: Mutex<Requests>,
+}
+
+impl HttpMetrics {
+    pub fn observe(&self, route: &str, method: &str, status: u16, elapsed: Duration) {
+        let mut requests = self.requests.lock().unwrap();
+        *requests
+            .counts
+            .entry((route.to_string(), method.to_string(), status))
+            .or_default() += 1;
+
+        let histogram = requests.latency.entry(route.to_string()).or_default();
+        let seconds = elapsed.as_secs_f64();
+        if let Some(i) = LATENCY_BUCKETS.iter().position(|&le| seconds <= le) {
+            histogram.buckets[i] += 1;
+        }
+        histogram.count += 1;
+        histogram.sum += seconds;
+    }
+
+    pub fn encode(&self, encoder: &mut Encoder) {
+        let requests = self.requests.lock().unwrap();
+
+        encoder.family(
+            "http_requests_total",
+            "counter",
+            "HTTP requests by route, method, and status.",
+        );
+        for ((route, method, status), count) in &requests.counts {
+            encoder.sample(
+                "http_requests_total",
+                &[
+                    ("route", route),
+                    ("method", method),
+                    ("status", &status.to_string()),
+                ],
+                *count as f64,
+            );
+        }
+
+        encoder.family(
+            "http_request_duration_seconds",
+            "histogram",
+            "HTTP request latency by route.",
+        );
+        for (route, histogram) in &requests.latency {
+            let mut cumulative = 0;
+            for (le, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                cumulative += count;
+                encoder.sample(
+                    "http_request_duration_seconds_bucket",
+                    &[("route", route), ("le", &le.to_string())],
+                    cumulative as f64,
+                );
+            }
+            encoder.sample(
+                "http_request_duration_seconds_bucket",
+                &[("route", route), ("le", "+Inf")],
+                histogram.count as f64,
+            );
+            encoder.sample(
+                "http_request_duration_seconds_sum",
+                &[("route", route)],
+                histogram.sum,
+            );
+            encoder.sample(
+                "http_request_duration_seconds_count",
+                &[("route", route)],
+                histogram.count as f64,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encoder() {
+        let mut encoder = Encoder::default();
+        encoder.family("registry_chains", "gauge", "Chains at the latest commit.");
+        encoder.sample("registry_chains", &[("network", "mainnet")], 42.0);
+        encoder.sample("registry_chains", &[("network", "a\"b\\c\nd")], 0.5);
+        encoder.sample("registry_commit_age_seconds", &[], 60.0);
+
+        assert_eq!(
+            encoder.finish(),
+            r#"# HELP registry_chains Chains at the latest commit.
+# TYPE registry_chains gauge
+registry_chains{network="mainnet"} 42
+registry_chains{network="a\"b\\c\nd"} 0.5
+registry_commit_age_seconds 60
+"#
+        );
+    }
+
+    #[test]
+    fn test_http_metrics() {
+        let metrics = HttpMetrics::default();
+        let route = "/v1/:network/:chain_name";
+        metrics.observe(route, "GET", 200, Duration::from_millis(3));
+        metrics.observe(route, "GET", 200, Duration::from_millis(30));
+        metrics.observe(route, "GET", 404, Duration::from_secs(20));
+
+        let mut encoder = Encoder::default();
+        metrics.encode(&mut encoder);
+        let out = encoder.finish();
+
+        for line in [
+            r#"http_requests_total{route="/v1/:network/:chain_name",method="GET",status="200"} 2"#,
+            r#"http_requests_total{route="/v1/:network/:chain_name",method="GET",status="404"} 1"#,
+            r#"http_request_duration_seconds_bucket{route="/v1/:network/:chain_name",le="0.005"} 1"#,
+            r#"http_request_duration_seconds_bucket{route="/v1/:network/:chain_name",le="0.05"} 2"#,
+            r#"http_request_duration_seconds_bucket{route="/v1/:network/:chain_name",le="10"} 2"#,
+            r#"http_request_duration_seconds_bucket{route="/v1/:network/:chain_name",le="+Inf"} 3"#,
+            r#"http_request_duration_seconds_count{route="/v1/:network/:chain_name"} 3"#,
+        ] {
+            assert!(out.lines().any(|l| l == line), "missing {}", line);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Renders config.toml and app.toml snippets for bootstrapping a node.
+//!
+//! Only the settings the registry knows about are rendered, so the snippets are meant to be merged
+//! into the files a node's init command generates.
+
+use crate::registry::FeeToken;
+use std::fmt::Write;
+
+// CometBFT's light client needs a primary and at least one witness.
+const MIN_STATE_SYNC_SERVERS: usize = 2;
+
+#[derive(Debug, Clone)]
+pub struct Source<'a> {
+    pub network: &'a str,
+    pub chain_name: &'a str,
+    pub commit: &'a str,
+}
+
+impl Source<'_> {
+    fn header(&self, file: &str) -> String {
+        format!(
+            "# Snippet for {} of {}/{}, from chain registry commit {}.\n",
+            file, self.network, self.chain_name, self.commit
+        )
+    }
+}
+
+/// The [p2p] and [statesync] sections of config.toml. State sync stays disabled because
+/// trust_height and trust_hash must come from a recent block.
+pub fn config_toml(
+    source: &Source,
+    seeds: &[String],
+    persistent_peers: &[String],
+    rpc_servers: &[String],
+) -> String {
+    let mut out = source.header("config.toml");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "[p2p]");
+    let _ = writeln!(out, "seeds = {}", quote(&seeds.join(",")));
+    let _ = writeln!(
+        out,
+        "persistent_peers = {}",
+        quote(&persistent_peers.join(","))
+    );
+    let _ = writeln!(out);
+    let _ = writeln!(out, "[statesync]");
+    let _ = writeln!(
+        out,
+        "# Set trust_height and trust_hash from a recent block, then set enable to true."
+    );
+    if rpc_servers.len() < MIN_STATE_SYNC_SERVERS {
+        let _ = writeln!(
+            out,
+            "# State sync needs at least {} RPC servers, but only {} are live.",
+            MIN_STATE_SYNC_SERVERS,
+            rpc_servers.len()
+        );
+    }
+    let _ = writeln!(out, "enable = false");
+    let _ = writeln!(out, "rpc_servers = {}", quote(&rpc_servers.join(",")));
+    let _ = writeln!(out, "trust_height = 0");
+    let _ = writeln!(out, "trust_hash = \"\"");
+    out
+}
+
+/// minimum-gas-prices of app.toml.
+pub fn app_toml(source: &Source, fee_tokens: &[FeeToken]) -> String {
+    let mut out = source.header("app.toml");
+    let _ = writeln!(out);
+    let prices = minimum_gas_prices(fee_tokens);
+    if prices.is_empty() {
+        let _ = writeln!(out, "# chain.json does not list gas prices.");
+    }
+    let _ = writeln!(out, "minimum-gas-prices = {}", quote(&prices));
+    out
+}
+
+/// Each fee token's fixed_min_gas_price, or its low_gas_price if there is no fixed minimum, e.g.
+/// 0.0025uatom,0.001ibc/27394FB0.
+pub fn minimum_gas_prices(fee_tokens: &[FeeToken]) -> String {
+    fee_tokens
+        .iter()
+        .filter_map(|token| {
+            let price = token
+                .fixed_min_gas_price
+                .as_ref()
+                .or(token.low_gas_price.as_ref())?
+                .as_f64()?;
+            // f64's Display never uses an exponent, which the SDK cannot parse.
+            Some(format!("{}{}", price, token.denom))
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// A TOML basic string.
+fn quote(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04X}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source() -> Source<'static> {
+        Source {
+            network: "mainnet",
+            chain_name: "cosmoshub",
+            commit: "abc123",
+        }
+    }
+
+    fn fee_token(value: serde_json::Value) -> FeeToken {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_config_toml() {
+        let out = config_toml(
+            &source(),
+            &["a@seed1:26656".to_string(), "b@seed2:26656".to_string()],
+            &[],
+            &["https://rpc1:443".to_string()],
+        );
+        assert_eq!(
+            out,
+            r#"# Snippet for config.toml of mainnet/cosmoshub, from chain registry commit abc123.
+
+[p2p]
+seeds = "a@seed1:26656,b@seed2:26656"
+persistent_peers = ""
+
+[statesync]
+# Set trust_height and trust_hash from a recent block, then set enable to true.
+# State sync needs at least 2 RPC servers, but only 1 are live.
+enable = false
+rpc_servers = "https://rpc1:443"
+trust_height = 0
+trust_hash = ""
+"#
+        );
+
+        let rpcs = ["https://rpc1:443".to_string(), "https://rpc2".to_string()];
+        let out = config_toml(&source(), &[], &[], &rpcs);
+        assert!(!out.contains("at least"));
+        assert!(out.contains("rpc_servers = \"https://rpc1:443,https://rpc2\"\n"));
+    }
+
+    #[test]
+    fn test_app_toml() {
+        let tokens = vec![
+            fee_token(
+                serde_json::json!({"denom": "uatom", "fixed_min_gas_price": 0.0025, "low_gas_price": 0.01}),
+            ),
+            fee_token(serde_json::json!({"denom": "ibc/ABC", "low_gas_price": 1e-7})),
+            fee_token(serde_json::json!({"denom": "uosmo"})),
+        ];
+        assert_eq!(minimum_gas_prices(&tokens), "0.0025uatom,0.0000001ibc/ABC");
+        assert_eq!(
+            app_toml(&source(), &tokens),
+            "# Snippet for app.toml of mainnet/cosmoshub, from chain registry commit abc123.\n\nminimum-gas-prices = \"0.0025uatom,0.0000001ibc/ABC\"\n"
+        );
+        assert!(app_toml(&source(), &[]).contains("does not list gas prices"));
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote(r#"a"b\c"#), r#""a\"b\\c""#);
+        assert_eq!(quote("a\nb\u{1}"), r#""a\nb\u0001""#);
+    }
+}
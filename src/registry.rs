@@ -0,0 +1,324 @@
+//! Typed models for the chain registry's chain.json and assetlist.json.
+//!
+//! The registry adds fields often, so every struct keeps fields it does not know about in `extra`
+//! and writes them back out unchanged. Only chain_name and chain_id are required; everything else
+//! is optional because older chains omit it.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+use utoipa::ToSchema;
+
+/// Fields this API does not model yet.
+pub type Extra = Map<String, Value>;
+
+/// A chain.json. See https://github.com/cosmos/chain-registry/blob/master/chain.schema.json.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ChainInfo {
+    #[schema(example = "cosmoshub")]
+    pub chain_name: String,
+    #[schema(example = "cosmoshub-4")]
+    pub chain_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pretty_name: Option<String>,
+    /// live, upcoming, or killed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// mainnet, testnet, or devnet
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bech32_prefix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub daemon_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_home: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slip44: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fees: Option<Fees>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub staking: Option<Staking>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peers: Option<Peers>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub apis: Option<Apis>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub explorers: Vec<Explorer>,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub extra: Extra,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Fees {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fee_tokens: Vec<FeeToken>,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub extra: Extra,
+}
+
+/// Gas prices keep the registry's number formatting, so 0 is not rewritten as 0.0.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FeeToken {
+    #[schema(example = "uatom")]
+    pub denom: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<f64>)]
+    pub fixed_min_gas_price: Option<Number>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<f64>)]
+    pub low_gas_price: Option<Number>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<f64>)]
+    pub average_gas_price: Option<Number>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<f64>)]
+    pub high_gas_price: Option<Number>,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub extra: Extra,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Staking {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub staking_tokens: Vec<StakingToken>,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub extra: Extra,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct StakingToken {
+    pub denom: String,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub extra: Extra,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Peers {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub seeds: Vec<PeerInfo>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub persistent_peers: Vec<PeerInfo>,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub extra: Extra,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PeerInfo {
+    /// Tendermint node id
+    pub id: String,
+    /// host:port
+    pub address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub extra: Extra,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Apis {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rpc: Vec<ApiEndpoint>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rest: Vec<ApiEndpoint>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub grpc: Vec<ApiEndpoint>,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub extra: Extra,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiEndpoint {
+    pub address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub extra: Extra,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Explorer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_page: Option<String>,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub extra: Extra,
+}
+
+/// An assetlist.json. See https://github.com/cosmos/chain-registry/blob/master/assetlist.schema.json.
+/// Chains without an assetlist.json have an empty one.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AssetList {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assets: Vec<Asset>,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub extra: Extra,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Asset {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub denom_units: Vec<DenomUnit>,
+    #[schema(example = "uatom")]
+    pub base: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coingecko_id: Option<String>,
+    /// e.g. sdk.coin, cw20, or ics20
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub type_asset: Option<String>,
+    /// Where the asset came from, such as over IBC. The last trace is the most recent hop.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub traces: Vec<AssetTrace>,
+    #[serde(rename = "logo_URIs", skip_serializing_if = "Option::is_none")]
+    pub logo_uris: Option<LogoUris>,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub extra: Extra,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AssetTrace {
+    /// e.g. ibc, ibc-cw20, or liquid-stake
+    #[serde(rename = "type")]
+    pub trace_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub counterparty: Option<TraceCounterparty>,
+    /// The receiving side of an IBC trace.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain: Option<TraceChain>,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub extra: Extra,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TraceCounterparty {
+    #[schema(example = "cosmoshub")]
+    pub chain_name: String,
+    #[schema(example = "uatom")]
+    pub base_denom: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<String>,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub extra: Extra,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DenomUnit {
+    pub denom: String,
+    pub exponent: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub extra: Extra,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct LogoUris {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub png: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub svg: Option<String>,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub extra: Extra,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TraceChain {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "channel-0")]
+    pub channel_id: Option<String>,
+    /// Denom trace path whose hash is the ibc/ denom, e.g. transfer/channel-0/uatom
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "transfer/channel-0/uatom")]
+    pub path: Option<String>,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub extra: Extra,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn fixture(path: &str) -> String {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/hydrate/fixtures/registry");
+        std::fs::read_to_string(root.join(path)).unwrap()
+    }
+
+    #[test]
+    fn test_chain_info_round_trip() {
+        let raw: Value = serde_json::from_str(&fixture("cosmoshub/chain.json")).unwrap();
+        let chain: ChainInfo = serde_json::from_value(raw.clone()).unwrap();
+
+        assert_eq!(chain.chain_id, "cosmoshub-4");
+        assert_eq!(chain.slip44, Some(118));
+        let fees = chain.fees.as_ref().unwrap();
+        assert_eq!(fees.fee_tokens[0].denom, "uatom");
+        assert_eq!(fees.fee_tokens[0].fixed_min_gas_price, Some(0.into()));
+        let apis = chain.apis.as_ref().unwrap();
+        assert_eq!(apis.grpc[0].provider.as_deref(), Some("Notional"));
+        // $schema is not modeled.
+        assert_eq!(chain.extra["$schema"], "../chain.schema.json");
+
+        assert_eq!(serde_json::to_value(&chain).unwrap(), raw);
+    }
+
+    #[test]
+    fn test_asset_list_round_trip() {
+        let raw: Value = serde_json::from_str(&fixture("cosmoshub/assetlist.json")).unwrap();
+        let list: AssetList = serde_json::from_value(raw.clone()).unwrap();
+
+        let asset = &list.assets[0];
+        assert_eq!(asset.base, "uatom");
+        assert_eq!(asset.denom_units[1].denom, "atom");
+        assert_eq!(asset.denom_units[1].exponent, 6);
+
+        assert_eq!(serde_json::to_value(&list).unwrap(), raw);
+
+        let empty: AssetList = serde_json::from_str("{}").unwrap();
+        assert_eq!(empty, AssetList::default());
+        assert_eq!(serde_json::to_string(&empty).unwrap(), "{}");
+    }
+
+    #[test]
+    fn test_keeps_unknown_fields() {
+        let raw = serde_json::json!({
+            "chain_name": "juno",
+            "chain_id": "juno-1",
+            "codebase": {"git_repo": "https://github.com/CosmosContracts/juno"},
+            "apis": {"rpc": [{"address": "https://rpc.juno.example", "archive": true}], "evm-http-jsonrpc": []},
+        });
+        let chain: ChainInfo = serde_json::from_value(raw.clone()).unwrap();
+
+        assert_eq!(chain.apis.as_ref().unwrap().rpc[0].extra["archive"], true);
+        assert_eq!(serde_json::to_value(&chain).unwrap(), raw);
+    }
+}
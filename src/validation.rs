@@ -0,0 +1,136 @@
+use jsonschema::{Draft, JSONSchema};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use utoipa::ToSchema;
+
+/// A file that does not match its registry schema.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ValidationError {
+    /// chain.json or assetlist.json
+    pub file: String,
+    /// JSON pointer to the invalid value
+    pub path: String,
+    pub message: String,
+}
+
+/// Validates chain files against the schemas at the root of the registry.
+pub struct Validator {
+    chain: Option<JSONSchema>,
+    assetlist: Option<JSONSchema>,
+}
+
+impl Validator {
+    /// Loads chain.schema.json and assetlist.schema.json from the registry root. A missing schema
+    /// skips validation for that file.
+    pub fn load(root: &Path) -> anyhow::Result<Validator> {
+        Ok(Validator {
+            chain: load_schema(&root.join("chain.schema.json"))?,
+            assetlist: load_schema(&root.join("assetlist.schema.json"))?,
+        })
+    }
+
+    /// Validates the chain.json and assetlist.json in a chain's directory. Chains do not need an
+    /// assetlist.json.
+    pub fn validate_dir(&self, dir: &Path) -> anyhow::Result<Vec<ValidationError>> {
+        let mut errors = validate_file(self.chain.as_ref(), &dir.join("chain.json"))?;
+        let assetlist = dir.join("assetlist.json");
+        if assetlist.exists() {
+            errors.extend(validate_file(self.assetlist.as_ref(), &assetlist)?);
+        }
+        Ok(errors)
+    }
+}
+
+fn load_schema(path: &Path) -> anyhow::Result<Option<JSONSchema>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let schema: serde_json::Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+    match JSONSchema::options()
+        .with_draft(Draft::Draft7)
+        .compile(&schema)
+    {
+        Ok(compiled) => Ok(Some(compiled)),
+        Err(err) => anyhow::bail!("invalid schema {}: {}", path.display(), err),
+    }
+}
+
+fn validate_file(schema: Option<&JSONSchema>, path: &Path) -> anyhow::Result<Vec<ValidationError>> {
+    let schema = match schema {
+        Some(schema) => schema,
+        None => return Ok(vec![]),
+    };
+    let file = path
+        .file_name()
+        .and_then(|f| f.to_str())
+        .unwrap_or_default()
+        .to_string();
+    let instance: serde_json::Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+    let errors = match schema.validate(&instance) {
+        Ok(_) => vec![],
+        Err(errors) => errors
+            .map(|err| ValidationError {
+                file: file.clone(),
+                path: err.instance_path.to_string(),
+                message: err.to_string(),
+            })
+            .collect(),
+    };
+    Ok(errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_validate_dir() {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/hydrate/fixtures/registry");
+        let validator = Validator::load(&fixture).unwrap();
+
+        let errors = validator.validate_dir(&fixture.join("cosmoshub")).unwrap();
+        assert_eq!(errors, vec![]);
+        let errors = validator
+            .validate_dir(&fixture.join("testnets/cosmoshubtestnet"))
+            .unwrap();
+        assert_eq!(errors, vec![]);
+
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("chain.json"),
+            r#"{"chain_name": "cosmoshub", "chain_id": 4, "status": "live", "network_type": "mainnet", "bech32_prefix": "cosmos"}"#,
+        )
+        .unwrap();
+        fs::write(temp_dir.path().join("assetlist.json"), r#"{"assets": {}}"#).unwrap();
+
+        let mut errors = validator.validate_dir(temp_dir.path()).unwrap();
+        errors.sort_by(|a, b| (&a.file, &a.path).cmp(&(&b.file, &b.path)));
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+        assert_eq!(errors[0].file, "assetlist.json");
+        assert_eq!(errors[0].path, "");
+        assert!(errors[0].message.contains("chain_name"), "{:?}", errors[0]);
+        assert_eq!(errors[1].file, "assetlist.json");
+        assert_eq!(errors[1].path, "/assets");
+        assert_eq!(errors[2].file, "chain.json");
+        assert_eq!(errors[2].path, "/chain_id");
+    }
+
+    #[test]
+    fn test_missing_schemas() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("chain.json"), r#"{"anything": true}"#).unwrap();
+
+        let validator = Validator::load(temp_dir.path()).unwrap();
+        assert_eq!(validator.validate_dir(temp_dir.path()).unwrap(), vec![]);
+
+        fs::write(
+            temp_dir.path().join("chain.schema.json"),
+            r#"{"type": "not a type"}"#,
+        )
+        .unwrap();
+        assert!(Validator::load(temp_dir.path()).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use crate::db::lock;
+use rand::Rng;
+use sqlx::postgres::PgPool;
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// A job the worker runs periodically.
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub name: &'static str,
+    /// Advisory lock key so only one replica runs the job at a time.
+    pub lock_key: i64,
+    pub interval: Duration,
+    /// Each run is delayed by a random amount up to jitter so replicas don't wake up together.
+    pub jitter: Duration,
+}
+
+/// Runs the job every interval until shutdown is signaled. A run in progress is allowed to finish.
+/// If another process holds the job's lock, the run is skipped.
+pub async fn run_job<F, Fut>(pool: PgPool, job: Job, mut shutdown: watch::Receiver<bool>, run: F)
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let mut delay = jittered(Duration::ZERO, job.jitter);
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = shutdown.changed() => break,
+        }
+        if *shutdown.borrow() {
+            break;
+        }
+
+        if let Err(err) = run_locked(&pool, job.name, job.lock_key, &run).await {
+            tracing::error!("{} job failed: {:?}", job.name, err);
+        }
+        delay = jittered(job.interval, job.jitter);
+    }
+    tracing::info!("Stopped {} job", job.name);
+}
+
+/// Runs the job while holding its advisory lock, so a job started by hand does not race another
+/// process running it. Returns None without running the job if another process holds the lock.
+pub async fn run_locked<F, Fut>(
+    pool: &PgPool,
+    name: &str,
+    lock_key: i64,
+    run: F,
+) -> anyhow::Result<Option<Fut::Output>>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future + Send + 'static,
+    Fut::Output: Send + 'static,
+{
+    let mut conn = pool.acquire().await?;
+
+    if !lock::try_lock(&mut conn, lock_key).await? {
+        tracing::info!("Skipping {} job, another process is running it", name);
+        return Ok(None);
+    }
+
+    tracing::info!("Starting {} job", name);
+    // Spawned so a panicking job cannot skip the unlock below.
+    let result = tokio::spawn(run()).await;
+
+    if let Err(err) = lock::unlock(&mut conn, lock_key).await {
+        tracing::error!("Failed to unlock {} job: {:?}", name, err);
+        // Closing the connection releases the lock.
+        let _ = conn.detach();
+    }
+
+    Ok(Some(result?))
+}
+
+fn jittered(interval: Duration, jitter: Duration) -> Duration {
+    if jitter.is_zero() {
+        return interval;
+    }
+    let millis = rand::thread_rng().gen_range(0..=jitter.as_millis() as u64);
+    interval + Duration::from_millis(millis)
+}
+
+/// Completes when the process receives SIGTERM or ctrl-c.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to install ctrl-c handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_jittered() {
+        let interval = Duration::from_secs(60);
+        assert_eq!(jittered(interval, Duration::ZERO), interval);
+
+        for _ in 0..100 {
+            let delay = jittered(interval, Duration::from_secs(5));
+            assert!(delay >= interval);
+            assert!(delay <= interval + Duration::from_secs(5));
+        }
+    }
+
+    fn counting_job(runs: &Arc<AtomicUsize>) -> impl Fn() -> std::future::Ready<()> + '_ {
+        move || {
+            runs.fetch_add(1, Ordering::SeqCst);
+            std::future::ready(())
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_run_job(pool: PgPool) -> sqlx::Result<()> {
+        let job = Job {
+            name: "test",
+            lock_key: lock::HYDRATE_LOCK,
+            interval: Duration::from_millis(10),
+            jitter: Duration::ZERO,
+        };
+        let runs = Arc::new(AtomicUsize::new(0));
+        let (tx, rx) = watch::channel(false);
+
+        let handle = {
+            let pool = pool.clone();
+            let job = job.clone();
+            let runs = runs.clone();
+            tokio::spawn(async move { run_job(pool, job, rx, counting_job(&runs)).await })
+        };
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        tx.send(true).unwrap();
+        handle.await.unwrap();
+
+        assert!(runs.load(Ordering::SeqCst) > 1);
+
+        // Skips runs while another session holds the lock.
+        let mut conn = pool.acquire().await?;
+        assert!(lock::try_lock(&mut conn, job.lock_key).await?);
+        let runs = Arc::new(AtomicUsize::new(0));
+        let ran = run_locked(&pool, job.name, job.lock_key, counting_job(&runs))
+            .await
+            .unwrap();
+        assert_eq!(ran, None);
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+
+        lock::unlock(&mut conn, job.lock_key).await?;
+        let ran = run_locked(&pool, job.name, job.lock_key, counting_job(&runs))
+            .await
+            .unwrap();
+        assert_eq!(ran, Some(()));
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        // The lock is released after the run, also when the job panics.
+        let ran = run_locked(&pool, job.name, job.lock_key, || async { panic!("boom") }).await;
+        assert!(ran.is_err());
+        assert!(lock::try_lock(&mut conn, job.lock_key).await?);
+
+        Ok(())
+    }
+}